@@ -14,6 +14,18 @@ use citadel_frontend::ir::irgen::HIRStream;
 // TODO: Remove these trait bounds?
 pub trait Target: Debug + Default + Copy + Clone {
     fn name(&self) -> &str;
+
+    /// Size and alignment of a pointer in bytes.
+    /// Used by the [data layout](crate::layout::DataLayout)
+    fn pointer_size(&self) -> u32 {
+        8
+    }
+
+    /// Size of the vector registers in bytes or 0 if the target has none.
+    /// Vectors of this size are lowered to SIMD instructions, all others are scalarized
+    fn vector_size(&self) -> u32 {
+        0
+    }
 }
 
 /// # Introduction
@@ -7,21 +7,22 @@
 
 use std::collections::{HashMap, HashSet};
 
-use citadel_frontend::{
-    ir::{
-        self, irgen::TypeTable, ArithOpExpr, BlockStmt, CallExpr, ExitStmt, FuncStmt, IRExpr,
-        IRStmt, JumpStmt, LabelStmt, ReturnStmt, StructInitExpr, Type, VarStmt, INT16_T,
-        INT32_T, INT64_T, INT8_T,
-    },
-    util::CompositeDataType,
+use citadel_frontend::ir::{
+    self, irgen::TypeTable, AddrOfExpr, AllocaExpr, ArithOpExpr, AssignStmt, BlockStmt, BranchStmt, CallExpr, CastExpr, CastKind, CmpOpExpr, CmpOperator,
+    DeclFuncStmt, ExitStmt, ExtractExpr, FuncStmt, IRExpr, IRStmt, InsertExpr, JumpStmt, LabelStmt, LoadExpr, NotExpr, ReturnStmt, ShuffleExpr,
+    SourceLoc, StoreStmt, StructInitExpr, Type, VarStmt, INT1_T,
 };
 
-use crate::asm::{
-    elements::{
-        AsmElement, DataSize, Declaration, Directive, DirectiveType, Instruction, Label, Literal,
-        Opcode, Operand, Register, Size, SizedLiteral, StdFunction,
+use crate::{
+    api::Target,
+    asm::{
+        elements::{
+            AsmElement, DataSize, DataValue, Declaration, Directive, DirectiveType, Instruction, Label,
+            Literal, MemAddr, Opcode, Operand, Register, Size, SizedLiteral, StdFunction,
+        },
+        utils::codegen as cutils,
     },
-    utils::codegen as cutils,
+    layout::{self, DataLayout},
 };
 
 pub const FUNCTION_ARG_REGISTERS_8: [Register; 6] = [
@@ -60,33 +61,149 @@ pub const FUNCTION_ARG_REGISTERS_64: [Register; 6] = [
     Register::R10,
 ];
 
-#[derive(Default)]
+/// Floats are passed in the first eight xmm registers, independently of the integer arguments
+pub const FUNCTION_FLOAT_ARG_REGISTERS: [Register; 8] = [
+    Register::Xmm0,
+    Register::Xmm1,
+    Register::Xmm2,
+    Register::Xmm3,
+    Register::Xmm4,
+    Register::Xmm5,
+    Register::Xmm6,
+    Register::Xmm7,
+];
+
 pub struct CodeGenerator<'c> {
     pub out: Vec<AsmElement>,
-    pub types: TypeTable<'c>,
+    pub types: &'c TypeTable<'c>,
+    pub layout: DataLayout<'c>,
+    /// Size of the target's vector registers in bytes, see [Target::vector_size]
+    pub vector_size: u32,
 
     // Literals
     /// Read only data section
     pub rodata: Vec<Declaration>,
     pub data: Vec<Declaration>,
+    /// Zero-initialized data section
+    pub bss: Vec<Declaration>,
     /// Literal constant index
     pub lc_index: usize,
+    /// Index of the labels that are generated for jumps within a single expression
+    pub label_index: usize,
+    /// Labels of the string literals in `.rodata` and their size including the padding,
+    /// so equal strings share one entry
+    pub strings: HashMap<(&'c [u8], u32), String>,
+    /// Labels of the float constants in `.rodata`, keyed by their bits and size
+    pub floats: HashMap<(u64, u8), String>,
 
     pub defined_functions: HashSet<StdFunction>,
-    pub symbol_table: HashMap<&'c str, i32>,
+    /// Stack position and type of every local variable of the current function
+    pub symbol_table: HashMap<&'c str, (i32, &'c Type<'c>)>,
+    /// Types of the variables at the top level, which are addressed by their label
+    pub globals: HashMap<&'c str, &'c Type<'c>>,
+    /// Return types of all functions
+    pub functions: HashMap<&'c str, Type<'c>>,
 
     pub stack_pointer: i32,
+
+    /// Whether `%line` directives are emitted for the source locations of the IR
+    pub line_directives: bool,
+    /// File and line of the last `%line` directive
+    pub cur_line: Option<(String, u32)>,
 }
 
 impl<'c> CodeGenerator<'c> {
-    pub fn new(types: TypeTable<'c>) -> Self {
+    pub fn new(types: &'c TypeTable<'c>, target: &impl Target) -> Self {
         Self {
+            out: Vec::new(),
             types,
-            ..Default::default()
+            layout: DataLayout::new(target, types),
+            vector_size: target.vector_size(),
+            rodata: Vec::new(),
+            data: Vec::new(),
+            bss: Vec::new(),
+            lc_index: 0,
+            label_index: 0,
+            strings: HashMap::new(),
+            floats: HashMap::new(),
+            defined_functions: HashSet::new(),
+            symbol_table: HashMap::new(),
+            globals: HashMap::new(),
+            functions: HashMap::new(),
+            stack_pointer: 0,
+            line_directives: false,
+            cur_line: None,
         }
     }
 
+    /// Registers the return types of all functions in the stream
+    /// so calls can be generated before the function is defined
+    pub fn declare_functions(&mut self, stream: &'c [IRStmt<'c>]) {
+        for stmt in stream {
+            match stmt {
+                IRStmt::Function(FuncStmt { name, .. })
+                | IRStmt::DeclaredFunction(DeclFuncStmt { name, .. }) => {
+                    self.functions.insert(name.ident, name._type);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Places the variables at the top level of the stream in the data sections, so functions
+    /// can access them wherever they are defined. Constants go to `.rodata`, other variables
+    /// to `.bss` if they are zero-initialized and to `.data` otherwise
+    pub fn declare_globals(&mut self, stream: &'c [IRStmt<'c>]) {
+        for stmt in stream {
+            if let IRStmt::Variable(node) = stmt {
+                self.gen_global(node);
+            }
+        }
+    }
+
+    fn gen_global(&mut self, node: &'c VarStmt) {
+        let name = node.name.ident;
+        let layout = self.layout.layout_of(&node.name._type);
+        if !node.is_const && cutils::is_zero(&node.val) {
+            if layout.align > 1 {
+                self.bss.push(Declaration::AlignB(layout.align));
+            }
+            self.bss.push(Declaration::Reserve(name.to_string(), layout.size));
+        } else {
+            let mut decls = Vec::new();
+            if layout.align > 1 {
+                decls.push(Declaration::Align(layout.align));
+            }
+            let scalar = match &node.val {
+                IRExpr::Literal(lit, _) => cutils::scalar_literal(lit),
+                _ => None,
+            };
+            match scalar {
+                Some(lit) => decls.push(Declaration::Define(
+                    name.to_string(),
+                    cutils::word_from_size(layout.size as u8),
+                    lit,
+                )),
+                None if cutils::is_constant(&node.val) => {
+                    decls.push(Declaration::Label(name.to_string()));
+                    self.gen_const(&node.val, &node.name._type, &mut decls);
+                }
+                None => panic!(
+                    "The global {name} has to be initialized with a constant, received {} instead",
+                    node.val
+                ),
+            }
+            if node.is_const {
+                self.rodata.extend(decls);
+            } else {
+                self.data.extend(decls);
+            }
+        }
+        self.globals.insert(name, &node.name._type);
+    }
+
     pub fn gen_stmt(&mut self, node: &'c IRStmt) {
+        self.gen_loc(node.loc());
         match node {
             IRStmt::DeclaredFunction(_) => todo!(),
             IRStmt::Function(node) => self.gen_function(node),
@@ -98,36 +215,77 @@ impl<'c> CodeGenerator<'c> {
             IRStmt::Return(node) => self.gen_return(node),
             IRStmt::Exit(node) => self.gen_exit(node),
             IRStmt::Jump(node) => self.gen_jump(node),
+            IRStmt::Branch(node) => self.gen_branch(node),
+            IRStmt::Assign(node) => self.gen_assign(node),
+            IRStmt::Store(node) => self.gen_store(node),
             IRStmt::Call(node) => self.gen_call(node),
         }
     }
 
     fn gen_expr(&mut self, node: &'c IRExpr) -> Operand {
+        self.gen_loc(node.loc());
         match &node {
             IRExpr::Literal(node, type_) => match node {
-                ir::Literal::Int32(val) => {
-                    Operand::SizedLiteral(SizedLiteral(Literal::Int32(*val), DataSize::DWord))
-                }
                 ir::Literal::String(val) => self.gen_string(val, type_),
-                int => todo!("Handle non-i32 literals here: {:?}", int),
+                ir::Literal::Float32(val) => self.gen_float_const(Literal::Float32(*val)),
+                ir::Literal::Float64(val) => self.gen_float_const(Literal::Float64(*val)),
+                ir::Literal::Array(_, elems) => self.gen_array_literal(elems, type_),
+                ir::Literal::Vector(elems) => self.gen_elems(elems, type_),
+                lit => {
+                    let lit = cutils::scalar_literal(lit)
+                        .unwrap_or_else(|| todo!("Handle {:?} literals here", lit));
+                    let size = self.size_of(type_) as u8;
+                    Operand::SizedLiteral(SizedLiteral(lit, cutils::word_from_size(size)))
+                }
             },
             IRExpr::Call(node) => {
                 self.gen_call(node);
-                let reg = Register::Rax;
+                // Floats are returned in xmm0
+                let reg = match self.functions.get(node.name) {
+                    Some(_type) if _type.is_float() => Register::Xmm0,
+                    _ => Register::Rax,
+                };
                 Operand::Register(reg)
             }
-            IRExpr::ArithOp(node) => self.gen_arith_op(node, true),
-            IRExpr::Ident(node) => cutils::get_stack_location(
-                *self
-                    .symbol_table
-                    .get(node)
-                    .unwrap_or_else(|| panic!("Could not find ident with name {node:?}")),
-            ),
+            IRExpr::ArithOp(node) => self.gen_arith_op(node),
+            IRExpr::CmpOp(node) => self.gen_cmp_op(node),
+            IRExpr::Not(node) => self.gen_not(node),
+            IRExpr::Cast(node) => self.gen_cast(node),
+            IRExpr::Alloca(node) => self.gen_alloca(node),
+            IRExpr::Load(node) => self.gen_ptr_load(node),
+            IRExpr::AddrOf(node) => self.gen_addr_of(node),
+            IRExpr::Field(_) | IRExpr::Index(_) => Operand::MemAddr(self.gen_place(node)),
+            IRExpr::Ident(node) => Operand::MemAddr(self.symbol(node).0),
+            IRExpr::Extract(node) => self.gen_extract(node),
+            IRExpr::Insert(node) => self.gen_insert(node),
+            IRExpr::Shuffle(node) => self.gen_shuffle(node),
             IRExpr::StructInit(node) => self.gen_struct_init(node),
         }
     }
 
+    /// Emits a `%line` directive if the location is on another line than the previous one
+    fn gen_loc(&mut self, loc: Option<SourceLoc>) {
+        let Some(loc) = loc.filter(|_| self.line_directives) else {
+            return;
+        };
+        if self
+            .cur_line
+            .as_ref()
+            .is_some_and(|(file, line)| file == loc.file && *line == loc.line)
+        {
+            return;
+        }
+        self.cur_line = Some((loc.file.to_string(), loc.line));
+        self.out.push(AsmElement::Directive(Directive {
+            _type: DirectiveType::Line {
+                line: loc.line,
+                file: loc.file.to_string(),
+            },
+        }));
+    }
+
     pub fn gen_entry(&mut self, node: &'c BlockStmt<'c>) {
+        self.symbol_table.clear();
         // Text directive (entry point)
         self.out.push(AsmElement::Directive(Directive {
             _type: DirectiveType::Text,
@@ -150,7 +308,7 @@ impl<'c> CodeGenerator<'c> {
             "print" => self.gen_print(node),
             _ => {
                 self.gen_call_args(node);
-                self.out.push(cutils::gen_call(&node.name))
+                self.out.push(cutils::gen_call(node.name))
             }
         }
     }
@@ -162,61 +320,1086 @@ impl<'c> CodeGenerator<'c> {
         }))
     }
 
-    fn gen_string(&mut self, val: &str, type_: &Type<'c>) -> Operand {
-        let size = *match type_ {
-            Type::Ident(_) => todo!(),
-            Type::Array(_, len) => len,
+    fn gen_branch(&mut self, node: &'c BranchStmt) {
+        let jump = if let IRExpr::CmpOp(cmp) = &node.condition {
+            // Use the flags set by the comparison instead of materializing the i1
+            let op = self.gen_cmp_ins(cmp);
+            cutils::jump_opcode(op)
+        } else {
+            let condition = self.gen_load(&node.condition, Register::Rax);
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode: Opcode::Cmp,
+                args: vec![
+                    Operand::Register(condition),
+                    Operand::Literal(Literal::Int32(0)),
+                ],
+            }));
+            Opcode::JNz
         };
-        // TODO: use different splitting techniques based on string length
-        let mut strings = cutils::split_string(val, 8);
-        let last_string = strings.pop().unwrap();
-        self.stack_pointer -= size as i32;
-        Operand::SizedLiteral(SizedLiteral(
-            Literal::Int64(cutils::conv_str_to_bytes(last_string) as i64),
-            cutils::word_from_size(size as u8),
-        ))
-    }
-
-    fn gen_arith_op(&mut self, node: &'c ArithOpExpr, move_to_rax: bool) -> Operand {
-        if move_to_rax {
-            let left_expr = self.gen_expr(&node.values.0);
-            self.gen_mov_ins(Operand::Register(Register::Rax), left_expr)
-        }
-        let arith_op = match node.op {
-            ir::Operator::Add => self.gen_arith_op_ins(Opcode::Add, node),
-            ir::Operator::Sub => self.gen_arith_op_ins(Opcode::Sub, node),
-            ir::Operator::Mul => self.gen_arith_op_ins(Opcode::Mul, node),
-            ir::Operator::Div => self.gen_arith_op_ins(Opcode::Div, node),
+        self.out.push(AsmElement::Instruction(Instruction {
+            opcode: jump,
+            args: vec![Operand::Ident(node.then_label.to_string())],
+        }));
+        self.out.push(AsmElement::Instruction(Instruction {
+            opcode: Opcode::Jmp,
+            args: vec![Operand::Ident(node.else_label.to_string())],
+        }));
+    }
+
+    /// Places the string in `.rodata` with a null terminator, so it can be passed to C.
+    /// Arrays that are longer than the string are padded with zeros, so all of their
+    /// elements can be read. Equal strings share one entry. Returns the array in memory
+    /// or its address for pointers
+    fn gen_string(&mut self, val: &'c [u8], type_: &Type<'c>) -> Operand {
+        let padding = match type_ {
+            Type::Array(..) => self.string_padding(val, type_).saturating_sub(1),
+            _ => 0,
         };
-        self.out.push(arith_op);
-        Operand::Register(Register::Rax)
+        let key = (val, val.len() as u32 + 1 + padding);
+        let name = match self.strings.get(&key) {
+            Some(name) => name.clone(),
+            None => {
+                let name = self.next_lc();
+                self.rodata.push(Declaration::DefineBytes(
+                    name.clone(),
+                    DataValue::String(val.to_vec()),
+                    Some(0),
+                ));
+                cutils::push_zeros(&mut self.rodata, padding);
+                self.strings.insert(key, name.clone());
+                name
+            }
+        };
+        let addr = MemAddr::RipRelative(name, 0);
+        match type_ {
+            Type::Pointer(_) => self.gen_lea(addr),
+            _ => Operand::MemAddr(addr),
+        }
     }
 
-    fn gen_arith_op_ins(&mut self, opcode: Opcode, node: &'c ArithOpExpr) -> AsmElement {
-        AsmElement::Instruction(Instruction {
-            opcode,
-            args: vec![
-                Operand::Register(Register::Rax),
-                match &*node.values.1 {
-                    IRExpr::ArithOp(expr) => self.gen_arith_op(expr, false),
-                    expr => self.gen_expr(expr),
-                },
-            ],
+    /// Places an array of constants in `.rodata` and builds all other arrays on the stack
+    fn gen_array_literal(&mut self, elems: &'c [IRExpr<'c>], type_: &Type<'c>) -> Operand {
+        if !elems.iter().all(cutils::is_constant) {
+            return self.gen_elems(elems, type_);
+        }
+        let name = self.next_lc();
+        let align = self.layout.align_of(type_);
+        if align > 1 {
+            self.rodata.push(Declaration::Align(align));
+        }
+        let mut decls = vec![Declaration::Label(name.clone())];
+        let elem_type = self.element_of(*type_);
+        for elem in elems {
+            self.gen_const(elem, elem_type, &mut decls);
+        }
+        self.rodata.extend(decls);
+        Operand::MemAddr(MemAddr::RipRelative(name, 0))
+    }
+
+    /// Appends the bytes of a constant to the declarations of a data section.
+    /// Padding and zero-initialized aggregates are written with `times`
+    fn gen_const(&self, node: &'c IRExpr<'c>, _type: &Type<'c>, decls: &mut Vec<Declaration>) {
+        let size = self.size_of(_type);
+        if matches!(_type, Type::Array(..) | Type::Vector(..) | Type::Ident(_)) && cutils::is_zero(node) {
+            cutils::push_zeros(decls, size);
+            return;
+        }
+        match node {
+            IRExpr::Literal(ir::Literal::String(val), _) => {
+                cutils::push_value(decls, DataSize::Byte, DataValue::String(val.to_vec()));
+                cutils::push_zeros(decls, self.string_padding(val, _type));
+            }
+            IRExpr::Literal(ir::Literal::Array(_, elems) | ir::Literal::Vector(elems), _) => {
+                let elem_type = match _type {
+                    Type::Array(elem_type, _) | Type::Vector(elem_type, _) => elem_type,
+                    _type => panic!("Expected an array or vector, received {_type} instead"),
+                };
+                for elem in elems {
+                    self.gen_const(elem, elem_type, decls);
+                }
+                // Vectors are padded to a power of two
+                cutils::push_zeros(decls, size - elems.len() as u32 * self.size_of(elem_type));
+            }
+            IRExpr::StructInit(init) => {
+                let layout = self.layout.aggregate_layout(init.name);
+                let fields = &self
+                    .types
+                    .get(init.name)
+                    .unwrap_or_else(|| panic!("Could not find type with the name {}", init.name))
+                    .1;
+                let mut end = 0;
+                for ((val, field), offset) in init.values.iter().zip(fields).zip(layout.offsets) {
+                    cutils::push_zeros(decls, offset - end);
+                    self.gen_const(val, &field._type, decls);
+                    end = offset + self.size_of(&field._type);
+                }
+                cutils::push_zeros(decls, layout.layout.size - end);
+            }
+            IRExpr::Literal(lit, _) => {
+                let lit = cutils::scalar_literal(lit)
+                    .unwrap_or_else(|| todo!("Handle {:?} literals here", lit));
+                cutils::push_value(decls, cutils::word_from_size(size as u8), DataValue::Literal(lit));
+            }
+            node => panic!("Expected a constant, received {node} instead"),
+        }
+    }
+
+    /// Returns the number of bytes of the array that are not covered by the string
+    fn string_padding(&self, val: &[u8], _type: &Type<'c>) -> u32 {
+        self.size_of(_type).checked_sub(val.len() as u32).unwrap_or_else(|| {
+            let val = String::from_utf8_lossy(val);
+            panic!("The string {val:?} is longer than its type {_type}")
         })
     }
 
+    /// Returns a new label for a literal constant
+    fn next_lc(&mut self) -> String {
+        let name = format!("LC{}", self.lc_index);
+        self.lc_index += 1;
+        name
+    }
+
+    /// Returns a new label for a jump target. `@` cannot be part of an IR identifier,
+    /// so these labels never clash with the labels of the program
+    fn next_label(&mut self) -> String {
+        let name = format!("L@{}", self.label_index);
+        self.label_index += 1;
+        name
+    }
+
+    fn gen_ins(&mut self, opcode: Opcode, args: Vec<Operand>) {
+        self.out.push(AsmElement::Instruction(Instruction { opcode, args }));
+    }
+
+    fn gen_internal_label(&mut self, name: String) {
+        self.out.push(AsmElement::Label(Label { name }));
+    }
+
+    /// Places a float in the read only data section and returns its location,
+    /// since SSE instructions cannot take immediates. Equal floats share one entry
+    fn gen_float_const(&mut self, lit: Literal) -> Operand {
+        let key = match lit {
+            Literal::Float32(val) => (val.to_bits() as u64, 4),
+            Literal::Float64(val) => (val.to_bits(), 8),
+            lit => panic!("Expected a float, received {lit} instead"),
+        };
+        let name = match self.floats.get(&key) {
+            Some(name) => name.clone(),
+            None => {
+                let name = self.next_lc();
+                let SizedLiteral(lit, size) = cutils::literal_to_sized_literal(lit);
+                self.rodata.push(Declaration::Define(name.clone(), size, lit));
+                self.floats.insert(key, name.clone());
+                name
+            }
+        };
+        Operand::MemAddr(MemAddr::RipRelative(name, 0))
+    }
+
+    fn gen_arith_op(&mut self, node: &'c ArithOpExpr) -> Operand {
+        let _type = self.type_of(&node.values.0);
+        if _type.is_vector() {
+            return self.gen_vector_arith_op(node);
+        }
+        if _type.is_float() {
+            return self.gen_float_arith_op(node);
+        }
+        if let ir::Operator::Shl | ir::Operator::LShr | ir::Operator::AShr = node.op {
+            return self.gen_shift(node);
+        }
+        let (left, right) = self.gen_binary_operands(&node.values.0, &node.values.1);
+        self.gen_int_op(node.op, left, right)
+    }
+
+    /// Applies the operator to the integer in the accumulator and the right operand
+    fn gen_int_op(&mut self, op: ir::Operator, left: Register, mut right: Operand) -> Operand {
+        let opcode = match op {
+            ir::Operator::Add => Opcode::Add,
+            ir::Operator::Sub => Opcode::Sub,
+            ir::Operator::Mul => Opcode::IMul,
+            ir::Operator::And => Opcode::And,
+            ir::Operator::Or => Opcode::Or,
+            ir::Operator::XOr => Opcode::XOr,
+            ir::Operator::Shl | ir::Operator::LShr | ir::Operator::AShr => {
+                let count = match right {
+                    Operand::Literal(_) | Operand::Register(Register::Cl) => right,
+                    right => {
+                        let reg = cutils::register_by_size(Register::Rcx, left.size());
+                        self.gen_mov_ins(Operand::Register(reg), right);
+                        Operand::Register(Register::Cl)
+                    }
+                };
+                return self.gen_shift_ins(op, left, count);
+            }
+            ir::Operator::SDiv | ir::Operator::UDiv | ir::Operator::SRem | ir::Operator::URem => {
+                return self.gen_div(op, left, right)
+            }
+        };
+        if opcode == Opcode::IMul && left.size() == 1 {
+            // There is no two operand imul for bytes, so use the
+            // single operand form which multiplies al with its argument
+            if !matches!(right, Operand::Register(_)) {
+                self.gen_mov_ins(Operand::Register(Register::Cl), right);
+                right = Operand::Register(Register::Cl);
+            }
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode,
+                args: vec![right],
+            }));
+        } else {
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode,
+                args: vec![Operand::Register(left), right],
+            }));
+        }
+        Operand::Register(left)
+    }
+
+    fn gen_float_arith_op(&mut self, node: &'c ArithOpExpr) -> Operand {
+        let size = self.size_of(&self.type_of(&node.values.0));
+        let opcode = cutils::float_arith_opcode(node.op, size);
+        let (left, right) = self.gen_float_operands(&node.values.0, &node.values.1);
+        self.out.push(AsmElement::Instruction(Instruction {
+            opcode,
+            args: vec![Operand::Register(left), right],
+        }));
+        Operand::Register(left)
+    }
+
+    /// Divides rax by the right value. The quotient ends up in rax and the remainder in rdx
+    /// (ah for bytes)
+    fn gen_div(&mut self, op: ir::Operator, left: Register, mut right: Operand) -> Operand {
+        let signed = matches!(op, ir::Operator::SDiv | ir::Operator::SRem);
+        let size = left.size();
+        // div and idiv cannot take an immediate and the size of a memory address is unknown
+        if !matches!(right, Operand::Register(_)) {
+            let reg = Operand::Register(cutils::register_by_size(Register::Rcx, size));
+            self.gen_mov_ins(reg.clone(), right);
+            right = reg;
+        }
+        if signed {
+            let opcode = match size {
+                1 => Opcode::Cbw,
+                2 => Opcode::Cwd,
+                4 => Opcode::Cdq,
+                _ => Opcode::Cqo,
+            };
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode,
+                args: vec![],
+            }));
+        } else if size == 1 {
+            self.gen_mov_ins(Operand::Register(Register::Ax), Operand::Register(Register::Al));
+        } else {
+            let edx = Operand::Register(Register::Edx);
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode: Opcode::XOr,
+                args: vec![edx.clone(), edx],
+            }));
+        }
+        self.out.push(AsmElement::Instruction(Instruction {
+            opcode: if signed { Opcode::IDiv } else { Opcode::Div },
+            args: vec![right],
+        }));
+        match op {
+            ir::Operator::SDiv | ir::Operator::UDiv => Operand::Register(left),
+            _ if size == 1 => {
+                self.gen_mov_ins(Operand::Register(Register::Al), Operand::Register(Register::Ah));
+                Operand::Register(Register::Al)
+            }
+            _ => Operand::Register(cutils::register_by_size(Register::Rdx, size)),
+        }
+    }
+
+    /// Shifts the left value by the right value, which has to be an immediate or live in cl
+    fn gen_shift(&mut self, node: &'c ArithOpExpr) -> Operand {
+        let count = match &*node.values.1 {
+            IRExpr::Literal(
+                ir::Literal::Int8(_)
+                | ir::Literal::Int16(_)
+                | ir::Literal::Int32(_)
+                | ir::Literal::Int64(_)
+                | ir::Literal::UInt8(_)
+                | ir::Literal::UInt16(_)
+                | ir::Literal::UInt32(_)
+                | ir::Literal::UInt64(_),
+                _,
+            ) => {
+                let count = match self.gen_expr(&node.values.1) {
+                    Operand::SizedLiteral(SizedLiteral(lit, _)) => lit,
+                    _ => unreachable!(),
+                };
+                self.gen_load(&node.values.0, Register::Rax);
+                Operand::Literal(count)
+            }
+            _ => {
+                let (_, right) = self.gen_binary_operands(&node.values.0, &node.values.1);
+                if !matches!(right, Operand::Register(_)) {
+                    let size = self.size_of(&self.type_of(&node.values.1));
+                    self.gen_mov_ins(
+                        Operand::Register(cutils::register_by_size(Register::Rcx, size as u8)),
+                        right,
+                    );
+                }
+                Operand::Register(Register::Cl)
+            }
+        };
+        let left = cutils::register_by_size(
+            Register::Rax,
+            self.size_of(&self.type_of(&node.values.0)) as u8,
+        );
+        self.gen_shift_ins(node.op, left, count)
+    }
+
+    fn gen_shift_ins(&mut self, op: ir::Operator, left: Register, count: Operand) -> Operand {
+        let opcode = match op {
+            ir::Operator::Shl => Opcode::Shl,
+            ir::Operator::LShr => Opcode::Shr,
+            _ => Opcode::Sar,
+        };
+        self.out.push(AsmElement::Instruction(Instruction {
+            opcode,
+            args: vec![Operand::Register(left), count],
+        }));
+        Operand::Register(left)
+    }
+
+    /// Applies the operator to every lane. Vectors that fill a vector register use a single
+    /// SIMD instruction if there is one for the operator, all others are computed lane by lane
+    fn gen_vector_arith_op(&mut self, node: &'c ArithOpExpr) -> Operand {
+        let _type = self.type_of(&node.values.0);
+        let (elem_type, lanes) = self.lanes_of(_type);
+        let size = self.size_of(elem_type);
+        let left = self.gen_vector(&node.values.0);
+        let right = self.gen_vector(&node.values.1);
+        let pos = self.alloc_stack(&_type);
+        let packed = cutils::packed_opcode(node.op, elem_type.is_float(), size)
+            .filter(|_| self.size_of(&_type) == self.vector_size);
+        if let Some(opcode) = packed {
+            let mov = self.vector_mov_opcode(elem_type);
+            let (xmm0, xmm1) = (Operand::Register(Register::Xmm0), Operand::Register(Register::Xmm1));
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode: mov,
+                args: vec![xmm0.clone(), Operand::MemAddr(left)],
+            }));
+            // The memory operands of packed instructions have to be aligned, so both sides use registers
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode: mov,
+                args: vec![xmm1.clone(), Operand::MemAddr(right)],
+            }));
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode,
+                args: vec![xmm0.clone(), xmm1],
+            }));
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode: mov,
+                args: vec![cutils::get_stack_location(pos), xmm0],
+            }));
+            return cutils::get_stack_location(pos);
+        }
+        for lane in 0..lanes {
+            let offset = (lane * size) as i32;
+            let left = Operand::MemAddr(cutils::offset_mem_addr(left.clone(), offset));
+            let right = Operand::MemAddr(cutils::offset_mem_addr(right.clone(), offset));
+            let target = cutils::get_stack_location(pos + offset);
+            if elem_type.is_float() {
+                let xmm0 = Operand::Register(Register::Xmm0);
+                self.gen_float_mov(xmm0.clone(), left, size);
+                self.out.push(AsmElement::Instruction(Instruction {
+                    opcode: cutils::float_arith_opcode(node.op, size),
+                    args: vec![xmm0.clone(), right],
+                }));
+                self.gen_float_mov(target, xmm0, size);
+            } else {
+                let reg = cutils::register_by_size(Register::Rax, size as u8);
+                self.gen_mov_ins(Operand::Register(reg), left);
+                let val = self.gen_int_op(node.op, reg, right);
+                self.gen_store_val(target, val, elem_type);
+            }
+        }
+        cutils::get_stack_location(pos)
+    }
+
+    /// Builds an array or vector on the stack element by element
+    fn gen_elems(&mut self, elems: &'c [IRExpr<'c>], _type: &Type<'c>) -> Operand {
+        let elem_type = match _type {
+            Type::Array(elem_type, _) | Type::Vector(elem_type, _) => elem_type,
+            _type => panic!("Expected an array or vector, received {_type} instead"),
+        };
+        let size = self.size_of(elem_type) as i32;
+        let pos = self.alloc_stack(_type);
+        for (i, elem) in elems.iter().enumerate() {
+            let val = self.gen_expr(elem);
+            self.gen_store_val(cutils::get_stack_location(pos + i as i32 * size), val, elem_type);
+        }
+        cutils::get_stack_location(pos)
+    }
+
+    /// Returns the memory location of the lane, which can be used like a variable
+    fn gen_extract(&mut self, node: &'c ExtractExpr) -> Operand {
+        let (elem_type, _) = self.lanes_of(self.type_of(&node.vector));
+        let size = self.size_of(elem_type);
+        let vector = self.gen_vector(&node.vector);
+        Operand::MemAddr(cutils::offset_mem_addr(vector, (node.lane * size) as i32))
+    }
+
+    fn gen_insert(&mut self, node: &'c InsertExpr) -> Operand {
+        let _type = self.type_of(&node.vector);
+        let (elem_type, _) = self.lanes_of(_type);
+        let size = self.size_of(elem_type);
+        let vector = self.gen_vector(&node.vector);
+        let pos = self.alloc_stack(&_type);
+        self.gen_copy(MemAddr::RegisterPos(Register::Rbp, pos), vector, &_type);
+        let val = self.gen_expr(&node.val);
+        let lane = cutils::get_stack_location(pos + (node.lane * size) as i32);
+        self.gen_store_val(lane, val, elem_type);
+        cutils::get_stack_location(pos)
+    }
+
+    /// Shuffles of 32 bit lanes that take all lanes from one vector or the lower half from the
+    /// first and the upper half from the second vector use `pshufd` and `shufps`.
+    /// Other shuffles copy every lane on its own
+    fn gen_shuffle(&mut self, node: &'c ShuffleExpr) -> Operand {
+        let (elem_type, lanes) = self.lanes_of(self.type_of(&node.values.0));
+        let size = self.size_of(elem_type);
+        let _type = Type::Vector(elem_type, node.mask.len() as u32);
+        let left = self.gen_vector(&node.values.0);
+        let right = self.gen_vector(&node.values.1);
+        let pos = self.alloc_stack(&_type);
+        let res = cutils::get_stack_location(pos);
+        if self.size_of(&_type) == self.vector_size && size == 4 && lanes == 4 {
+            let mask = &node.mask;
+            let mov = self.vector_mov_opcode(elem_type);
+            let (xmm0, xmm1) = (Operand::Register(Register::Xmm0), Operand::Register(Register::Xmm1));
+            let imm = Operand::Literal(Literal::Int32(cutils::shuffle_imm(mask)));
+            let single = if mask.iter().all(|&lane| lane < 4) {
+                Some(left.clone())
+            } else if mask.iter().all(|&lane| lane >= 4) {
+                Some(right.clone())
+            } else {
+                None
+            };
+            let ins = if let Some(src) = single {
+                vec![
+                    (mov, vec![xmm0.clone(), Operand::MemAddr(src)]),
+                    (Opcode::Pshufd, vec![xmm0.clone(), xmm0.clone(), imm]),
+                ]
+            } else if mask[..2].iter().all(|&lane| lane < 4) && mask[2..].iter().all(|&lane| lane >= 4) {
+                vec![
+                    (mov, vec![xmm0.clone(), Operand::MemAddr(left.clone())]),
+                    (mov, vec![xmm1.clone(), Operand::MemAddr(right.clone())]),
+                    (Opcode::Shufps, vec![xmm0.clone(), xmm1, imm]),
+                ]
+            } else {
+                Vec::new()
+            };
+            if !ins.is_empty() {
+                for (opcode, args) in ins {
+                    self.out.push(AsmElement::Instruction(Instruction { opcode, args }));
+                }
+                self.out.push(AsmElement::Instruction(Instruction {
+                    opcode: mov,
+                    args: vec![res.clone(), xmm0],
+                }));
+                return res;
+            }
+        }
+        for (i, &lane) in node.mask.iter().enumerate() {
+            let src = match lane < lanes {
+                true => cutils::offset_mem_addr(left.clone(), (lane * size) as i32),
+                false => cutils::offset_mem_addr(right.clone(), ((lane - lanes) * size) as i32),
+            };
+            let target = cutils::get_stack_location(pos + (i as u32 * size) as i32);
+            self.gen_store_val(target, Operand::MemAddr(src), elem_type);
+        }
+        res
+    }
+
+    /// Evaluates the vector and returns the memory location that holds it.
+    /// Vectors that are addressed through a scratch register, like fields behind a pointer,
+    /// are copied to the stack, since evaluating the next operand can overwrite the register
+    fn gen_vector(&mut self, node: &'c IRExpr<'c>) -> MemAddr {
+        let addr = match self.gen_expr(node) {
+            Operand::MemAddr(addr) => addr,
+            val => panic!("Expected a vector in memory, received {val} instead"),
+        };
+        match addr {
+            MemAddr::RegisterPos(Register::Rbp, _) | MemAddr::RipRelative(..) => addr,
+            addr => {
+                let _type = self.type_of(node);
+                let pos = self.alloc_stack(&_type);
+                self.gen_copy(MemAddr::RegisterPos(Register::Rbp, pos), addr, &_type);
+                MemAddr::RegisterPos(Register::Rbp, pos)
+            }
+        }
+    }
+
+    /// Copies a value between memory locations. Vectors that fill a vector register
+    /// are moved through it, all other values are moved in chunks of up to 8 bytes
+    fn gen_copy(&mut self, target: MemAddr, val: MemAddr, _type: &Type<'c>) {
+        let size = self.size_of(_type);
+        if _type.is_vector() && size == self.vector_size {
+            let mov = self.vector_mov_opcode(self.lanes_of(*_type).0);
+            let xmm0 = Operand::Register(Register::Xmm0);
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode: mov,
+                args: vec![xmm0.clone(), Operand::MemAddr(val)],
+            }));
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode: mov,
+                args: vec![Operand::MemAddr(target), xmm0],
+            }));
+            return;
+        }
+        let mut offset = 0;
+        while offset < size {
+            let chunk = [8, 4, 2, 1].into_iter().find(|chunk| size - offset >= *chunk).unwrap();
+            // rax and rcx can hold the addresses
+            let reg = Operand::Register(cutils::register_by_size(Register::Rdx, chunk as u8));
+            let pos = offset as i32;
+            self.gen_mov_ins(reg.clone(), Operand::MemAddr(cutils::offset_mem_addr(val.clone(), pos)));
+            self.gen_mov_ins(Operand::MemAddr(cutils::offset_mem_addr(target.clone(), pos)), reg);
+            offset += chunk;
+        }
+    }
+
+    /// Integer vectors are moved with `movdqu` and float vectors with `movups`
+    fn vector_mov_opcode(&self, elem_type: &Type<'c>) -> Opcode {
+        match elem_type.is_float() {
+            true => Opcode::Movups,
+            false => Opcode::Movdqu,
+        }
+    }
+
+    /// Returns the type and number of the lanes of a vector
+    fn lanes_of(&self, _type: Type<'c>) -> (&'c Type<'c>, u32) {
+        match _type {
+            Type::Vector(elem_type, lanes) => (elem_type, lanes),
+            _type => panic!("Expected a vector, received {_type} instead"),
+        }
+    }
+
+    fn gen_not(&mut self, node: &'c NotExpr) -> Operand {
+        let val = self.gen_load(&node.val, Register::Rax);
+        // Only the lowest bit of an i1 is significant, so the others have to stay zero
+        let ins = if self.type_of(&node.val) == INT1_T {
+            Instruction {
+                opcode: Opcode::XOr,
+                args: vec![Operand::Register(val), Operand::Literal(Literal::Int8(1))],
+            }
+        } else {
+            Instruction {
+                opcode: Opcode::Not,
+                args: vec![Operand::Register(val)],
+            }
+        };
+        self.out.push(AsmElement::Instruction(ins));
+        Operand::Register(val)
+    }
+
+    fn gen_alloca(&mut self, node: &'c AllocaExpr) -> Operand {
+        let pos = self.alloc_stack(&node._type);
+        self.gen_lea(MemAddr::RegisterPos(Register::Rbp, pos))
+    }
+
+    fn gen_addr_of(&mut self, node: &'c AddrOfExpr) -> Operand {
+        let place = self.gen_place(&node.val);
+        self.gen_lea(place)
+    }
+
+    /// Loads the address of the memory location into rax
+    fn gen_lea(&mut self, addr: MemAddr) -> Operand {
+        self.out.push(AsmElement::Instruction(Instruction {
+            opcode: Opcode::Lea,
+            args: vec![Operand::Register(Register::Rax), Operand::MemAddr(addr)],
+        }));
+        Operand::Register(Register::Rax)
+    }
+
+    /// Returns the memory location of a variable, field or element.
+    /// Addresses that are only known at runtime are computed in rax
+    fn gen_place(&mut self, node: &'c IRExpr<'c>) -> MemAddr {
+        match node {
+            IRExpr::Ident(ident) => self.symbol(ident).0,
+            IRExpr::Field(field) => {
+                let (offset, _) = self.field_of(self.aggregate_type(&field.base), field.field);
+                let base = self.gen_base(&field.base);
+                cutils::offset_mem_addr(base, offset as i32)
+            }
+            IRExpr::Index(index) => {
+                let size = self.size_of(self.element_of(self.aggregate_type(&index.base))) as i32;
+                let base = self.gen_base(&index.base);
+                match &*index.index {
+                    IRExpr::Literal(lit, _) => {
+                        let index = match lit {
+                            ir::Literal::Int8(val) => *val as i32,
+                            ir::Literal::Int16(val) => *val as i32,
+                            ir::Literal::Int32(val) => *val,
+                            ir::Literal::Int64(val) => *val as i32,
+                            ir::Literal::UInt8(val) => *val as i32,
+                            ir::Literal::UInt16(val) => *val as i32,
+                            ir::Literal::UInt32(val) => *val as i32,
+                            ir::Literal::UInt64(val) => *val as i32,
+                            lit => panic!("Cannot index an array with {lit}"),
+                        };
+                        cutils::offset_mem_addr(base, index * size)
+                    }
+                    _ => {
+                        let acc = Operand::Register(Register::Rax);
+                        if base != MemAddr::Register(Register::Rax) {
+                            self.gen_lea(base);
+                        }
+                        self.out.push(AsmElement::Instruction(Instruction {
+                            opcode: Opcode::Push,
+                            args: vec![acc.clone()],
+                        }));
+                        let signed = self.type_of(&index.index).is_signed();
+                        let index = self.gen_load(&index.index, Register::Rcx);
+                        let rcx = Operand::Register(Register::Rcx);
+                        if index.size() < 8 && signed {
+                            self.out.push(AsmElement::Instruction(Instruction {
+                                opcode: if index.size() == 4 {
+                                    Opcode::Movsxd
+                                } else {
+                                    Opcode::Movsx
+                                },
+                                args: vec![rcx.clone(), Operand::Register(index)],
+                            }));
+                        } else if index.size() < 8 {
+                            self.gen_mov_ins(rcx.clone(), Operand::Register(index));
+                        }
+                        self.out.push(AsmElement::Instruction(Instruction {
+                            opcode: Opcode::IMul,
+                            args: vec![rcx.clone(), Operand::Literal(Literal::Int32(size))],
+                        }));
+                        self.out.push(AsmElement::Instruction(Instruction {
+                            opcode: Opcode::Pop,
+                            args: vec![acc.clone()],
+                        }));
+                        self.out.push(AsmElement::Instruction(Instruction {
+                            opcode: Opcode::Add,
+                            args: vec![acc, rcx],
+                        }));
+                        MemAddr::Register(Register::Rax)
+                    }
+                }
+            }
+            node => panic!("Expected a variable, field or element, received {node} instead"),
+        }
+    }
+
+    /// Returns the memory location of a struct or array. Pointers get loaded
+    /// into rax and the location they point to is used instead
+    fn gen_base(&mut self, node: &'c IRExpr<'c>) -> MemAddr {
+        match self.type_of(node) {
+            Type::Pointer(_) => MemAddr::Register(self.gen_load(node, Register::Rax)),
+            _ => self.gen_place(node),
+        }
+    }
+
+    /// Returns the type of a struct or array, looking through a pointer to it
+    fn aggregate_type(&self, node: &'c IRExpr<'c>) -> Type<'c> {
+        match self.type_of(node) {
+            Type::Pointer(_type) => *_type,
+            _type => _type,
+        }
+    }
+
+    /// Returns the offset and the type of a field
+    fn field_of(&self, _type: Type<'c>, field: &str) -> (u32, &'c Type<'c>) {
+        let name = match _type {
+            Type::Ident(name) => name,
+            _type => panic!("Cannot access field {field} of {_type}"),
+        };
+        self.layout
+            .field_of(name, field)
+            .unwrap_or_else(|| panic!("Type {name} has no field with the name {field}"))
+    }
+
+    /// Returns the type of the elements of an array
+    fn element_of(&self, _type: Type<'c>) -> &'c Type<'c> {
+        match _type {
+            Type::Array(_type, _) => _type,
+            _type => panic!("Cannot index into {_type}"),
+        }
+    }
+
+    /// Returns the location and type of a local or global variable.
+    /// Globals are addressed relative to the instruction pointer
+    fn symbol(&self, ident: &str) -> (MemAddr, &'c Type<'c>) {
+        if let Some((pos, _type)) = self.symbol_table.get(ident) {
+            return (MemAddr::RegisterPos(Register::Rbp, *pos), _type);
+        }
+        match self.globals.get(ident) {
+            Some(_type) => (MemAddr::RipRelative(ident.to_string(), 0), _type),
+            None => panic!("Could not find ident with name {ident:?}"),
+        }
+    }
+
+    /// Returns the type of a variable, field or element
+    fn place_type(&self, node: &'c IRExpr<'c>) -> &'c Type<'c> {
+        match node {
+            IRExpr::Ident(ident) => self.symbol(ident).1,
+            IRExpr::Field(field) => self.field_of(self.aggregate_type(&field.base), field.field).1,
+            IRExpr::Index(index) => self.element_of(self.aggregate_type(&index.base)),
+            node => panic!("Expected a variable, field or element, received {node} instead"),
+        }
+    }
+
+    fn gen_ptr_load(&mut self, node: &'c LoadExpr) -> Operand {
+        let ptr = self.gen_load(&node.ptr, Register::Rax);
+        if node._type.is_vector() {
+            let pos = self.alloc_stack(&node._type);
+            self.gen_copy(MemAddr::RegisterPos(Register::Rbp, pos), MemAddr::Register(ptr), &node._type);
+            return cutils::get_stack_location(pos);
+        }
+        if node._type.is_float() {
+            let size = self.size_of(&node._type);
+            let reg = Operand::Register(Register::Xmm0);
+            self.gen_float_mov(reg.clone(), Operand::MemAddr(MemAddr::Register(ptr)), size);
+            return reg;
+        }
+        let reg = cutils::register_by_size(Register::Rax, self.size_of(&node._type) as u8);
+        self.out.push(cutils::gen_mov_ins(
+            Operand::Register(reg),
+            Operand::MemAddr(MemAddr::Register(ptr)),
+        ));
+        Operand::Register(reg)
+    }
+
+    fn gen_store(&mut self, node: &'c StoreStmt) {
+        let _type = self.type_of(&node.val);
+        if _type.is_vector() {
+            let val = self.gen_vector(&node.val);
+            let ptr = self.gen_load(&node.ptr, Register::Rax);
+            self.gen_copy(MemAddr::Register(ptr), val, &_type);
+            return;
+        }
+        if _type.is_float() {
+            let acc = Operand::Register(Register::Rax);
+            let ptr = self.gen_load(&node.ptr, Register::Rax);
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode: Opcode::Push,
+                args: vec![Operand::Register(ptr)],
+            }));
+            let val = self.gen_float_load(&node.val, Register::Xmm0);
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode: Opcode::Pop,
+                args: vec![acc],
+            }));
+            let size = self.size_of(&_type);
+            self.gen_float_mov(
+                Operand::MemAddr(MemAddr::Register(ptr)),
+                Operand::Register(val),
+                size,
+            );
+            return;
+        }
+        let (val, ptr) = self.gen_binary_operands(&node.val, &node.ptr);
+        let ptr = match ptr {
+            Operand::Register(reg) => reg,
+            ptr => {
+                self.gen_mov_ins(Operand::Register(Register::Rcx), ptr);
+                Register::Rcx
+            }
+        };
+        self.gen_mov_ins(
+            Operand::MemAddr(MemAddr::Register(ptr)),
+            Operand::Register(val),
+        );
+    }
+
+    fn gen_cmp_op(&mut self, node: &'c CmpOpExpr) -> Operand {
+        let op = self.gen_cmp_ins(node);
+        self.out.push(AsmElement::Instruction(Instruction {
+            opcode: cutils::set_opcode(op),
+            args: vec![Operand::Register(Register::Al)],
+        }));
+        Operand::Register(Register::Al)
+    }
+
+    /// Compares the values and returns the operator whose condition codes match the flags
+    fn gen_cmp_ins(&mut self, node: &'c CmpOpExpr) -> CmpOperator {
+        let _type = self.type_of(&node.values.0);
+        if _type.is_float() {
+            let size = self.size_of(&_type);
+            let (left, right) = self.gen_float_operands(&node.values.0, &node.values.1);
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode: cutils::sse_opcode(size, Opcode::Ucomiss, Opcode::Ucomisd),
+                args: vec![Operand::Register(left), right],
+            }));
+            return cutils::float_cmp_operator(node.op);
+        }
+        let (left, right) = self.gen_binary_operands(&node.values.0, &node.values.1);
+        self.out.push(AsmElement::Instruction(Instruction {
+            opcode: Opcode::Cmp,
+            args: vec![Operand::Register(left), right],
+        }));
+        node.op
+    }
+
+    fn gen_cast(&mut self, node: &'c CastExpr) -> Operand {
+        let from = self.type_of(&node.val);
+        if let Err(err) = node.kind.check(&from, &node._type) {
+            panic!("{err}");
+        }
+        let target = cutils::register_by_size(Register::Rax, self.size_of(&node._type) as u8);
+        match node.kind {
+            CastKind::SExt => {
+                let val = self.gen_load(&node.val, Register::Rax);
+                self.out.push(AsmElement::Instruction(Instruction {
+                    opcode: if val.size() == 4 {
+                        Opcode::Movsxd
+                    } else {
+                        Opcode::Movsx
+                    },
+                    args: vec![Operand::Register(target), Operand::Register(val)],
+                }));
+            }
+            CastKind::ZExt => {
+                let val = self.gen_load(&node.val, Register::Rax);
+                // A 32 bit mov clears the upper half, even if the source is the same register
+                if val.size() == 4 {
+                    self.out.push(cutils::gen_mov_ins(
+                        Operand::Register(val),
+                        Operand::Register(val),
+                    ));
+                } else {
+                    self.gen_mov_ins(Operand::Register(target), Operand::Register(val));
+                }
+            }
+            CastKind::Trunc => {
+                self.gen_load(&node.val, Register::Rax);
+                if node._type == INT1_T {
+                    self.out.push(AsmElement::Instruction(Instruction {
+                        opcode: Opcode::And,
+                        args: vec![Operand::Register(target), Operand::Literal(Literal::Int8(1))],
+                    }));
+                }
+            }
+            CastKind::Bitcast if from.is_integer() && node._type.is_integer() => {
+                self.gen_load(&node.val, Register::Rax);
+            }
+            CastKind::Bitcast if from.is_float() && node._type.is_float() => {
+                return Operand::Register(self.gen_float_load(&node.val, Register::Xmm0));
+            }
+            CastKind::Bitcast if from.is_float() => {
+                let val = self.gen_float_load(&node.val, Register::Xmm0);
+                self.out.push(AsmElement::Instruction(Instruction {
+                    opcode: if target.size() == 4 { Opcode::Movd } else { Opcode::Movq },
+                    args: vec![Operand::Register(target), Operand::Register(val)],
+                }));
+            }
+            CastKind::Bitcast => {
+                let val = self.gen_load(&node.val, Register::Rax);
+                let reg = Operand::Register(Register::Xmm0);
+                self.out.push(AsmElement::Instruction(Instruction {
+                    opcode: if val.size() == 4 { Opcode::Movd } else { Opcode::Movq },
+                    args: vec![reg.clone(), Operand::Register(val)],
+                }));
+                return reg;
+            }
+            CastKind::IntToFloat => {
+                let mut val = self.gen_load(&node.val, Register::Rax);
+                // The conversion only takes 32 and 64 bit integers and treats them as signed,
+                // so smaller and unsigned 32 bit integers are extended first
+                if from.is_signed() && val.size() < 4 {
+                    self.out.push(AsmElement::Instruction(Instruction {
+                        opcode: Opcode::Movsx,
+                        args: vec![Operand::Register(Register::Eax), Operand::Register(val)],
+                    }));
+                    val = Register::Eax;
+                } else if !from.is_signed() && val.size() == 4 {
+                    self.out.push(cutils::gen_mov_ins(
+                        Operand::Register(val),
+                        Operand::Register(val),
+                    ));
+                    val = Register::Rax;
+                } else if val.size() < 4 {
+                    self.gen_mov_ins(Operand::Register(Register::Eax), Operand::Register(val));
+                    val = Register::Eax;
+                }
+                let size = self.size_of(&node._type);
+                let reg = Operand::Register(Register::Xmm0);
+                let cvt = cutils::sse_opcode(size, Opcode::Cvtsi2ss, Opcode::Cvtsi2sd);
+                if from.is_signed() || self.size_of(&from) < 8 {
+                    self.gen_ins(cvt, vec![reg.clone(), Operand::Register(val)]);
+                    return reg;
+                }
+                // Unsigned 64 bit integers with the highest bit set are negative to the conversion.
+                // They are halved, keeping the lowest bit for rounding, converted and doubled
+                let (rax, rcx) = (Operand::Register(Register::Rax), Operand::Register(Register::Rcx));
+                let (big, done) = (self.next_label(), self.next_label());
+                let one = Operand::Literal(Literal::Int8(1));
+                self.gen_ins(Opcode::Cmp, vec![rax.clone(), Operand::Literal(Literal::Int32(0))]);
+                self.gen_ins(Opcode::JL, vec![Operand::Ident(big.clone())]);
+                self.gen_ins(cvt, vec![reg.clone(), rax.clone()]);
+                self.gen_ins(Opcode::Jmp, vec![Operand::Ident(done.clone())]);
+                self.gen_internal_label(big);
+                self.gen_mov_ins(rcx.clone(), rax.clone());
+                self.gen_ins(Opcode::Shr, vec![rcx.clone(), one.clone()]);
+                self.gen_ins(Opcode::And, vec![Operand::Register(Register::Eax), one]);
+                self.gen_ins(Opcode::Or, vec![rcx.clone(), rax]);
+                self.gen_ins(cvt, vec![reg.clone(), rcx]);
+                let add = cutils::sse_opcode(size, Opcode::Addss, Opcode::Addsd);
+                self.gen_ins(add, vec![reg.clone(), reg.clone()]);
+                self.gen_internal_label(done);
+                return reg;
+            }
+            CastKind::FloatToInt => {
+                let size = self.size_of(&from);
+                let val = self.gen_float_load(&node.val, Register::Xmm0);
+                let cvt = cutils::sse_opcode(size, Opcode::Cvttss2si, Opcode::Cvttsd2si);
+                // The conversion only produces signed 32 and 64 bit integers. Smaller ones
+                // are the lower bits of the result and unsigned 32 bit ones of a 64 bit result
+                let reg = match (target.size(), node._type.is_signed()) {
+                    (1 | 2, _) => Register::Eax,
+                    (4, false) => Register::Rax,
+                    _ => target,
+                };
+                if node._type.is_signed() || target.size() < 8 {
+                    self.gen_ins(cvt, vec![Operand::Register(reg), Operand::Register(val)]);
+                    return Operand::Register(target);
+                }
+                // Floats of at least 2^63 do not fit into a signed integer,
+                // so 2^63 is subtracted before and the highest bit is set after the conversion
+                let limit = match size {
+                    4 => self.gen_float_const(Literal::Float32(2f32.powi(63))),
+                    _ => self.gen_float_const(Literal::Float64(2f64.powi(63))),
+                };
+                let (val, xmm1) = (Operand::Register(val), Operand::Register(Register::Xmm1));
+                let (rax, rcx) = (Operand::Register(Register::Rax), Operand::Register(Register::Rcx));
+                let (big, done) = (self.next_label(), self.next_label());
+                self.gen_float_mov(xmm1.clone(), limit, size);
+                self.gen_ins(cutils::sse_opcode(size, Opcode::Ucomiss, Opcode::Ucomisd), vec![val.clone(), xmm1.clone()]);
+                self.gen_ins(Opcode::JAe, vec![Operand::Ident(big.clone())]);
+                self.gen_ins(cvt, vec![rax.clone(), val.clone()]);
+                self.gen_ins(Opcode::Jmp, vec![Operand::Ident(done.clone())]);
+                self.gen_internal_label(big);
+                self.gen_ins(cutils::sse_opcode(size, Opcode::Subss, Opcode::Subsd), vec![val.clone(), xmm1]);
+                self.gen_ins(cvt, vec![rax.clone(), val]);
+                self.gen_mov_ins(rcx.clone(), Operand::Literal(Literal::Int64(i64::MIN)));
+                self.gen_ins(Opcode::XOr, vec![rax, rcx]);
+                self.gen_internal_label(done);
+            }
+        }
+        Operand::Register(target)
+    }
+
+    /// Loads the left value into the accumulator and returns it together with
+    /// an operand for the right value that can be used as the second argument
+    /// of a binary instruction
+    fn gen_binary_operands(&mut self, left: &'c IRExpr, right: &'c IRExpr) -> (Register, Operand) {
+        let left = self.gen_load(left, Register::Rax);
+        let right = match right {
+            IRExpr::Ident(_) => self.gen_expr(right),
+            // 64 bit immediates can only be used with `mov`
+            IRExpr::Literal(..) if self.size_of(&self.type_of(right)) < 8 => self.gen_expr(right),
+            _ => {
+                let acc = Operand::Register(Register::Rax);
+                self.out.push(AsmElement::Instruction(Instruction {
+                    opcode: Opcode::Push,
+                    args: vec![acc.clone()],
+                }));
+                let right = self.gen_load(right, Register::Rcx);
+                self.out.push(AsmElement::Instruction(Instruction {
+                    opcode: Opcode::Pop,
+                    args: vec![acc],
+                }));
+                Operand::Register(right)
+            }
+        };
+        (left, right)
+    }
+
+    /// Loads the left float into xmm0 and returns it together with
+    /// an operand for the right float that can be used as the second
+    /// argument of an SSE instruction
+    fn gen_float_operands(&mut self, left: &'c IRExpr, right: &'c IRExpr) -> (Register, Operand) {
+        let _type = self.type_of(left);
+        let size = self.size_of(&_type);
+        let left = self.gen_float_load(left, Register::Xmm0);
+        let right = match right {
+            IRExpr::Ident(_) | IRExpr::Literal(..) => self.gen_expr(right),
+            _ => {
+                // No xmm register is preserved across calls, so the left float waits on the stack
+                let slot = cutils::get_stack_location(self.alloc_stack(&_type));
+                self.gen_float_mov(slot.clone(), Operand::Register(left), size);
+                let right = self.gen_float_load(right, Register::Xmm1);
+                self.gen_float_mov(Operand::Register(left), slot, size);
+                Operand::Register(right)
+            }
+        };
+        (left, right)
+    }
+
+    /// Evaluates the float expression and moves the result into the xmm register
+    fn gen_float_load(&mut self, node: &'c IRExpr, reg: Register) -> Register {
+        let size = self.size_of(&self.type_of(node));
+        let val = self.gen_expr(node);
+        self.gen_float_mov(Operand::Register(reg), val, size);
+        reg
+    }
+
+    /// Evaluates the expression and moves the result into the variant
+    /// of `reg` that matches the size of the expression's type
+    fn gen_load(&mut self, node: &'c IRExpr, reg: Register) -> Register {
+        let size = self.size_of(&self.type_of(node));
+        let reg = cutils::register_by_size(reg, size as u8);
+        let val = self.gen_expr(node);
+        self.gen_mov_ins(Operand::Register(reg), val);
+        reg
+    }
+
+    /// Returns the type of the value that the expression evaluates to
+    fn type_of(&self, node: &'c IRExpr<'c>) -> Type<'c> {
+        match node {
+            IRExpr::Literal(_, _type) => *_type,
+            IRExpr::Ident(_) | IRExpr::Field(_) | IRExpr::Index(_) => *self.place_type(node),
+            IRExpr::Alloca(alloca) => Type::Pointer(&alloca._type),
+            IRExpr::Load(load) => load._type,
+            IRExpr::AddrOf(addr_of) => Type::Pointer(self.place_type(&addr_of.val)),
+            IRExpr::Call(call) => *self
+                .functions
+                .get(call.name)
+                .unwrap_or_else(|| panic!("Could not find function with name {:?}", call.name)),
+            IRExpr::ArithOp(op) => self.type_of(&op.values.0),
+            IRExpr::CmpOp(_) => INT1_T,
+            IRExpr::Not(not) => self.type_of(&not.val),
+            IRExpr::Cast(cast) => cast._type,
+            IRExpr::Extract(extract) => *self.lanes_of(self.type_of(&extract.vector)).0,
+            IRExpr::Insert(insert) => self.type_of(&insert.vector),
+            IRExpr::Shuffle(shuffle) => {
+                let (elem_type, _) = self.lanes_of(self.type_of(&shuffle.values.0));
+                Type::Vector(elem_type, shuffle.mask.len() as u32)
+            }
+            IRExpr::StructInit(init) => Type::Ident(init.name),
+        }
+    }
+
     fn gen_return(&mut self, node: &'c ReturnStmt) {
-        let val = self.gen_expr(&node.ret_val);
-        self.out
-            .push(cutils::gen_mov_ins(Operand::Register(Register::Rax), val));
-        self.out.push(cutils::destroy_stackframe());
+        if self.type_of(&node.ret_val).is_float() {
+            self.gen_float_load(&node.ret_val, Register::Xmm0);
+        } else {
+            self.gen_load(&node.ret_val, Register::Rax);
+        }
+        let stack_frame = cutils::destroy_stackframe();
+        self.out.push(stack_frame.0);
+        self.out.push(stack_frame.1);
         self.out.push(cutils::gen_ret());
     }
 
     fn gen_exit(&mut self, node: &'c ExitStmt) {
-        let expr = self.gen_expr(&node.exit_code);
-        self.out
-            .push(cutils::gen_mov_ins(Operand::Register(Register::Rdi), expr));
+        if self.type_of(&node.exit_code) == Type::Void {
+            // Exiting with the result of a void function, like `exit call %main()`, succeeds
+            self.gen_expr(&node.exit_code);
+            self.gen_mov_ins(
+                Operand::Register(Register::Edi),
+                Operand::Literal(Literal::Int32(0)),
+            );
+        } else {
+            self.gen_load(&node.exit_code, Register::Rdi);
+        }
         self.gen_mov_ins(
             Operand::Register(Register::Rax),
             Operand::Literal(Literal::Int32(60)),
@@ -225,27 +1408,25 @@ impl<'c> CodeGenerator<'c> {
     }
 
     fn gen_variable(&mut self, node: &'c VarStmt) {
-        let size = self.size_of(&node.name._type);
-        let mut val = self.gen_expr(&node.val);
-        // FIXME: This is a hack to ensure that the size does not get decremented for arrays
-        if let Type::Ident(_) = node.name._type {
-            self.stack_pointer -= size as i32
-        }
-
-        if let Operand::Literal(lit) = val {
-            val = Operand::SizedLiteral(cutils::literal_to_sized_literal(lit)
-                .expect("Failed to convert literal to sized literal, most likely caused due to usage of float which are not supported yet"))
+        let val = self.gen_expr(&node.val);
+        let pos = match (&node.val, val) {
+            // Structs are initialized in place, so the variable only has to refer to their location.
+            // Their fields may have allocated temporaries after it, so the stack pointer can be further down
+            (IRExpr::StructInit(_), Operand::MemAddr(MemAddr::RegisterPos(Register::Rbp, pos))) => pos,
+            (_, val) => {
+                let pos = self.alloc_stack(&node.name._type);
+                self.gen_store_val(cutils::get_stack_location(pos), val, &node.name._type);
+                pos
+            }
         };
 
-        if let Operand::SizedLiteral(SizedLiteral(lit, DataSize::QWord)) = val {
-            self.gen_mov_ins(Operand::Register(Register::Rax), Operand::Literal(lit));
-            val = Operand::Register(Register::Rax);
-        }
-
-        self.gen_mov_ins(cutils::get_stack_location(self.stack_pointer), val);
+        self.symbol_table.insert(node.name.ident, (pos, &node.name._type));
+    }
 
-        self.symbol_table
-            .insert(&node.name.ident, self.stack_pointer);
+    fn gen_assign(&mut self, node: &'c AssignStmt) {
+        let (addr, _type) = self.symbol(node.name);
+        let val = self.gen_expr(&node.val);
+        self.gen_store_val(Operand::MemAddr(addr), val, _type);
     }
 
     fn gen_function(&mut self, node: &'c FuncStmt) {
@@ -258,12 +1439,31 @@ impl<'c> CodeGenerator<'c> {
         self.out.push(stack_frame.0);
         self.out.push(stack_frame.1);
 
+        self.stack_pointer = 0;
+        self.symbol_table.clear();
+        let frame_index = self.out.len();
+
         self.gen_args(node);
 
         for stmt in &node.block.stmts {
             self.gen_stmt(stmt);
         }
 
+        // Reserve the space of the locals so pushes and calls do not overwrite them
+        let frame_size = (-self.stack_pointer + 15) & !15;
+        if frame_size > 0 {
+            self.out.insert(
+                frame_index,
+                AsmElement::Instruction(Instruction {
+                    opcode: Opcode::Sub,
+                    args: vec![
+                        Operand::Register(Register::Rsp),
+                        Operand::Literal(Literal::Int32(frame_size)),
+                    ],
+                }),
+            );
+        }
+
         if let Some(elem) = self.out.last() {
             match elem {
                 AsmElement::Instruction(Instruction {
@@ -271,65 +1471,151 @@ impl<'c> CodeGenerator<'c> {
                     ..
                 }) => (),
                 _ => {
-                    self.out.push(cutils::destroy_stackframe());
+                    let stack_frame = cutils::destroy_stackframe();
+                    self.out.push(stack_frame.0);
+                    self.out.push(stack_frame.1);
                     self.out.push(cutils::gen_ret());
                 }
             }
         }
     }
 
+    /// Moves a value into memory, going through a register if it cannot be moved directly
+    fn gen_store_val(&mut self, target: Operand, mut val: Operand, _type: &Type<'c>) {
+        let size = self.size_of(_type);
+        if matches!(_type, Type::Array(..) | Type::Vector(..) | Type::Ident(_)) {
+            match (target, val) {
+                (Operand::MemAddr(target), Operand::MemAddr(val)) => self.gen_copy(target, val, _type),
+                (_, val) => panic!("Expected an aggregate in memory, received {val} instead"),
+            }
+            return;
+        }
+        if _type.is_float() {
+            if let Operand::MemAddr(_) = val {
+                self.gen_float_mov(Operand::Register(Register::Xmm0), val, size);
+                val = Operand::Register(Register::Xmm0);
+            }
+            self.gen_float_mov(target, val, size);
+            return;
+        }
+
+        if let Operand::Literal(lit) = val {
+            val = Operand::SizedLiteral(cutils::literal_to_sized_literal(lit))
+        };
+
+        if let Operand::SizedLiteral(SizedLiteral(lit, DataSize::QWord)) = val {
+            self.gen_mov_ins(Operand::Register(Register::Rax), Operand::Literal(lit));
+            val = Operand::Register(Register::Rax);
+        }
+
+        // There is no memory to memory mov
+        if let Operand::MemAddr(_) = val {
+            let reg = Operand::Register(cutils::register_by_size(Register::Rax, size as u8));
+            self.gen_mov_ins(reg.clone(), val);
+            val = reg;
+        }
+
+        self.gen_mov_ins(target, val);
+    }
+
     fn gen_struct_init(&mut self, node: &'c StructInitExpr) -> Operand {
-        let size = self.size_of(&ir::Type::Ident(node.name));
-        self.gen_mov_ins(
-            cutils::get_stack_location(self.stack_pointer - size as i32),
-            Operand::Literal(Literal::Int32(0)),
-        );
-        self.stack_pointer -= size as i32;
-        // TODO: Use type suffixes for this
-        for (i, val) in node.values.iter().enumerate() {
-            let fields = &self.types.get(&node.name).unwrap().1;
-            let _field = &fields[i];
-            let expr = self.gen_expr(val);
-            self.gen_mov_ins(cutils::get_stack_location(0), expr);
+        let _type = Type::Ident(node.name);
+        let pos = self.alloc_stack(&_type);
+        let types = self.types;
+        let fields = &types
+            .get(node.name)
+            .unwrap_or_else(|| panic!("Could not find type with the name {}", node.name))
+            .1;
+        for (val, field) in node.values.iter().zip(fields) {
+            let (offset, field_type) = self.field_of(_type, field.ident);
+            let val = self.gen_expr(val);
+            self.gen_store_val(cutils::get_stack_location(pos + offset as i32), val, field_type);
         }
-        todo!()
+        cutils::get_stack_location(pos)
     }
 
     fn gen_args(&mut self, node: &'c FuncStmt) {
-        for (i, expr) in node.args.iter().enumerate() {
+        let (mut ints, mut floats) = (0, 0);
+        for expr in &node.args {
             let size = self.size_of(&expr._type);
-            self.gen_mov_ins(
-                cutils::get_stack_location(self.stack_pointer - size as i32),
-                Operand::Register(
-                    cutils::arg_regs_by_size(size.try_into().expect("Failed to convert u32 to u8"))
-                        [i],
-                ),
-            );
-            self.stack_pointer -= size as i32;
-            self.symbol_table.insert(&expr.ident, self.stack_pointer);
+            let pos = self.alloc_stack(&expr._type);
+            if expr._type.is_float() {
+                self.gen_float_mov(
+                    cutils::get_stack_location(pos),
+                    Operand::Register(FUNCTION_FLOAT_ARG_REGISTERS[floats]),
+                    size,
+                );
+                floats += 1;
+            } else {
+                self.gen_mov_ins(
+                    cutils::get_stack_location(pos),
+                    Operand::Register(
+                        cutils::arg_regs_by_size(size.try_into().expect("Failed to convert u32 to u8"))
+                            [ints],
+                    ),
+                );
+                ints += 1;
+            }
+            self.symbol_table.insert(expr.ident, (pos, &expr._type));
         }
     }
 
     fn gen_call_args(&mut self, node: &'c CallExpr) {
-        for (i, expr) in node.args.iter().enumerate() {
+        let mut floats = Vec::new();
+        let mut ints = 0;
+        for expr in &node.args {
+            let _type = self.type_of(expr);
+            if _type.is_float() {
+                // Evaluating the other arguments can overwrite the xmm registers,
+                // so computed floats wait on the stack until all arguments are evaluated
+                let size = self.size_of(&_type);
+                let val = match expr {
+                    IRExpr::Ident(_) | IRExpr::Literal(..) => self.gen_expr(expr),
+                    _ => {
+                        let val = self.gen_float_load(expr, Register::Xmm0);
+                        let slot = cutils::get_stack_location(self.alloc_stack(&_type));
+                        self.gen_float_mov(slot.clone(), Operand::Register(val), size);
+                        slot
+                    }
+                };
+                floats.push((val, size));
+                continue;
+            }
             let val = self.gen_expr(expr);
             self.gen_mov_ins(
-                Operand::Register(cutils::arg_regs_by_size(val.size())[i]),
+                Operand::Register(cutils::arg_regs_by_size(val.size())[ints]),
                 val,
             );
+            ints += 1;
+        }
+        for (reg, (val, size)) in FUNCTION_FLOAT_ARG_REGISTERS.into_iter().zip(floats) {
+            self.gen_float_mov(Operand::Register(reg), val, size);
         }
     }
 
     fn gen_print(&mut self, node: &'c CallExpr) {
-        let arg = self.gen_expr(
-            node.args
-                .first()
-                .expect("Print function neeeds at least one argument"),
-        );
-        self.gen_mov_ins(Operand::Register(Register::Rsi), arg);
+        let arg_node = node
+            .args
+            .first()
+            .expect("Print function neeeds at least one argument");
+        let arg = self.gen_expr(arg_node);
+        // Arrays are passed by address and bound the length, pointers are read up to a NUL byte
+        let max_len = match (self.type_of(arg_node), arg) {
+            (Type::Array(_, len), Operand::MemAddr(addr)) => {
+                self.gen_ins(
+                    Opcode::Lea,
+                    vec![Operand::Register(Register::Rsi), Operand::MemAddr(addr)],
+                );
+                len as i64
+            }
+            (_, arg) => {
+                self.gen_mov_ins(Operand::Register(Register::Rsi), arg);
+                i64::MAX
+            }
+        };
         self.gen_mov_ins(
             Operand::Register(Register::Rdx),
-            Operand::Literal(Literal::Int8(8)),
+            Operand::Literal(Literal::Int64(max_len)),
         );
         self.out.push(cutils::gen_call("print"));
         self.defined_functions.insert(StdFunction::Print);
@@ -343,54 +1629,47 @@ impl<'c> CodeGenerator<'c> {
 
     /// Returns the size of the type in bytes
     fn size_of(&self, _type: &ir::Type<'c>) -> u32 {
-        // The type or array is an integer type/array
-        match _type {
-            Type::Ident(ident @ (INT8_T | INT16_T | INT32_T | INT64_T)) => {
-                return cutils::int_size(*ident) as u32;
-            }
-            Type::Array(Type::Ident(ident @ (INT8_T | INT16_T | INT32_T | INT64_T)), size) => {
-                return cutils::int_size(*ident) as u32 * *size;
-            }
-            _ => (),
-        }
+        self.layout.size_of(_type)
+    }
 
-        let type_name = match _type {
-            Type::Ident(ident) => ident,
-            Type::Array(ident, _) => match ident {
-                Type::Ident(id) => id,
-                Type::Array(id, _) => return self.size_of(id),
-            },
-        };
+    /// Reserves an aligned stack slot for a value of the type and returns its position
+    fn alloc_stack(&mut self, _type: &ir::Type<'c>) -> i32 {
+        let layout = self.layout.layout_of(_type);
+        let top = layout::align_to((-self.stack_pointer) as u32 + layout.size, layout.align);
+        self.stack_pointer = -(top as i32);
+        self.stack_pointer
+    }
 
-        let cdt = self
-            .types
-            .get(type_name)
-            .unwrap_or_else(|| panic!("Could not find type with the name {}", _type));
-        let mut size: u32 = 0;
-        match cdt.0 {
-            // Add sizes if cdt is a struct
-            CompositeDataType::Struct => {
-                for field in &cdt.1 {
-                    size += self.size_of(&field._type);
-                }
-            }
-            // Use largest size if cdt is a union
-            CompositeDataType::Union => {
-                for variant in &cdt.1 {
-                    let size1 = self.size_of(&variant._type);
-                    if size1 > size {
-                        size = size1;
-                    }
-                }
-            }
-        }
-        match _type {
-            Type::Ident(_) => size,
-            Type::Array(_, arr_size) => size * *arr_size,
+    /// Moves a float between xmm registers and memory
+    fn gen_float_mov(&mut self, target: Operand, val: Operand, size: u32) {
+        if target != val {
+            self.out.push(AsmElement::Instruction(Instruction {
+                opcode: cutils::sse_opcode(size, Opcode::Movss, Opcode::Movsd),
+                args: vec![target, val],
+            }));
         }
     }
 
     fn gen_mov_ins(&mut self, target: Operand, val: Operand) {
+        let (target, val) = match (target, val) {
+            (Operand::Register(target), Operand::Register(val)) if target.size() < val.size() => (
+                Operand::Register(target),
+                Operand::Register(cutils::register_by_size(val, target.size())),
+            ),
+            // Writing to a 32 bit register already clears the upper half
+            (Operand::Register(target), Operand::Register(val)) if val.size() == 4 => (
+                Operand::Register(cutils::register_by_size(target, 4)),
+                Operand::Register(val),
+            ),
+            (Operand::Register(target), Operand::Register(val)) if target.size() > val.size() => {
+                self.out.push(AsmElement::Instruction(Instruction {
+                    opcode: Opcode::Movzx,
+                    args: vec![Operand::Register(target), Operand::Register(val)],
+                }));
+                return;
+            }
+            ops => ops,
+        };
         if target != val {
             self.out.push(cutils::gen_mov_ins(target, val))
         }
@@ -28,7 +28,30 @@ pub struct Directive {
 #[derive(Debug, Clone, PartialEq)]
 pub enum Declaration {
     Global(String),
-    DefineBytes(String, Literal, Option<u8>),
+    /// Defines bytes followed by an optional terminator, for example ``LC0 db `hello`, 0``
+    DefineBytes(String, DataValue, Option<u8>),
+    /// Defines a constant with the given size, for example `LC0 dq 1.5`
+    Define(String, DataSize, Literal),
+    /// Labels the values that follow, which is used for aggregates that span multiple lines
+    Label(String),
+    /// Defines unlabeled values of the same size, for example `dd 1,2,3`
+    Values(DataSize, Vec<DataValue>),
+    /// Repeats a value, for example `times 4 db 0`
+    Times(u32, DataSize, Literal),
+    /// Reserves the given number of uninitialized bytes in `.bss`, for example `buf resb 16`
+    Reserve(String, u32),
+    /// Pads the section until the next declaration is aligned to the given number of bytes
+    Align(u32),
+    /// Like [Declaration::Align] but for `.bss`, which cannot contain padding bytes
+    AlignB(u32),
+}
+
+/// Value of a data definition
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataValue {
+    Literal(Literal),
+    /// Bytes that are written as a string
+    String(Vec<u8>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -46,7 +69,12 @@ pub struct Instruction {
 pub enum DirectiveType {
     Data,
     Rodata,
+    /// Zero-initialized data that takes no space in the executable
+    Bss,
     Text,
+    /// Attributes the following lines to a line of a source file,
+    /// which is used by NASM for error messages and debug information
+    Line { line: u32, file: String },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -75,6 +103,8 @@ pub enum MemAddr {
     RegisterPos(Register, i32),
     Literal(Literal),
     Ident(String),
+    /// Label plus offset relative to the instruction pointer, for example `[rel x+4]`
+    RipRelative(String, i32),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -151,6 +181,7 @@ pub enum Register {
     R15w,
 
     // 8 bit
+    Ah,
     Al,
     Bl,
     Cl,
@@ -169,17 +200,51 @@ pub enum Register {
     R13b,
     R14b,
     R15b,
+
+    // 128 bit SSE
+    Xmm0,
+    Xmm1,
+    Xmm2,
+    Xmm3,
+    Xmm4,
+    Xmm5,
+    Xmm6,
+    Xmm7,
+    Xmm8,
+    Xmm9,
+    Xmm10,
+    Xmm11,
+    Xmm12,
+    Xmm13,
+    Xmm14,
+    Xmm15,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Opcode {
     Mov,
+    /// Load the effective address of a memory operand
+    Lea,
+    Movzx,
+    Movsx,
+    Movsxd,
     Syscall,
 
     Add,
     Sub,
     Mul,
+    IMul,
     Div,
+    IDiv,
+
+    /// Sign extend al into ax
+    Cbw,
+    /// Sign extend ax into dx:ax
+    Cwd,
+    /// Sign extend eax into edx:eax
+    Cdq,
+    /// Sign extend rax into rdx:rax
+    Cqo,
 
     And,
     Or,
@@ -192,6 +257,25 @@ pub enum Opcode {
     JNe,
     JZ,
     JNz,
+    JL,
+    JLe,
+    JG,
+    JGe,
+    JB,
+    JBe,
+    JA,
+    JAe,
+
+    SetE,
+    SetNe,
+    SetL,
+    SetLe,
+    SetG,
+    SetGe,
+    SetB,
+    SetBe,
+    SetA,
+    SetAe,
 
     Call,
     Ret,
@@ -201,6 +285,8 @@ pub enum Opcode {
 
     Shl,
     Shr,
+    /// Arithmetic shift right
+    Sar,
 
     Movsb,
     Movsw,
@@ -216,6 +302,63 @@ pub enum Opcode {
 
     Dec,
     Inc,
+
+    // SSE scalar instructions, the `ss` variants operate on
+    // single precision floats and the `sd` variants on double precision ones
+    Movss,
+    Movsd,
+    /// Moves 32 bits between a general purpose and an xmm register
+    Movd,
+    /// Moves 64 bits between a general purpose and an xmm register
+    Movq,
+    Addss,
+    Addsd,
+    Subss,
+    Subsd,
+    Mulss,
+    Mulsd,
+    Divss,
+    Divsd,
+    /// Compares floats and sets the flags like an unsigned comparison
+    Ucomiss,
+    Ucomisd,
+    /// Converts a signed integer to a float
+    Cvtsi2ss,
+    Cvtsi2sd,
+    /// Converts a float to a signed integer, rounding towards zero
+    Cvttss2si,
+    Cvttsd2si,
+
+    // SSE2 packed instructions that operate on every lane of an xmm register
+    /// Moves 128 bits of integers without requiring an aligned address
+    Movdqu,
+    /// Moves 128 bits of floats without requiring an aligned address
+    Movups,
+    Paddb,
+    Paddw,
+    Paddd,
+    Paddq,
+    Psubb,
+    Psubw,
+    Psubd,
+    Psubq,
+    /// Multiplies 16 bit lanes and keeps the low half of the products
+    Pmullw,
+    Pand,
+    Por,
+    Pxor,
+    Addps,
+    Addpd,
+    Subps,
+    Subpd,
+    Mulps,
+    Mulpd,
+    Divps,
+    Divpd,
+    /// Reorders the 32 bit lanes of a register as selected by an immediate
+    Pshufd,
+    /// Takes two 32 bit lanes from each register as selected by an immediate
+    Shufps,
 }
 
 pub trait BuiltinFunction {
@@ -246,37 +389,47 @@ impl BuiltinFunction for StdFunction {
 pub(super) mod builtins {
     use crate::asm::{codegen::CodeGenerator, utils::codegen as cutils};
 
-    use super::{AsmElement, Declaration, Label, Literal, MemAddr, Operand, Register};
+    use super::{AsmElement, Instruction, Label, Literal, MemAddr, Opcode, Operand, Register};
 
+    /// Writes the string at rsi to stdout. At most rdx bytes are written,
+    /// stopping early at the first NUL byte
     pub fn generate_print(codegen: &mut CodeGenerator) {
         let out = &mut codegen.out;
 
-        codegen.data.push(Declaration::DefineBytes(
-            "msg".into(),
-            Literal::Int8(8),
-            None,
-        ));
+        let ins = |opcode, args| AsmElement::Instruction(Instruction { opcode, args });
+        let label = |name: &str| {
+            AsmElement::Label(Label {
+                name: name.to_string(),
+            })
+        };
+        let reg = Operand::Register;
 
         let instructions = vec![
-            AsmElement::Label(Label {
-                name: "print".to_string(),
-            }),
+            label("print"),
+            cutils::gen_mov_ins(reg(Register::Rdi), reg(Register::Rsi)),
+            label(".next"),
+            cutils::gen_mov_ins(reg(Register::Rcx), reg(Register::Rdi)),
+            ins(Opcode::Sub, vec![reg(Register::Rcx), reg(Register::Rsi)]),
+            ins(Opcode::Cmp, vec![reg(Register::Rcx), reg(Register::Rdx)]),
+            ins(Opcode::JAe, vec![Operand::Ident(".write".to_string())]),
             cutils::gen_mov_ins(
-                Operand::MemAddr(MemAddr::Ident("msg".to_string())),
-                Operand::Register(Register::Rsi),
+                reg(Register::Al),
+                Operand::MemAddr(MemAddr::Register(Register::Rdi)),
             ),
-            cutils::gen_mov_ins(
-                Operand::Register(Register::Rsi),
-                Operand::Ident("msg".to_string()),
+            ins(
+                Opcode::Cmp,
+                vec![reg(Register::Al), Operand::Literal(Literal::Int8(0))],
             ),
-            cutils::gen_mov_ins(
-                Operand::Register(Register::Rax),
-                Operand::Literal(Literal::Int32(1)),
-            ),
-            cutils::gen_mov_ins(
-                Operand::Register(Register::Rdi),
-                Operand::Literal(Literal::Int32(1)),
+            ins(Opcode::JE, vec![Operand::Ident(".write".to_string())]),
+            ins(
+                Opcode::Add,
+                vec![reg(Register::Rdi), Operand::Literal(Literal::Int8(1))],
             ),
+            ins(Opcode::Jmp, vec![Operand::Ident(".next".to_string())]),
+            label(".write"),
+            cutils::gen_mov_ins(reg(Register::Rdx), reg(Register::Rcx)),
+            cutils::gen_mov_ins(reg(Register::Rax), Operand::Literal(Literal::Int32(1))),
+            cutils::gen_mov_ins(reg(Register::Rdi), Operand::Literal(Literal::Int32(1))),
             cutils::gen_syscall(),
             cutils::gen_ret(),
         ];
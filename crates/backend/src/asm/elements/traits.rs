@@ -3,7 +3,7 @@
 use std::fmt::Display;
 
 use crate::asm::{elements::{
-    AsmElement, Declaration, Directive, DirectiveType, Instruction, Label, Literal, MemAddr, Opcode, Operand,
+    AsmElement, DataValue, Declaration, Directive, DirectiveType, Instruction, Label, Literal, MemAddr, Opcode, Operand,
     Register,
 }, utils};
 
@@ -37,11 +37,42 @@ impl Display for Declaration {
                         Some(terminator) => format!(", {}", terminator),
                         None => String::new()
                     }),
+                Declaration::Define(ident, size, lit) => format!("{ident} {} {lit}", size.directive()),
+                Declaration::Label(ident) => format!("{ident}:"),
+                Declaration::Values(size, vals) => format!(
+                    "{} {}",
+                    size.directive(),
+                    vals.iter().map(|val| val.to_string()).collect::<Vec<_>>().join(",")
+                ),
+                Declaration::Times(count, size, lit) => format!("times {count} {} {lit}", size.directive()),
+                Declaration::Reserve(ident, size) => format!("{ident} resb {size}"),
+                Declaration::Align(align) => format!("align {align}"),
+                Declaration::AlignB(align) => format!("alignb {align}"),
             }
         )
     }
 }
 
+impl Display for DataValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataValue::Literal(lit) => write!(f, "{lit}"),
+            // Backquoted strings support escape sequences in NASM
+            DataValue::String(bytes) => {
+                write!(f, "`")?;
+                for byte in bytes {
+                    match byte {
+                        b'`' | b'\\' => write!(f, "\\{}", *byte as char)?,
+                        b' '..=b'~' => write!(f, "{}", *byte as char)?,
+                        byte => write!(f, "\\x{byte:02x}")?,
+                    }
+                }
+                write!(f, "`")
+            }
+        }
+    }
+}
+
 impl Display for Label {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}:", self.name)
@@ -65,15 +96,13 @@ impl Display for Instruction {
 
 impl Display for Directive {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "section .{}",
-            match self._type {
-                DirectiveType::Data => "data",
-                DirectiveType::Rodata => "rodata",
-                DirectiveType::Text => "text",
-            },
-        )
+        match &self._type {
+            DirectiveType::Data => write!(f, "section .data"),
+            DirectiveType::Rodata => write!(f, "section .rodata"),
+            DirectiveType::Bss => write!(f, "section .bss"),
+            DirectiveType::Text => write!(f, "section .text"),
+            DirectiveType::Line { line, file } => write!(f, "%line {line}+0 {file}"),
+        }
     }
 }
 
@@ -171,6 +200,7 @@ impl Display for Register {
                 Register::R14w => "r14w",
                 Register::R15w => "r15w",
 
+                Register::Ah => "ah",
                 Register::Al => "al",
                 Register::Bl => "bl",
                 Register::Cl => "cl",
@@ -187,6 +217,23 @@ impl Display for Register {
                 Register::R13b => "r13b",
                 Register::R14b => "r14b",
                 Register::R15b => "r15b",
+
+                Register::Xmm0 => "xmm0",
+                Register::Xmm1 => "xmm1",
+                Register::Xmm2 => "xmm2",
+                Register::Xmm3 => "xmm3",
+                Register::Xmm4 => "xmm4",
+                Register::Xmm5 => "xmm5",
+                Register::Xmm6 => "xmm6",
+                Register::Xmm7 => "xmm7",
+                Register::Xmm8 => "xmm8",
+                Register::Xmm9 => "xmm9",
+                Register::Xmm10 => "xmm10",
+                Register::Xmm11 => "xmm11",
+                Register::Xmm12 => "xmm12",
+                Register::Xmm13 => "xmm13",
+                Register::Xmm14 => "xmm14",
+                Register::Xmm15 => "xmm15",
             }
         )
     }
@@ -201,11 +248,16 @@ impl Display for MemAddr {
                 MemAddr::Register(reg) => reg.to_string(),
                 MemAddr::Literal(lit) => lit.to_string(),
                 MemAddr::RegisterPos(reg, pos) => format!("{}{}", reg, if *pos != 0 {
-                    pos.to_string()
+                    format!("{pos:+}")
                 } else {
                     String::new()
                 }),
                 MemAddr::Ident(ident) => ident.to_string(),
+                MemAddr::RipRelative(ident, pos) => format!("rel {ident}{}", if *pos != 0 {
+                    format!("{pos:+}")
+                } else {
+                    String::new()
+                }),
             }
         )
     }
@@ -221,13 +273,30 @@ impl Display for Literal {
                 Literal::Int16(int) => int.to_string(),
                 Literal::Int32(int) => int.to_string(),
                 Literal::Int64(int) => int.to_string(),
-                Literal::Float32(float) => float.to_string(),
-                Literal::Float64(float) => float.to_string(),
+                Literal::Float32(float) => float_to_string(float),
+                Literal::Float64(float) => float_to_string(float),
             }
         )
     }
 }
 
+/// Formats a float so that NASM does not mistake it for an integer, which requires a period
+fn float_to_string<F: Into<f64> + std::fmt::Debug + Copy>(float: F) -> String {
+    let val: f64 = float.into();
+    if val.is_nan() {
+        return "__QNaN__".into();
+    }
+    if val.is_infinite() {
+        return if val > 0.0 { "__Infinity__" } else { "-__Infinity__" }.into();
+    }
+    // Debug prints the shortest representation that reads back to the same value
+    let str = format!("{float:?}");
+    match str.find('e') {
+        Some(pos) if !str.contains('.') => format!("{}.0{}", &str[..pos], &str[pos..]),
+        _ => str,
+    }
+}
+
 impl Display for Opcode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -235,27 +304,56 @@ impl Display for Opcode {
             "{}",
             match self {
                 Opcode::Mov => "mov",
+                Opcode::Lea => "lea",
+                Opcode::Movzx => "movzx",
+                Opcode::Movsx => "movsx",
+                Opcode::Movsxd => "movsxd",
                 Opcode::Syscall => "syscall",
                 Opcode::Add => "add",
                 Opcode::Sub => "sub",
                 Opcode::Mul => "mul",
-                Opcode::Div => todo!(),
-                Opcode::And => todo!(),
-                Opcode::Or => todo!(),
-                Opcode::XOr => todo!(),
-                Opcode::Not => todo!(),
-                Opcode::Cmp => todo!(),
+                Opcode::IMul => "imul",
+                Opcode::Div => "div",
+                Opcode::IDiv => "idiv",
+                Opcode::Cbw => "cbw",
+                Opcode::Cwd => "cwd",
+                Opcode::Cdq => "cdq",
+                Opcode::Cqo => "cqo",
+                Opcode::And => "and",
+                Opcode::Or => "or",
+                Opcode::XOr => "xor",
+                Opcode::Not => "not",
+                Opcode::Cmp => "cmp",
                 Opcode::Jmp => "jmp",
-                Opcode::JE => todo!(),
-                Opcode::JNe => todo!(),
-                Opcode::JZ => todo!(),
-                Opcode::JNz => todo!(),
+                Opcode::JE => "je",
+                Opcode::JNe => "jne",
+                Opcode::JZ => "jz",
+                Opcode::JNz => "jnz",
+                Opcode::JL => "jl",
+                Opcode::JLe => "jle",
+                Opcode::JG => "jg",
+                Opcode::JGe => "jge",
+                Opcode::JB => "jb",
+                Opcode::JBe => "jbe",
+                Opcode::JA => "ja",
+                Opcode::JAe => "jae",
+                Opcode::SetE => "sete",
+                Opcode::SetNe => "setne",
+                Opcode::SetL => "setl",
+                Opcode::SetLe => "setle",
+                Opcode::SetG => "setg",
+                Opcode::SetGe => "setge",
+                Opcode::SetB => "setb",
+                Opcode::SetBe => "setbe",
+                Opcode::SetA => "seta",
+                Opcode::SetAe => "setae",
                 Opcode::Call => "call",
                 Opcode::Ret => "ret",
                 Opcode::Push => "push",
                 Opcode::Pop => "pop",
-                Opcode::Shl => todo!(),
-                Opcode::Shr => todo!(),
+                Opcode::Shl => "shl",
+                Opcode::Shr => "shr",
+                Opcode::Sar => "sar",
                 Opcode::Movsb => todo!(),
                 Opcode::Movsw => todo!(),
                 Opcode::Int => todo!(),
@@ -267,6 +365,48 @@ impl Display for Opcode {
                 Opcode::FAbs => todo!(),
                 Opcode::Dec => todo!(),
                 Opcode::Inc => todo!(),
+                Opcode::Movss => "movss",
+                Opcode::Movsd => "movsd",
+                Opcode::Movd => "movd",
+                Opcode::Movq => "movq",
+                Opcode::Addss => "addss",
+                Opcode::Addsd => "addsd",
+                Opcode::Subss => "subss",
+                Opcode::Subsd => "subsd",
+                Opcode::Mulss => "mulss",
+                Opcode::Mulsd => "mulsd",
+                Opcode::Divss => "divss",
+                Opcode::Divsd => "divsd",
+                Opcode::Ucomiss => "ucomiss",
+                Opcode::Ucomisd => "ucomisd",
+                Opcode::Cvtsi2ss => "cvtsi2ss",
+                Opcode::Cvtsi2sd => "cvtsi2sd",
+                Opcode::Cvttss2si => "cvttss2si",
+                Opcode::Cvttsd2si => "cvttsd2si",
+                Opcode::Movdqu => "movdqu",
+                Opcode::Movups => "movups",
+                Opcode::Paddb => "paddb",
+                Opcode::Paddw => "paddw",
+                Opcode::Paddd => "paddd",
+                Opcode::Paddq => "paddq",
+                Opcode::Psubb => "psubb",
+                Opcode::Psubw => "psubw",
+                Opcode::Psubd => "psubd",
+                Opcode::Psubq => "psubq",
+                Opcode::Pmullw => "pmullw",
+                Opcode::Pand => "pand",
+                Opcode::Por => "por",
+                Opcode::Pxor => "pxor",
+                Opcode::Addps => "addps",
+                Opcode::Addpd => "addpd",
+                Opcode::Subps => "subps",
+                Opcode::Subpd => "subpd",
+                Opcode::Mulps => "mulps",
+                Opcode::Mulpd => "mulpd",
+                Opcode::Divps => "divps",
+                Opcode::Divpd => "divpd",
+                Opcode::Pshufd => "pshufd",
+                Opcode::Shufps => "shufps",
             }
         )
     }
@@ -290,7 +430,7 @@ impl Size for Register {
             | Register::R12
             | Register::R13
             | Register::R14
-            | Register::R15 => 64,
+            | Register::R15 => 8,
             Register::Eax
             | Register::Ebx
             | Register::Ecx
@@ -306,7 +446,7 @@ impl Size for Register {
             | Register::R12d
             | Register::R13d
             | Register::R14d
-            | Register::R15d => 32,
+            | Register::R15d => 4,
             Register::Ax
             | Register::Bx
             | Register::Cx
@@ -322,8 +462,9 @@ impl Size for Register {
             | Register::R12w
             | Register::R13w
             | Register::R14w
-            | Register::R15w => 16,
-            Register::Al
+            | Register::R15w => 2,
+            Register::Ah
+            | Register::Al
             | Register::Bl
             | Register::Cl
             | Register::Dl
@@ -338,7 +479,23 @@ impl Size for Register {
             | Register::R12b
             | Register::R13b
             | Register::R14b
-            | Register::R15b => 8,
+            | Register::R15b => 1,
+            Register::Xmm0
+            | Register::Xmm1
+            | Register::Xmm2
+            | Register::Xmm3
+            | Register::Xmm4
+            | Register::Xmm5
+            | Register::Xmm6
+            | Register::Xmm7
+            | Register::Xmm8
+            | Register::Xmm9
+            | Register::Xmm10
+            | Register::Xmm11
+            | Register::Xmm12
+            | Register::Xmm13
+            | Register::Xmm14
+            | Register::Xmm15 => 16,
         }
     }
 }
@@ -355,6 +512,18 @@ impl Size for Operand {
     }
 }
 
+impl DataSize {
+    /// Returns the directive that defines data of this size
+    pub fn directive(&self) -> &'static str {
+        match self {
+            DataSize::Byte => "db",
+            DataSize::Word => "dw",
+            DataSize::DWord => "dd",
+            DataSize::QWord => "dq",
+        }
+    }
+}
+
 impl Size for DataSize {
     fn size(&self) -> u8 {
         match self {
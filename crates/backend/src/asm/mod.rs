@@ -7,7 +7,6 @@ pub mod elements;
 pub mod utils;
 mod tests;
 
-use std::process::Output;
 
 use citadel_frontend::ir::irgen::HIRStream;
 
@@ -23,16 +22,32 @@ impl Target for TargetX86_64 {
     fn name(&self) -> &str {
         "x86-64"
     }
+
+    /// Every x86-64 cpu supports SSE2
+    fn vector_size(&self) -> u32 {
+        16
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct AsmBackend<T: Target> {
     target: T,
+    line_directives: bool,
 }
 
 impl<T: Target> AsmBackend<T> {
     pub fn new(target: T) -> Self {
-        Self { target }
+        Self {
+            target,
+            line_directives: false,
+        }
+    }
+
+    /// Emits `%line` directives that map the generated assembly
+    /// back to the source locations of the IR
+    pub fn with_line_directives(mut self, enabled: bool) -> Self {
+        self.line_directives = enabled;
+        self
     }
 }
 
@@ -45,7 +60,7 @@ impl<T: Target> Backend for AsmBackend<T> {
     }
 
     fn generate(&self, ir_stream: HIRStream) -> Self::Output {
-        utils::compile_program(ir_stream, self.target())
+        utils::compile_program(ir_stream, self.target(), self.line_directives)
     }
 
     fn format(&self, output: &Self::Output) -> Option<String> {
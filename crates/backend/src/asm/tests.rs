@@ -1,15 +1,56 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
     use std::{fs, path::PathBuf};
 
     use bumpalo::Bump;
     use citadel_irparser::{IRLexer, IRParser};
 
+    use citadel_frontend::ir::SourceLoc;
+
     use crate::{
-        api::Backend,
+        api::{Backend, Target},
         asm::{utils, AsmBackend, TargetX86_64},
     };
 
+    /// Compiles the source for x86-64 and returns the formatted assembly
+    fn compile(src: &str) -> String {
+        compile_for(TargetX86_64, src)
+    }
+
+    fn compile_for(target: impl Target, src: &str) -> String {
+        let lexer = IRLexer::new(src);
+        let arena = Bump::new();
+        let ir_stream = IRParser::new(&lexer, &arena).parse_program();
+        utils::format(AsmBackend::new(target).generate(ir_stream).as_slice())
+    }
+
+    /// Asserts that the snippets appear in the assembly in the given order without overlapping
+    #[track_caller]
+    fn assert_in_order(asm_code: &str, snippets: &[&str]) {
+        let mut rest = asm_code;
+        for snippet in snippets {
+            match rest.find(snippet) {
+                Some(pos) => rest = &rest[pos + snippet.len()..],
+                None => panic!("Expected {snippet:?} after the previous snippets in:\n{asm_code}"),
+            }
+        }
+    }
+
+    /// Returns the contents of a section up to the next one
+    #[track_caller]
+    fn section<'a>(asm_code: &'a str, name: &str) -> &'a str {
+        let header = format!("section {name}\n");
+        let start = asm_code
+            .find(&header)
+            .unwrap_or_else(|| panic!("Missing section {name} in:\n{asm_code}"))
+            + header.len();
+        let end = asm_code[start..]
+            .find("section ")
+            .map_or(asm_code.len(), |end| start + end);
+        &asm_code[start..end]
+    }
+
     #[test]
     fn test_asm_compiler() {
         let backend = AsmBackend::new(TargetX86_64);
@@ -23,4 +64,580 @@ mod tests {
         let asm_code = backend.generate(ir_stream);
         utils::compiler_output(utils::format(asm_code.as_slice()), PathBuf::from("build/asm/out.asm"));
     }
+
+    #[test]
+    fn test_branch() {
+        let asm_code = compile(
+            "func @main() i32 {
+                br l{1:i32}, 'then, 'else
+                'then:
+                ret l{0:i32}
+                'else:
+                ret l{1:i32}
+            }",
+        );
+        assert_in_order(
+            &asm_code,
+            &["main:\n", "cmp eax,0\n    jnz then\n    jmp else\n", "then:\n", "else:\n"],
+        );
+    }
+
+    #[test]
+    fn test_cmp_op() {
+        let asm_code = compile(
+            "func @main() i1 {
+                $x i32 = l{5:i32}
+                ret cmp ult %x, l{10:i32}
+            }",
+        );
+        assert_in_order(&asm_code, &["main:\n", "mov eax,[rbp-4]\n    cmp eax,dword 10\n    setb al\n"]);
+    }
+
+    #[test]
+    fn test_cast() {
+        let asm_code = compile(
+            "func @main() i64 {
+                $x i8 = l{5:i8}
+                ret cast sext i64, %x
+            }",
+        );
+        assert_in_order(&asm_code, &["main:\n", "mov al,[rbp-1]\n    movsx rax,al\n"]);
+    }
+
+    #[test]
+    fn test_exit() {
+        let asm_code = compile(
+            "entry {
+                $code i32 = l{3:i32}
+                exit %code
+            }",
+        );
+        assert_in_order(&asm_code, &["mov edi,[rbp-4]\n", "mov rax,60\n    syscall\n"]);
+    }
+
+    #[test]
+    fn test_div() {
+        let asm_code = compile(
+            "func @main() i32 {
+                $x i32 = l{17:i32}
+                $y i32 = l{5:i32}
+                ret srem %x, %y
+            }",
+        );
+        assert_in_order(&asm_code, &["main:\n", "cdq\n    idiv ecx\n    mov eax,edx\n"]);
+    }
+
+    #[test]
+    fn test_shift() {
+        let asm_code = compile(
+            "func @main() i32 {
+                $x i32 = l{-16:i32}
+                $y i8 = l{2:i8}
+                $z i32 = ashr %x, %y
+                ret not shl %z, l{1:i32}
+            }",
+        );
+        assert_in_order(
+            &asm_code,
+            &["main:\n", "mov cl,[rbp-5]\n    sar eax,cl\n", "shl eax,1\n    not eax\n"],
+        );
+    }
+
+    #[test]
+    fn test_pointer() {
+        let asm_code = compile(
+            "func @main() i32 {
+                $x i32 = l{5:i32}
+                $p *i32 = addr-of %x
+                store l{7:i32}, %p
+                $q *i32 = alloca i32
+                ret load i32, %p
+            }",
+        );
+        assert_in_order(
+            &asm_code,
+            &[
+                "main:\n",
+                "lea rax,[rbp-4]\n    mov [rbp-16],rax\n",
+                "mov eax,dword 7\n    mov rcx,[rbp-16]\n    mov [rcx],eax\n",
+                "lea rax,[rbp-20]\n",
+                "mov rax,[rbp-16]\n    mov eax,[rax]\n",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_field() {
+        let asm_code = compile(
+            "struct @Point {
+                $x i32,
+                $y i64,
+            }
+            func @main() i64 {
+                $p Point = struct %Point {l{1:i32}, l{2:i64}}
+                store l{3:i64}, addr-of getfield %p, y
+                ret getfield %p, y
+            }",
+        );
+        assert_in_order(
+            &asm_code,
+            &[
+                "main:\n",
+                "mov [rbp-16],dword 1\n    mov rax,2\n    mov [rbp-8],rax\n",
+                "lea rax,[rbp-8]\n",
+                "mov rax,[rbp-8]\n",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_struct_temporaries() {
+        let asm_code = compile(
+            "struct @Inner {
+                $a i32,
+                $b i32,
+            }
+            struct @Outer {
+                $i Inner,
+                $x i32,
+            }
+            func @main() i32 {
+                $s Outer = struct %Outer {struct %Inner {l{1:i32}, l{2:i32}}, l{5:i32}}
+                ret getfield %s, x
+            }",
+        );
+        // The inner struct is built below the outer one, which is still found at its own slot
+        assert_in_order(&asm_code, &["main:\n", "    mov [rbp-4],dword 5\n    mov eax,[rbp-4]\n"]);
+    }
+
+    #[test]
+    fn test_index() {
+        let asm_code = compile(
+            "func @main() i32 {
+                $a *[i32; 4] = alloca [i32; 4]
+                $i i64 = l{1:i64}
+                store l{3:i32}, addr-of getelem %a, l{2:i64}
+                ret getelem %a, %i
+            }",
+        );
+        assert_in_order(
+            &asm_code,
+            &[
+                "main:\n",
+                "mov rax,[rbp-24]\n    lea rax,[rax+8]\n",
+                "mov rcx,[rbp-32]\n    imul rcx,4\n    pop rax\n    add rax,rcx\n    mov eax,[rax]\n",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_unsigned() {
+        let asm_code = compile(
+            "func @main() u32 {
+                $a *[u32; 4] = alloca [u32; 4]
+                $i u8 = l{255:u8}
+                $x u32 = getelem %a, %i
+                ret udiv %x, l{4000000000:u32}
+            }",
+        );
+        assert_in_order(
+            &asm_code,
+            &[
+                "main:\n",
+                "mov cl,[rbp-25]\n    movzx rcx,cl\n    imul rcx,4\n",
+                "xor edx,edx\n    div ecx\n",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_assign() {
+        let asm_code = compile(
+            "func @main() i32 {
+                ?x i32 = l{1:i32}
+                %x = add %x, l{2:i32}
+                ret %x
+            }",
+        );
+        assert_in_order(&asm_code, &["main:\n", "mov eax,[rbp-4]\n    add eax,dword 2\n    mov [rbp-4],eax\n"]);
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let asm_code = compile(
+            r#"func @main() i32 {
+                $s [i8; 4] = l{"\n\0\xff\\":[i8; 4]}
+                ret l{0:i32}
+            }"#,
+        );
+        assert_eq!(section(&asm_code, ".rodata"), "    LC0 db `\\x0a\\x00\\xff\\\\`, 0\n");
+        assert_in_order(&asm_code, &["main:\n", "    mov edx,[rel LC0]\n    mov [rbp-4],edx\n"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot assign a new value to the constant x")]
+    fn test_assign_const() {
+        let lexer = IRLexer::new(
+            "func @main() i32 {
+                $x i32 = l{1:i32}
+                %x = l{2:i32}
+                ret %x
+            }",
+        );
+        let arena = Bump::new();
+        let mut parser = IRParser::new(&lexer, &arena);
+        parser.parse_program();
+    }
+
+    #[test]
+    fn test_line_directives() {
+        let backend = AsmBackend::new(TargetX86_64).with_line_directives(true);
+        let lexer = IRLexer::with_file(
+            "func @main() i32 {
+    $x i32 = l{1:i32}
+    ret add %x,
+        l{2:i32}
+}",
+            "main.chir",
+        );
+        let arena = Bump::new();
+        let mut parser = IRParser::new(&lexer, &arena);
+        let ir_stream = parser.parse_program();
+        assert_eq!(
+            ir_stream.stream[0].loc(),
+            Some(SourceLoc {
+                file: "main.chir",
+                line: 1,
+                col: 1
+            })
+        );
+        let asm_code = utils::format(backend.generate(ir_stream).as_slice());
+        assert!(asm_code.starts_with("%line 1+0 main.chir\nmain:\n"));
+        assert_in_order(
+            &asm_code,
+            &["main:\n", "%line 2+0 main.chir\n    mov [rbp-4],dword 1\n%line 3+0 main.chir\n"],
+        );
+        assert!(!asm_code.contains("%line 4+0"));
+    }
+
+    #[test]
+    fn test_floats() {
+        let asm_code = compile(
+            "func @scale($x f64, $n i32, $y f32) f64 {
+                $f f64 = cast itof f64, %n
+                $g f64 = mul %x, %f
+                br cmp slt %g, l{1e20:f64}, 'ok, 'bad
+                'ok:
+                ret sub %g, l{1.5:f64}
+                'bad:
+                ret l{-0.0:f64}
+            }
+
+            func @main() i32 {
+                $r f64 = call %scale(add l{2.0:f64}, l{1.5:f64}, l{3:i32}, l{0.5:f32})
+                ret cast ftoi i32, %r
+            }",
+        );
+        // 1.5 is only emitted once
+        assert_eq!(
+            section(&asm_code, ".rodata"),
+            "    LC0 dq 1.0e20\n    LC1 dq 1.5\n    LC2 dq -0.0\n    LC3 dq 2.0\n    LC4 dd 0.5\n"
+        );
+        assert_in_order(
+            &asm_code,
+            &[
+                "scale:\n",
+                "movsd [rbp-8],xmm0\n    mov [rbp-12],edi\n    movss [rbp-16],xmm1\n",
+                "cvtsi2sd xmm0,eax\n",
+                "mulsd xmm0,[rbp-24]\n",
+                "ucomisd xmm0,[rel LC0]\n    jb ok\n",
+                "subsd xmm0,[rel LC1]\n",
+                "movsd xmm0,[rel LC2]\n",
+                "main:\n",
+                "addsd xmm0,[rel LC1]\n",
+                "movsd xmm0,[rbp-8]\n    movss xmm1,[rel LC4]\n    call scale\n",
+                "cvttsd2si eax,xmm0\n",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_unsigned_float_casts() {
+        let asm_code = compile(
+            "func @main() i32 {
+                $big u64 = l{18446744073709551615:u64}
+                $f f64 = cast itof f64, %big
+                $back u64 = cast ftoi u64, %f
+                $word u32 = l{4294967295:u32}
+                $g f32 = cast itof f32, %word
+                $w u32 = cast ftoi u32, %g
+                ret l{0:i32}
+            }",
+        );
+        assert_eq!(section(&asm_code, ".rodata"), "    LC0 dq 9.223372036854776e18\n");
+        assert_in_order(
+            &asm_code,
+            &[
+                "main:\n",
+                // u64 to float halves values with the highest bit set
+                "    mov rax,[rbp-8]\n    cmp rax,0\n    jl L@0\n    cvtsi2sd xmm0,rax\n    jmp L@1\n\
+                L@0:\n    mov rcx,rax\n    shr rcx,1\n    and eax,1\n    or rcx,rax\n\
+                \x20   cvtsi2sd xmm0,rcx\n    addsd xmm0,xmm0\nL@1:\n",
+                // Float to u64 subtracts 2^63 from big values and sets the highest bit afterwards
+                "    movsd xmm1,[rel LC0]\n    ucomisd xmm0,xmm1\n    jae L@2\n    cvttsd2si rax,xmm0\n\
+                \x20   jmp L@3\nL@2:\n    subsd xmm0,xmm1\n    cvttsd2si rax,xmm0\n\
+                \x20   mov rcx,-9223372036854775808\n    xor rax,rcx\nL@3:\n",
+                // u32 is zero extended and converted from and to a 64 bit register
+                "    mov eax,[rbp-28]\n    mov eax,eax\n    cvtsi2ss xmm0,rax\n",
+                "    cvttss2si rax,xmm0\n    mov [rbp-36],eax\n",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_vector_fields_behind_pointers() {
+        let src = "struct @Lanes {
+                $v <i32; 4>,
+            }
+            func @main() i32 {
+                $a Lanes = struct %Lanes {l{<1, 2, 3, 4>:<i32; 4>}}
+                $b Lanes = struct %Lanes {l{<5, 6, 7, 8>:<i32; 4>}}
+                $p *Lanes = addr-of %a
+                $q *Lanes = addr-of %b
+                $v <i32; 4> = add getfield %p, v, getfield %q, v
+                ret extractelem %v, 0
+            }";
+        let asm_code = compile(src);
+        // Both fields are copied out before the next pointer is loaded into rax
+        assert_in_order(
+            &asm_code,
+            &[
+                "main:\n",
+                "    mov rax,[rbp-72]\n    movdqu xmm0,[rax]\n    movdqu [rbp-96],xmm0\n\
+                \x20   mov rax,[rbp-80]\n    movdqu xmm0,[rax]\n    movdqu [rbp-112],xmm0\n\
+                \x20   movdqu xmm0,[rbp-96]\n    movdqu xmm1,[rbp-112]\n    paddd xmm0,xmm1\n",
+            ],
+        );
+
+        let asm_code = compile_for(TargetScalar, src);
+        // Lanes are added one by one using eax, so they cannot be read through rax
+        assert_in_order(
+            &asm_code,
+            &[
+                "main:\n",
+                "    mov rax,[rbp-72]\n    mov rdx,[rax]\n    mov [rbp-96],rdx\n",
+                "    mov rax,[rbp-80]\n    mov rdx,[rax]\n    mov [rbp-112],rdx\n",
+                "    mov eax,[rbp-96]\n    add eax,[rbp-112]\n",
+            ],
+        );
+        assert!(!asm_code.contains("eax,[rax"));
+    }
+
+    /// A target without vector registers
+    #[derive(Debug, Default, Clone, Copy)]
+    struct TargetScalar;
+
+    impl Target for TargetScalar {
+        fn name(&self) -> &str {
+            "scalar"
+        }
+    }
+
+    const VECTORS: &str = "func @main() i32 {
+        $a <i32; 4> = l{<1, 2, 3, 4>:<i32; 4>}
+        $b <i32; 4> = add %a, %a
+        $c <i32; 4> = mul %a, %b
+        $d <i32; 4> = shuffle %b, %c, [3,2,1,0]
+        $e <i32; 4> = shuffle %b, %c, [0,1,4,5]
+        $f <f32; 4> = l{<1.5, 2, 3, 4>:<f32; 4>}
+        $g <f32; 4> = mul %f, %f
+        $h <i32; 4> = insertelem %e, l{7:i32}, 0
+        ret extractelem %h, 0
+    }";
+
+    #[test]
+    fn test_vectors() {
+        let asm_code = compile(VECTORS);
+        assert_in_order(
+            &asm_code,
+            &[
+                "main:\n",
+                "    mov [rbp-16],dword 1\n    mov [rbp-12],dword 2\n",
+                "movdqu xmm0,[rbp-32]\n    movdqu xmm1,[rbp-32]\n    paddd xmm0,xmm1\n    movdqu [rbp-48],xmm0\n",
+                // SSE2 has no multiplication of 32 bit lanes
+                "    mov eax,[rbp-28]\n    imul eax,[rbp-60]\n    mov [rbp-76],eax\n",
+                "movdqu xmm0,[rbp-64]\n    pshufd xmm0,xmm0,27\n",
+                "movdqu xmm1,[rbp-96]\n    shufps xmm0,xmm1,68\n",
+                "movups xmm1,[rbp-192]\n    mulps xmm0,xmm1\n",
+                "    mov [rbp-240],dword 7\n",
+                "    mov eax,[rbp-256]\n    mov rsp,rbp\n",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_vectors_scalarized() {
+        let asm_code = compile_for(TargetScalar, VECTORS);
+        assert!(!asm_code.contains("xmm1"));
+        assert_in_order(
+            &asm_code,
+            &[
+                "main:\n",
+                "    mov rdx,[rbp-16]\n    mov [rbp-32],rdx\n",
+                "    mov eax,[rbp-20]\n    add eax,[rbp-20]\n    mov [rbp-36],eax\n",
+                "    mov eax,[rbp-52]\n    mov [rbp-112],eax\n    mov eax,[rbp-56]\n",
+                "    mov eax,[rbp-96]\n    mov [rbp-136],eax\n",
+                "    movss xmm0,[rbp-188]\n    mulss xmm0,[rbp-188]\n    movss [rbp-204],xmm0\n",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_globals() {
+        let asm_code = compile(
+            "$LIMIT i32 = l{100:i32}
+            ?counter i64 = l{0:i64}
+            ?scale f64 = l{1.5:f64}
+            ?table [i32; 4] = l{[0, 0, 0, 0]:[i32; 4]}
+
+            func @main() i32 {
+                %counter = add %counter, l{1:i64}
+                $x i32 = getelem %table, l{2:i32}
+                ret %LIMIT
+            }",
+        );
+        assert!(asm_code.starts_with(
+            "section .bss\n    alignb 8\n    counter resb 8\n    alignb 4\n    table resb 16\n\
+            section .data\n    align 8\n    scale dq 1.5\n\
+            section .rodata\n    align 4\n    LIMIT dd 100\n\
+            section .text\nmain:\n"
+        ));
+        assert_in_order(
+            &asm_code,
+            &[
+                "main:\n",
+                "    mov rax,[rel counter]\n",
+                "    mov [rel counter],rax\n",
+                "    mov eax,[rel table+8]\n",
+                "    mov eax,[rel LIMIT]\n",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_sections() {
+        let asm_code = compile(
+            r#"$NAME [i8; 8] = l{"citadel":[i8; 8]}
+            ?buffer [i8; 8] = l{"hi":[i8; 8]}
+            ?zeros [i8; 8] = l{[0, 0, 0, 0, 0, 0, 0, 0]:[i8; 8]}
+            ?pair [i32; 2] = l{[1, 2]:[i32; 2]}
+
+            func @main() i32 {
+                $s [i8; 4] = l{"abc":[i8; 4]}
+                ret l{0:i32}
+            }"#,
+        );
+        // Zeroed globals are reserved, mutable ones are writable and constants and literals are read-only
+        assert_eq!(section(&asm_code, ".bss"), "    zeros resb 8\n");
+        assert_eq!(
+            section(&asm_code, ".data"),
+            "buffer:\n    db `hi`\n    times 6 db 0\n    align 4\npair:\n    dd 1,2\n"
+        );
+        assert_eq!(
+            section(&asm_code, ".rodata"),
+            "NAME:\n    db `citadel`\n    times 1 db 0\n    LC0 db `abc`, 0\n"
+        );
+        assert_in_order(
+            &asm_code,
+            &["section .bss\n", "section .data\n", "section .rodata\n", "section .text\n"],
+        );
+    }
+
+    #[test]
+    fn test_string_padding() {
+        let asm_code = compile(
+            r#"$GREETING [i8; 4] = l{"hi":[i8; 4]}
+
+            func @main() i32 {
+                $s [i8; 8] = l{"hi":[i8; 8]}
+                $t [i8; 2] = l{"hi":[i8; 2]}
+                ret l{0:i32}
+            }"#,
+        );
+        assert_eq!(
+            section(&asm_code, ".rodata"),
+            "GREETING:\n    db `hi`\n    times 2 db 0\n    \
+             LC0 db `hi`, 0\n    times 5 db 0\n    LC1 db `hi`, 0\n"
+        );
+    }
+
+    #[test]
+    fn test_print_strings() {
+        let asm_code = compile(
+            r#"func @main() i32 {
+                $s [i8; 6] = l{"hello":[i8; 6]}
+                call %print(l{"world":[i8; 6]})
+                call %print(%s)
+                ret l{0:i32}
+            }"#,
+        );
+        assert_in_order(
+            &asm_code,
+            &[
+                "main:\n",
+                "    lea rsi,[rel LC1]\n    mov rdx,6\n    call print\n",
+                "    lea rsi,[rbp-6]\n    mov rdx,6\n    call print\n",
+                "print:\n    mov rdi,rsi\n",
+            ],
+        );
+        assert!(!asm_code.contains("mov rsi,[rel"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The string \"hello\" is longer than its type [i8; 3]")]
+    fn test_string_too_long() {
+        compile(
+            r#"$GREETING [i8; 3] = l{"hello":[i8; 3]}
+
+            func @main() i32 {
+                ret l{0:i32}
+            }"#,
+        );
+    }
+
+    #[test]
+    fn test_constant_data() {
+        let asm_code = compile(
+            r#"struct @Pair {
+                $a i8,
+                $b i32,
+            }
+            $PAIR Pair = struct %Pair {l{1:i8}, l{2:i32}}
+
+            func @main() i32 {
+                $s [i8; 12] = l{"hello world!":[i8; 12]}
+                $t [i8; 12] = l{"hello world!":[i8; 12]}
+                $p *i8 = l{"hi":*i8}
+                $a [i32; 3] = l{[1, 2, 3]:[i32; 3]}
+                ret l{0:i32}
+            }"#,
+        );
+        // The repeated string is only emitted once
+        assert_eq!(
+            section(&asm_code, ".rodata"),
+            "    align 4\nPAIR:\n    db 1\n    times 3 db 0\n    dd 2\n\
+            \x20   LC0 db `hello world!`, 0\n    LC1 db `hi`, 0\n    align 4\nLC2:\n    dd 1,2,3\n"
+        );
+        assert_in_order(
+            &asm_code,
+            &[
+                "main:\n",
+                // The string is copied in chunks that fit its size
+                "    mov rdx,[rel LC0]\n    mov [rbp-12],rdx\n    mov edx,[rel LC0+8]\n",
+                "    mov rdx,[rel LC0]\n    mov [rbp-24],rdx\n    mov edx,[rel LC0+8]\n",
+                "    lea rax,[rel LC1]\n",
+                "    mov rdx,[rel LC2]\n    mov [rbp-44],rdx\n",
+            ],
+        );
+    }
 }
@@ -1,10 +1,10 @@
-use citadel_frontend::ir::{INT16_T, INT32_T, INT64_T, INT8_T};
+use citadel_frontend::ir::{self, CmpOperator, IRExpr, Operator};
 
 use crate::asm::{
     self,
     elements::{
-        AsmElement, DataSize, Instruction, Literal, MemAddr, Opcode, Operand, Register,
-        SizedLiteral,
+        AsmElement, DataSize, DataValue, Declaration, Instruction, Literal, MemAddr, Opcode,
+        Operand, Register, SizedLiteral,
     },
 };
 
@@ -46,6 +46,16 @@ pub(crate) fn get_stack_location(pos: i32) -> Operand {
     Operand::MemAddr(MemAddr::RegisterPos(Register::Rbp, pos))
 }
 
+/// Moves the memory address by `offset` bytes
+pub(crate) fn offset_mem_addr(addr: MemAddr, offset: i32) -> MemAddr {
+    match addr {
+        MemAddr::Register(reg) => MemAddr::RegisterPos(reg, offset),
+        MemAddr::RegisterPos(reg, pos) => MemAddr::RegisterPos(reg, pos + offset),
+        MemAddr::RipRelative(ident, pos) => MemAddr::RipRelative(ident, pos + offset),
+        addr => panic!("Cannot offset memory address {addr}"),
+    }
+}
+
 #[inline(always)]
 pub(crate) fn create_stackframe() -> (AsmElement, AsmElement) {
     (
@@ -61,11 +71,51 @@ pub(crate) fn create_stackframe() -> (AsmElement, AsmElement) {
 }
 
 #[inline(always)]
-pub(crate) fn destroy_stackframe() -> AsmElement {
-    AsmElement::Instruction(Instruction {
-        opcode: Opcode::Pop,
-        args: vec![Operand::Register(Register::Rbp)],
-    })
+pub(crate) fn destroy_stackframe() -> (AsmElement, AsmElement) {
+    (
+        gen_mov_ins(
+            Operand::Register(Register::Rsp),
+            Operand::Register(Register::Rbp),
+        ),
+        AsmElement::Instruction(Instruction {
+            opcode: Opcode::Pop,
+            args: vec![Operand::Register(Register::Rbp)],
+        }),
+    )
+}
+
+/// Returns the `setcc` instruction that stores the result of the comparison
+#[inline(always)]
+pub(crate) fn set_opcode(op: CmpOperator) -> Opcode {
+    match op {
+        CmpOperator::Eq => Opcode::SetE,
+        CmpOperator::Ne => Opcode::SetNe,
+        CmpOperator::SLt => Opcode::SetL,
+        CmpOperator::SLe => Opcode::SetLe,
+        CmpOperator::SGt => Opcode::SetG,
+        CmpOperator::SGe => Opcode::SetGe,
+        CmpOperator::ULt => Opcode::SetB,
+        CmpOperator::ULe => Opcode::SetBe,
+        CmpOperator::UGt => Opcode::SetA,
+        CmpOperator::UGe => Opcode::SetAe,
+    }
+}
+
+/// Returns the conditional jump that is taken if the comparison is true
+#[inline(always)]
+pub(crate) fn jump_opcode(op: CmpOperator) -> Opcode {
+    match op {
+        CmpOperator::Eq => Opcode::JE,
+        CmpOperator::Ne => Opcode::JNe,
+        CmpOperator::SLt => Opcode::JL,
+        CmpOperator::SLe => Opcode::JLe,
+        CmpOperator::SGt => Opcode::JG,
+        CmpOperator::SGe => Opcode::JGe,
+        CmpOperator::ULt => Opcode::JB,
+        CmpOperator::ULe => Opcode::JBe,
+        CmpOperator::UGt => Opcode::JA,
+        CmpOperator::UGe => Opcode::JAe,
+    }
 }
 
 /// `size` is the size in bytes
@@ -80,38 +130,40 @@ pub(crate) fn arg_regs_by_size(size: u8) -> [Register; 6] {
     }
 }
 
-/// returns the size of the specified integer in bytes
-// TODO: Think about using 4 byte alignment
-#[inline(always)]
-pub(crate) fn int_size(int: &str) -> u8 {
-    match int {
-        INT8_T => 1,
-        INT16_T => 2,
-        INT32_T => 4,
-        INT64_T => 8,
-        _ => unreachable!(),
-    }
-}
-
-pub(crate) fn conv_str_to_bytes(string: &str) -> u64 {
-    let mut res = 0;
-    for (i, ch) in string.chars().enumerate() {
-        res |= (ch as u64) << (i * 8);
-    }
-    res
-}
-
-pub(crate) fn split_string(input: &str, sub_string_len: usize) -> Vec<&str> {
-    let mut result = Vec::new();
-    let mut start = 0;
+/// Every general purpose register in its 64, 32, 16 and 8 bit variant
+const REGISTER_FAMILIES: [[Register; 4]; 16] = [
+    [Register::Rax, Register::Eax, Register::Ax, Register::Al],
+    [Register::Rbx, Register::Ebx, Register::Bx, Register::Bl],
+    [Register::Rcx, Register::Ecx, Register::Cx, Register::Cl],
+    [Register::Rdx, Register::Edx, Register::Dx, Register::Dl],
+    [Register::Rsi, Register::Esi, Register::Si, Register::Sil],
+    [Register::Rdi, Register::Edi, Register::Di, Register::Dil],
+    [Register::Rsp, Register::Esp, Register::Sp, Register::Spl],
+    [Register::Rbp, Register::Ebp, Register::Bp, Register::Bpl],
+    [Register::R8, Register::R8d, Register::R8w, Register::R8b],
+    [Register::R9, Register::R9d, Register::R9w, Register::R9b],
+    [Register::R10, Register::R10d, Register::R10w, Register::R10b],
+    [Register::R11, Register::R11d, Register::R11w, Register::R11b],
+    [Register::R12, Register::R12d, Register::R12w, Register::R12b],
+    [Register::R13, Register::R13d, Register::R13w, Register::R13b],
+    [Register::R14, Register::R14d, Register::R14w, Register::R14b],
+    [Register::R15, Register::R15d, Register::R15w, Register::R15b],
+];
 
-    while start < input.len() {
-        let end = (start + sub_string_len).min(input.len());
-        result.push(&input[start..end]);
-        start = end;
+/// Returns the variant of `reg` with the specified size in bytes,
+/// for example `eax` for `rax` and a size of 4
+pub(crate) fn register_by_size(reg: Register, size: u8) -> Register {
+    let family = REGISTER_FAMILIES
+        .iter()
+        .find(|family| family.contains(&reg))
+        .unwrap_or_else(|| panic!("{reg:?} is not a general purpose register"));
+    match size {
+        8 => family[0],
+        4 => family[1],
+        2 => family[2],
+        1 => family[3],
+        _ => panic!("Invalid size: {size}"),
     }
-
-    result
 }
 
 // size from word can be obtained by calling word.size()
@@ -127,15 +179,158 @@ pub(crate) fn word_from_size(size: u8) -> DataSize {
 }
 
 #[inline(always)]
-pub(crate) fn literal_to_sized_literal(literal: Literal) -> Option<SizedLiteral> {
-    Some(SizedLiteral(
+pub(crate) fn literal_to_sized_literal(literal: Literal) -> SizedLiteral {
+    SizedLiteral(
         literal,
         match literal {
             Literal::Int8(_) => DataSize::Byte,
             Literal::Int16(_) => DataSize::Word,
-            Literal::Int32(_) => DataSize::DWord,
-            Literal::Int64(_) => DataSize::QWord,
-            Literal::Float32(_) | Literal::Float64(_) => return None,
+            Literal::Int32(_) | Literal::Float32(_) => DataSize::DWord,
+            Literal::Int64(_) | Literal::Float64(_) => DataSize::QWord,
         },
-    ))
+    )
+}
+
+/// Converts a literal that fits into a register. Returns None for strings and aggregates
+pub(crate) fn scalar_literal(lit: &ir::Literal) -> Option<Literal> {
+    let lit = match lit {
+        ir::Literal::Bool(val) => Literal::Int8(*val as i8),
+        ir::Literal::Char(val) => Literal::Int8(*val as i8),
+        ir::Literal::Int8(val) => Literal::Int8(*val),
+        ir::Literal::Int16(val) => Literal::Int16(*val),
+        ir::Literal::Int32(val) => Literal::Int32(*val),
+        ir::Literal::Int64(val) => Literal::Int64(*val),
+        // Unsigned literals keep their bits
+        ir::Literal::UInt8(val) => Literal::Int8(*val as i8),
+        ir::Literal::UInt16(val) => Literal::Int16(*val as i16),
+        ir::Literal::UInt32(val) => Literal::Int32(*val as i32),
+        ir::Literal::UInt64(val) => Literal::Int64(*val as i64),
+        ir::Literal::Float32(val) => Literal::Float32(*val),
+        ir::Literal::Float64(val) => Literal::Float64(*val),
+        _ => return None,
+    };
+    Some(lit)
+}
+
+/// Whether the expression is a literal or struct whose bytes are all zero
+pub(crate) fn is_zero(expr: &IRExpr) -> bool {
+    match expr {
+        IRExpr::Literal(ir::Literal::Array(_, elems) | ir::Literal::Vector(elems), _) => {
+            elems.iter().all(is_zero)
+        }
+        IRExpr::Literal(ir::Literal::String(val), _) => val.iter().all(|byte| *byte == 0),
+        IRExpr::Literal(ir::Literal::Float32(val), _) => val.to_bits() == 0,
+        IRExpr::Literal(ir::Literal::Float64(val), _) => val.to_bits() == 0,
+        IRExpr::Literal(ir::Literal::Int128(val), _) => *val == 0,
+        IRExpr::Literal(ir::Literal::UInt128(val), _) => *val == 0,
+        IRExpr::Literal(lit, _) => matches!(
+            scalar_literal(lit),
+            Some(Literal::Int8(0) | Literal::Int16(0) | Literal::Int32(0) | Literal::Int64(0))
+        ),
+        IRExpr::StructInit(init) => init.values.iter().all(is_zero),
+        _ => false,
+    }
+}
+
+/// Whether the expression only consists of literals, so it can be placed in a data section
+pub(crate) fn is_constant(expr: &IRExpr) -> bool {
+    match expr {
+        IRExpr::Literal(ir::Literal::Array(_, elems) | ir::Literal::Vector(elems), _) => {
+            elems.iter().all(is_constant)
+        }
+        IRExpr::Literal(..) => true,
+        IRExpr::StructInit(init) => init.values.iter().all(is_constant),
+        _ => false,
+    }
+}
+
+/// Appends a value to the previous definition if it has the same size
+pub(crate) fn push_value(decls: &mut Vec<Declaration>, size: DataSize, val: DataValue) {
+    if let Some(Declaration::Values(last, vals)) = decls.last_mut() {
+        if *last == size {
+            vals.push(val);
+            return;
+        }
+    }
+    decls.push(Declaration::Values(size, vec![val]));
+}
+
+/// Appends the given number of zero bytes
+pub(crate) fn push_zeros(decls: &mut Vec<Declaration>, count: u32) {
+    if count > 0 {
+        decls.push(Declaration::Times(count, DataSize::Byte, Literal::Int8(0)));
+    }
+}
+
+/// Returns the single precision variant of an SSE instruction for 4 byte floats
+/// and the double precision variant for 8 byte floats
+#[inline(always)]
+pub(crate) fn sse_opcode(size: u32, single: Opcode, double: Opcode) -> Opcode {
+    match size {
+        4 => single,
+        8 => double,
+        _ => panic!("Invalid float size: {size}"),
+    }
+}
+
+/// Returns the scalar SSE instruction for an arithmetic operator on floats of the size
+pub(crate) fn float_arith_opcode(op: Operator, size: u32) -> Opcode {
+    match op {
+        Operator::Add => sse_opcode(size, Opcode::Addss, Opcode::Addsd),
+        Operator::Sub => sse_opcode(size, Opcode::Subss, Opcode::Subsd),
+        Operator::Mul => sse_opcode(size, Opcode::Mulss, Opcode::Mulsd),
+        Operator::SDiv | Operator::UDiv => sse_opcode(size, Opcode::Divss, Opcode::Divsd),
+        op => panic!("Cannot use the {op} operator on floats"),
+    }
+}
+
+/// Returns the SSE2 instruction that applies the operator to every lane of an xmm register,
+/// or None if SSE2 has no such instruction for lanes of this kind and size
+pub(crate) fn packed_opcode(op: Operator, float: bool, size: u32) -> Option<Opcode> {
+    let opcode = match (op, float, size) {
+        (Operator::Add, true, 4) => Opcode::Addps,
+        (Operator::Add, true, 8) => Opcode::Addpd,
+        (Operator::Sub, true, 4) => Opcode::Subps,
+        (Operator::Sub, true, 8) => Opcode::Subpd,
+        (Operator::Mul, true, 4) => Opcode::Mulps,
+        (Operator::Mul, true, 8) => Opcode::Mulpd,
+        (Operator::SDiv | Operator::UDiv, true, 4) => Opcode::Divps,
+        (Operator::SDiv | Operator::UDiv, true, 8) => Opcode::Divpd,
+        (Operator::Add, false, 1) => Opcode::Paddb,
+        (Operator::Add, false, 2) => Opcode::Paddw,
+        (Operator::Add, false, 4) => Opcode::Paddd,
+        (Operator::Add, false, 8) => Opcode::Paddq,
+        (Operator::Sub, false, 1) => Opcode::Psubb,
+        (Operator::Sub, false, 2) => Opcode::Psubw,
+        (Operator::Sub, false, 4) => Opcode::Psubd,
+        (Operator::Sub, false, 8) => Opcode::Psubq,
+        (Operator::Mul, false, 2) => Opcode::Pmullw,
+        (Operator::And, false, _) => Opcode::Pand,
+        (Operator::Or, false, _) => Opcode::Por,
+        (Operator::XOr, false, _) => Opcode::Pxor,
+        _ => return None,
+    };
+    Some(opcode)
+}
+
+/// Encodes the lanes of a shuffle mask for four lanes into the immediate of `pshufd` and `shufps`.
+/// Every lane takes two bits, so only the position inside of its vector is kept
+pub(crate) fn shuffle_imm(mask: &[u32]) -> i32 {
+    mask.iter()
+        .enumerate()
+        .fold(0, |imm, (i, lane)| imm | ((lane % 4) << (i * 2)) as i32)
+}
+
+/// `ucomiss` and `ucomisd` set the flags like an unsigned comparison,
+/// so float comparisons use the unsigned conditions.
+/// Unordered operands (NaN) are not handled specially
+#[inline(always)]
+pub(crate) fn float_cmp_operator(op: CmpOperator) -> CmpOperator {
+    match op {
+        CmpOperator::SLt | CmpOperator::ULt => CmpOperator::ULt,
+        CmpOperator::SLe | CmpOperator::ULe => CmpOperator::ULe,
+        CmpOperator::SGt | CmpOperator::UGt => CmpOperator::UGt,
+        CmpOperator::SGe | CmpOperator::UGe => CmpOperator::UGe,
+        op => op,
+    }
 }
@@ -14,8 +14,12 @@ use crate::{
 
 use super::elements::{Declaration, Directive, DirectiveType, Operand};
 
-pub fn compile_program(input: HIRStream, _target: impl Target) -> Vec<AsmElement> {
-    let mut codegen = CodeGenerator::new(input.types);
+pub fn compile_program(input: HIRStream, target: impl Target, line_directives: bool) -> Vec<AsmElement> {
+    let mut codegen = CodeGenerator::new(&input.types, &target);
+    codegen.line_directives = line_directives;
+
+    codegen.declare_functions(&input.stream);
+    codegen.declare_globals(&input.stream);
 
     gen_code(&input.stream, &mut codegen);
 
@@ -23,18 +27,29 @@ pub fn compile_program(input: HIRStream, _target: impl Target) -> Vec<AsmElement
 
     let rodata = codegen.rodata;
     let data = codegen.data;
+    let bss = codegen.bss;
     let mut out = codegen.out;
 
+    // The code has to be in the text section when it follows the data sections
+    let has_data = !(rodata.is_empty() && data.is_empty() && bss.is_empty());
+    if has_data && !matches!(out.first(), Some(AsmElement::Directive(Directive { _type: DirectiveType::Text }))) {
+        out.insert(0, AsmElement::Directive(Directive { _type: DirectiveType::Text }));
+    }
+
     // Add data sections
     add_data_section(rodata, DirectiveType::Rodata, &mut out);
     add_data_section(data, DirectiveType::Data, &mut out);
+    add_data_section(bss, DirectiveType::Bss, &mut out);
 
     out
 }
 
 fn gen_code<'c>(input: &'c [IRStmt<'c>], codegen: &mut CodeGenerator<'c>) {
     for stmt in input.iter() {
-        codegen.gen_stmt(stmt);
+        // Globals are already placed in the data sections by `declare_globals`
+        if !matches!(stmt, IRStmt::Variable(_)) {
+            codegen.gen_stmt(stmt);
+        }
     }
 }
 
@@ -70,7 +85,9 @@ pub fn format(asm: &[AsmElement]) -> String{
     let mut out = String::new();
     for elem in asm{
         match elem {
-            AsmElement::Directive(_) | AsmElement::Label(_) => (),
+            AsmElement::Directive(_)
+            | AsmElement::Label(_)
+            | AsmElement::Declaration(Declaration::Label(_)) => (),
             _ => out.push_str("    "),
         }
         out.push_str(&elem.to_string());
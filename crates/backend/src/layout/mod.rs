@@ -0,0 +1,131 @@
+//! Computes how values of an [ir::Type] are laid out in memory.
+//!
+//! Sizes, alignments and field offsets follow the rules C compilers use:
+//! every field is placed at the next offset that is a multiple of its alignment
+//! and the size of an aggregate is rounded up to a multiple of its own alignment.
+//! This makes structs that are passed to or received from C code ABI-compatible.
+
+mod tests;
+
+use citadel_frontend::{
+    ir::{self, irgen::TypeTable, Type},
+    util::CompositeDataType,
+};
+
+use crate::api::Target;
+
+/// Size and alignment of a type in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    pub size: u32,
+    pub align: u32,
+}
+
+/// Layout of a struct or union including the offset of every field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregateLayout {
+    pub layout: Layout,
+    /// Offsets in the same order as the fields in the [TypeTable]
+    pub offsets: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DataLayout<'l> {
+    pointer_size: u32,
+    types: &'l TypeTable<'l>,
+}
+
+impl<'l> DataLayout<'l> {
+    pub fn new(target: &impl Target, types: &'l TypeTable<'l>) -> Self {
+        Self {
+            pointer_size: target.pointer_size(),
+            types,
+        }
+    }
+
+    pub fn layout_of(&self, _type: &Type) -> Layout {
+        match _type {
+            Type::Bool => Layout { size: 1, align: 1 },
+            Type::Int(_) | Type::Float(_) => {
+                let size = _type.bit_width().unwrap() / 8;
+                Layout { size, align: size }
+            }
+            Type::Pointer(_) => Layout {
+                size: self.pointer_size,
+                align: self.pointer_size,
+            },
+            Type::Void => Layout { size: 0, align: 1 },
+            Type::Array(elem, len) => {
+                let elem = self.layout_of(elem);
+                Layout {
+                    size: elem.size * len,
+                    align: elem.align,
+                }
+            }
+            // Like C vector extensions, vectors are aligned to their size rounded up to a power of two
+            Type::Vector(elem, lanes) => {
+                let align = (self.size_of(elem) * lanes).next_power_of_two();
+                Layout {
+                    size: align,
+                    align,
+                }
+            }
+            Type::Ident(name) => self.aggregate_layout(name).layout,
+        }
+    }
+
+    pub fn size_of(&self, _type: &Type) -> u32 {
+        self.layout_of(_type).size
+    }
+
+    pub fn align_of(&self, _type: &Type) -> u32 {
+        self.layout_of(_type).align
+    }
+
+    /// Computes the layout of the struct or union with the specified name
+    pub fn aggregate_layout(&self, name: &str) -> AggregateLayout {
+        let (cdt, fields) = self
+            .types
+            .get(name)
+            .unwrap_or_else(|| panic!("Could not find type with the name {name}"));
+        let mut size = 0;
+        let mut align = 1;
+        let mut offsets = Vec::with_capacity(fields.len());
+        for field in fields {
+            let layout = self.layout_of(&field._type);
+            align = align.max(layout.align);
+            match cdt {
+                CompositeDataType::Struct => {
+                    let offset = align_to(size, layout.align);
+                    offsets.push(offset);
+                    size = offset + layout.size;
+                }
+                // All variants of a union start at the same location
+                CompositeDataType::Union => {
+                    offsets.push(0);
+                    size = size.max(layout.size);
+                }
+            }
+        }
+        AggregateLayout {
+            layout: Layout {
+                size: align_to(size, align),
+                align,
+            },
+            offsets,
+        }
+    }
+
+    /// Returns the offset and the type of a field of a struct or union
+    pub fn field_of(&self, name: &str, field: &str) -> Option<(u32, &'l ir::Type<'l>)> {
+        let (_, fields) = self.types.get(name)?;
+        let index = fields.iter().position(|typed_ident| typed_ident.ident == field)?;
+        let offset = self.aggregate_layout(name).offsets[index];
+        Some((offset, &fields[index]._type))
+    }
+}
+
+/// Rounds the value up to the next multiple of `align`
+pub fn align_to(val: u32, align: u32) -> u32 {
+    val.div_ceil(align) * align
+}
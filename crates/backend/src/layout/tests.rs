@@ -0,0 +1,41 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests {
+    use bumpalo::Bump;
+    use citadel_frontend::ir::{Type, INT8_T};
+    use citadel_irparser::{IRLexer, IRParser};
+
+    use crate::{
+        asm::TargetX86_64,
+        layout::{DataLayout, Layout},
+    };
+
+    #[test]
+    fn test_layout() {
+        let lexer = IRLexer::new(
+            "struct @Padded {
+                $a i8,
+                $b i32,
+                $c i8,
+            }
+            union @Number {
+                $small i8,
+                $big i64,
+            }",
+        );
+        let arena = Bump::new();
+        let mut parser = IRParser::new(&lexer, &arena);
+        let ir_stream = parser.parse_program();
+        let layout = DataLayout::new(&TargetX86_64, &ir_stream.types);
+        let padded = layout.aggregate_layout("Padded");
+        assert_eq!(padded.layout, Layout { size: 12, align: 4 });
+        assert_eq!(padded.offsets, vec![0, 4, 8]);
+        assert_eq!(layout.layout_of(&Type::Ident("Number")), Layout { size: 8, align: 8 });
+        assert_eq!(
+            layout.layout_of(&Type::Array(&Type::Ident("Padded"), 2)),
+            Layout { size: 24, align: 4 }
+        );
+        assert_eq!(layout.size_of(&Type::Pointer(&INT8_T)), 8);
+        assert_eq!(layout.layout_of(&Type::Vector(&INT8_T, 3)), Layout { size: 4, align: 4 });
+    }
+}
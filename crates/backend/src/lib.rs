@@ -12,4 +12,5 @@
 
 pub mod asm;
 pub mod api;
+pub mod layout;
 
@@ -0,0 +1,879 @@
+//! Binary encoding of an [HIRStream]
+//!
+//! Bitcode is a compact alternative to the textual format of CHIR that can be
+//! loaded without lexing and parsing. Every identifier and string literal is
+//! stored once in a string table and referenced by its index. The format looks
+//! like this:
+//!
+//! ```text
+//! magic          "CHBC"
+//! version        u16, little endian
+//! strings        count, then the length and bytes of every string
+//! types          count, then the name, kind and fields of every composite type
+//! statements     count, then every statement of the stream
+//! ```
+//!
+//! Nodes start with a tag that selects their variant, followed by their children
+//! in the order they are declared in. Nodes that can have a [SourceLoc] end with
+//! it. Unsigned integers are encoded as LEB128, signed integers are zigzag-encoded
+//! first. Floats are stored as their little-endian bits.
+//!
+//! [VERSION] has to be increased whenever the encoding of a node changes.
+//! Nodes that are nested deeper than [MAX_DEPTH] are rejected while reading,
+//! so crafted input cannot overflow the stack.
+
+use std::collections::HashMap;
+
+use bumpalo::Bump;
+
+use crate::util::{errors::BitcodeError, CompositeDataType};
+
+use super::{
+    irgen::{HIRStream, TypeTable},
+    *,
+};
+
+pub const MAGIC: [u8; 4] = *b"CHBC";
+pub const VERSION: u16 = 6;
+/// How deeply statements, expressions and types can be nested in each other
+pub const MAX_DEPTH: usize = 128;
+
+// Enums without data are encoded by their index in these tables
+const INT_TYPES: [IntType; 10] = [
+    IntType::I8,
+    IntType::I16,
+    IntType::I32,
+    IntType::I64,
+    IntType::I128,
+    IntType::U8,
+    IntType::U16,
+    IntType::U32,
+    IntType::U64,
+    IntType::U128,
+];
+const FLOAT_TYPES: [FloatType; 2] = [FloatType::F32, FloatType::F64];
+const OPERATORS: [Operator; 13] = [
+    Operator::Add,
+    Operator::Sub,
+    Operator::Mul,
+    Operator::SDiv,
+    Operator::UDiv,
+    Operator::SRem,
+    Operator::URem,
+    Operator::And,
+    Operator::Or,
+    Operator::XOr,
+    Operator::Shl,
+    Operator::LShr,
+    Operator::AShr,
+];
+const CMP_OPERATORS: [CmpOperator; 10] = [
+    CmpOperator::Eq,
+    CmpOperator::Ne,
+    CmpOperator::SLt,
+    CmpOperator::SLe,
+    CmpOperator::SGt,
+    CmpOperator::SGe,
+    CmpOperator::ULt,
+    CmpOperator::ULe,
+    CmpOperator::UGt,
+    CmpOperator::UGe,
+];
+const CAST_KINDS: [CastKind; 6] = [
+    CastKind::SExt,
+    CastKind::ZExt,
+    CastKind::Trunc,
+    CastKind::IntToFloat,
+    CastKind::FloatToInt,
+    CastKind::Bitcast,
+];
+
+/// Encodes the stream and its type table as bitcode
+pub fn write(stream: &HIRStream) -> Vec<u8> {
+    let mut writer = Writer::default();
+    writer.types(&stream.types);
+    writer.uint(stream.stream.len() as u64);
+    for stmt in &stream.stream {
+        writer.stmt(stmt);
+    }
+
+    let mut out = Writer::default();
+    out.buf.extend(MAGIC);
+    out.buf.extend(VERSION.to_le_bytes());
+    out.uint(writer.strings.len() as u64);
+    for string in &writer.strings {
+        out.uint(string.len() as u64);
+        out.buf.extend(*string);
+    }
+    out.buf.extend(writer.buf);
+    out.buf
+}
+
+/// Decodes bitcode that was created by [write]. Identifiers and
+/// string literals of the returned stream are allocated in the arena
+pub fn read<'b>(bytes: &[u8], arena: &'b Bump) -> Result<HIRStream<'b>, BitcodeError> {
+    let mut reader = Reader {
+        bytes,
+        pos: 0,
+        depth: 0,
+        strings: Vec::new(),
+        arena,
+    };
+    if reader.bytes(MAGIC.len())? != MAGIC {
+        return Err(BitcodeError::InvalidMagic);
+    }
+    let version = u16::from_le_bytes(reader.array()?);
+    if version != VERSION {
+        return Err(BitcodeError::UnsupportedVersion(version));
+    }
+    reader.strings = reader.list(|reader| {
+        let len = reader.uint()?;
+        Ok(&*reader.arena.alloc_slice_copy(reader.bytes(len)?))
+    })?;
+    let types = reader.types()?;
+    let stream = reader.list(Reader::stmt)?;
+    Ok(HIRStream { stream, types })
+}
+
+#[derive(Default)]
+struct Writer<'w> {
+    buf: Vec<u8>,
+    strings: Vec<&'w [u8]>,
+    string_ids: HashMap<&'w [u8], u64>,
+}
+
+impl<'w> Writer<'w> {
+    fn byte(&mut self, byte: u8) {
+        self.buf.push(byte);
+    }
+
+    fn varint(&mut self, mut val: u128) {
+        while val >= 0x80 {
+            self.buf.push(val as u8 | 0x80);
+            val >>= 7;
+        }
+        self.buf.push(val as u8);
+    }
+
+    fn uint(&mut self, val: u64) {
+        self.varint(val as u128);
+    }
+
+    fn int(&mut self, val: i128) {
+        self.varint(((val << 1) ^ (val >> 127)) as u128);
+    }
+
+    fn string(&mut self, string: &'w str) {
+        self.byte_string(string.as_bytes());
+    }
+
+    fn byte_string(&mut self, string: &'w [u8]) {
+        let id = match self.string_ids.get(string) {
+            Some(id) => *id,
+            None => {
+                let id = self.strings.len() as u64;
+                self.strings.push(string);
+                self.string_ids.insert(string, id);
+                id
+            }
+        };
+        self.uint(id);
+    }
+
+    fn index<T: PartialEq>(&mut self, table: &[T], val: &T) {
+        let index = table.iter().position(|elem| elem == val);
+        self.uint(index.expect("Every variant is part of its table") as u64);
+    }
+
+    fn types(&mut self, types: &TypeTable<'w>) {
+        // Sorted so that the same stream always results in the same bitcode
+        let mut types: Vec<_> = types.iter().collect();
+        types.sort_by_key(|(name, _)| **name);
+        self.uint(types.len() as u64);
+        for (name, (kind, fields)) in types {
+            self.string(name);
+            self.byte(match kind {
+                CompositeDataType::Struct => 0,
+                CompositeDataType::Union => 1,
+            });
+            self.typed_idents(fields);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &IRStmt<'w>) {
+        match stmt {
+            IRStmt::Entry(block) => {
+                self.byte(0);
+                self.block(block);
+            }
+            IRStmt::DeclaredFunction(func) => {
+                self.byte(1);
+                self.typed_ident(&func.name);
+                self.typed_idents(&func.args);
+            }
+            IRStmt::Function(func) => {
+                self.byte(2);
+                self.typed_ident(&func.name);
+                self.typed_idents(&func.args);
+                self.block(&func.block);
+            }
+            IRStmt::Variable(var) => {
+                self.byte(3);
+                self.typed_ident(&var.name);
+                self.expr(&var.val);
+                self.byte(var.is_const as u8);
+            }
+            IRStmt::Assign(assign) => {
+                self.byte(4);
+                self.string(assign.name);
+                self.expr(&assign.val);
+            }
+            IRStmt::Label(label) => {
+                self.byte(5);
+                self.string(label.name);
+            }
+            IRStmt::Return(ret) => {
+                self.byte(6);
+                self.expr(&ret.ret_val);
+            }
+            IRStmt::Exit(exit) => {
+                self.byte(7);
+                self.expr(&exit.exit_code);
+            }
+            IRStmt::Jump(jump) => {
+                self.byte(8);
+                self.string(jump.label);
+            }
+            IRStmt::Branch(branch) => {
+                self.byte(9);
+                self.expr(&branch.condition);
+                self.string(branch.then_label);
+                self.string(branch.else_label);
+            }
+            IRStmt::Call(call) => {
+                self.byte(10);
+                self.call(call);
+            }
+            IRStmt::Store(store) => {
+                self.byte(11);
+                self.expr(&store.val);
+                self.expr(&store.ptr);
+            }
+            IRStmt::Struct(node) => {
+                self.byte(12);
+                self.string(node.name);
+                self.typed_idents(&node.fields);
+            }
+            IRStmt::Union(node) => {
+                self.byte(13);
+                self.string(node.name);
+                self.typed_idents(&node.variants);
+            }
+        }
+        if !matches!(stmt, IRStmt::Entry(_)) {
+            self.loc(stmt.loc());
+        }
+    }
+
+    fn expr(&mut self, expr: &IRExpr<'w>) {
+        match expr {
+            IRExpr::Call(call) => {
+                self.byte(0);
+                self.call(call);
+            }
+            IRExpr::Literal(lit, _type) => {
+                self.byte(1);
+                self.literal(lit);
+                self._type(_type);
+            }
+            IRExpr::Ident(ident) => {
+                self.byte(2);
+                self.string(ident);
+            }
+            IRExpr::ArithOp(op) => {
+                self.byte(3);
+                self.index(&OPERATORS, &op.op);
+                self.expr(&op.values.0);
+                self.expr(&op.values.1);
+            }
+            IRExpr::CmpOp(op) => {
+                self.byte(4);
+                self.index(&CMP_OPERATORS, &op.op);
+                self.expr(&op.values.0);
+                self.expr(&op.values.1);
+            }
+            IRExpr::Not(not) => {
+                self.byte(5);
+                self.expr(&not.val);
+            }
+            IRExpr::Cast(cast) => {
+                self.byte(6);
+                self.index(&CAST_KINDS, &cast.kind);
+                self._type(&cast._type);
+                self.expr(&cast.val);
+            }
+            IRExpr::Alloca(alloca) => {
+                self.byte(7);
+                self._type(&alloca._type);
+            }
+            IRExpr::Load(load) => {
+                self.byte(8);
+                self._type(&load._type);
+                self.expr(&load.ptr);
+            }
+            IRExpr::AddrOf(addr_of) => {
+                self.byte(9);
+                self.expr(&addr_of.val);
+            }
+            IRExpr::Field(field) => {
+                self.byte(10);
+                self.expr(&field.base);
+                self.string(field.field);
+            }
+            IRExpr::Index(index) => {
+                self.byte(11);
+                self.expr(&index.base);
+                self.expr(&index.index);
+            }
+            IRExpr::StructInit(init) => {
+                self.byte(12);
+                self.string(init.name);
+                self.exprs(&init.values);
+            }
+            IRExpr::Extract(extract) => {
+                self.byte(13);
+                self.expr(&extract.vector);
+                self.uint(extract.lane as u64);
+            }
+            IRExpr::Insert(insert) => {
+                self.byte(14);
+                self.expr(&insert.vector);
+                self.expr(&insert.val);
+                self.uint(insert.lane as u64);
+            }
+            IRExpr::Shuffle(shuffle) => {
+                self.byte(15);
+                self.expr(&shuffle.values.0);
+                self.expr(&shuffle.values.1);
+                self.uint(shuffle.mask.len() as u64);
+                for lane in &shuffle.mask {
+                    self.uint(*lane as u64);
+                }
+            }
+        }
+        if !matches!(expr, IRExpr::Literal(..) | IRExpr::Ident(_)) {
+            self.loc(expr.loc());
+        }
+    }
+
+    fn literal(&mut self, lit: &Literal<'w>) {
+        match lit {
+            Literal::String(string) => {
+                self.byte(0);
+                self.byte_string(string);
+            }
+            Literal::Char(char) => {
+                self.byte(1);
+                self.byte(*char);
+            }
+            Literal::Float32(float) => {
+                self.byte(2);
+                self.buf.extend(float.to_le_bytes());
+            }
+            Literal::Float64(float) => {
+                self.byte(3);
+                self.buf.extend(float.to_le_bytes());
+            }
+            Literal::Bool(bool) => {
+                self.byte(4);
+                self.byte(*bool as u8);
+            }
+            Literal::Int8(int) => {
+                self.byte(5);
+                self.int(*int as i128);
+            }
+            Literal::Int16(int) => {
+                self.byte(6);
+                self.int(*int as i128);
+            }
+            Literal::Int32(int) => {
+                self.byte(7);
+                self.int(*int as i128);
+            }
+            Literal::Int64(int) => {
+                self.byte(8);
+                self.int(*int as i128);
+            }
+            Literal::Int128(int) => {
+                self.byte(9);
+                self.int(*int);
+            }
+            Literal::Array(len, vals) => {
+                self.byte(10);
+                self.uint(*len as u64);
+                self.exprs(vals);
+            }
+            Literal::Vector(vals) => {
+                self.byte(11);
+                self.exprs(vals);
+            }
+            Literal::UInt8(int) => {
+                self.byte(12);
+                self.varint(*int as u128);
+            }
+            Literal::UInt16(int) => {
+                self.byte(13);
+                self.varint(*int as u128);
+            }
+            Literal::UInt32(int) => {
+                self.byte(14);
+                self.varint(*int as u128);
+            }
+            Literal::UInt64(int) => {
+                self.byte(15);
+                self.varint(*int as u128);
+            }
+            Literal::UInt128(int) => {
+                self.byte(16);
+                self.varint(*int);
+            }
+        }
+    }
+
+    fn _type(&mut self, _type: &Type<'w>) {
+        match _type {
+            Type::Bool => self.byte(0),
+            Type::Int(int) => {
+                self.byte(1);
+                self.index(&INT_TYPES, int);
+            }
+            Type::Float(float) => {
+                self.byte(2);
+                self.index(&FLOAT_TYPES, float);
+            }
+            Type::Void => self.byte(3),
+            Type::Ident(name) => {
+                self.byte(4);
+                self.string(name);
+            }
+            Type::Array(_type, len) => {
+                self.byte(5);
+                self._type(_type);
+                self.uint(*len as u64);
+            }
+            Type::Pointer(_type) => {
+                self.byte(6);
+                self._type(_type);
+            }
+            Type::Vector(_type, lanes) => {
+                self.byte(7);
+                self._type(_type);
+                self.uint(*lanes as u64);
+            }
+        }
+    }
+
+    fn loc(&mut self, loc: Option<SourceLoc<'w>>) {
+        match loc {
+            Some(loc) => {
+                self.byte(1);
+                self.string(loc.file);
+                self.uint(loc.line as u64);
+                self.uint(loc.col as u64);
+            }
+            None => self.byte(0),
+        }
+    }
+
+    fn block(&mut self, block: &BlockStmt<'w>) {
+        self.uint(block.stmts.len() as u64);
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn call(&mut self, call: &CallExpr<'w>) {
+        self.string(call.name);
+        self.exprs(&call.args);
+    }
+
+    fn exprs(&mut self, exprs: &[IRExpr<'w>]) {
+        self.uint(exprs.len() as u64);
+        for expr in exprs {
+            self.expr(expr);
+        }
+    }
+
+    fn typed_ident(&mut self, typed_ident: &IRTypedIdent<'w>) {
+        self.string(typed_ident.ident);
+        self._type(&typed_ident._type);
+    }
+
+    fn typed_idents(&mut self, typed_idents: &[IRTypedIdent<'w>]) {
+        self.uint(typed_idents.len() as u64);
+        for typed_ident in typed_idents {
+            self.typed_ident(typed_ident);
+        }
+    }
+}
+
+struct Reader<'r, 'b> {
+    bytes: &'r [u8],
+    pos: usize,
+    /// Number of nodes that are currently being read
+    depth: usize,
+    strings: Vec<&'b [u8]>,
+    arena: &'b Bump,
+}
+
+type ReadResult<T> = Result<T, BitcodeError>;
+
+impl<'r, 'b> Reader<'r, 'b> {
+    fn bytes(&mut self, len: usize) -> ReadResult<&'r [u8]> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.pos..end))
+            .ok_or(BitcodeError::UnexpectedEof)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> ReadResult<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().expect("The slice has a length of N"))
+    }
+
+    fn byte(&mut self) -> ReadResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn varint(&mut self) -> ReadResult<u128> {
+        let mut val = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            if shift >= 128 {
+                return Err(BitcodeError::InvalidInt);
+            }
+            val |= ((byte & 0x7f) as u128) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(val);
+            }
+            shift += 7;
+        }
+    }
+
+    /// Reads an unsigned integer and checks that it fits into `T`
+    fn uint<T: TryFrom<u128>>(&mut self) -> ReadResult<T> {
+        self.varint()?
+            .try_into()
+            .map_err(|_| BitcodeError::InvalidInt)
+    }
+
+    /// Reads a signed integer and checks that it fits into `T`
+    fn int<T: TryFrom<i128>>(&mut self) -> ReadResult<T> {
+        let val = self.varint()?;
+        ((val >> 1) as i128 ^ -((val & 1) as i128))
+            .try_into()
+            .map_err(|_| BitcodeError::InvalidInt)
+    }
+
+    fn bool(&mut self) -> ReadResult<bool> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(BitcodeError::InvalidTag("bool", tag as u64)),
+        }
+    }
+
+    fn string(&mut self) -> ReadResult<&'b str> {
+        std::str::from_utf8(self.byte_string()?).map_err(|_| BitcodeError::InvalidUtf8)
+    }
+
+    /// Reads a string literal, which does not have to be valid UTF-8
+    fn byte_string(&mut self) -> ReadResult<&'b [u8]> {
+        let index: u64 = self.uint()?;
+        self.strings
+            .get(index as usize)
+            .copied()
+            .ok_or(BitcodeError::InvalidString(index))
+    }
+
+    fn index<T: Copy>(&mut self, node: &'static str, table: &[T]) -> ReadResult<T> {
+        let index: u64 = self.uint()?;
+        table
+            .get(index as usize)
+            .copied()
+            .ok_or(BitcodeError::InvalidTag(node, index))
+    }
+
+    fn list<T>(&mut self, mut read: impl FnMut(&mut Self) -> ReadResult<T>) -> ReadResult<Vec<T>> {
+        let len: usize = self.uint()?;
+        // The length is not trusted for preallocating, every element takes at least a byte
+        let mut list = Vec::with_capacity(len.min(self.bytes.len() - self.pos));
+        for _ in 0..len {
+            list.push(read(self)?);
+        }
+        Ok(list)
+    }
+
+    fn types(&mut self) -> ReadResult<TypeTable<'b>> {
+        let types = self.list(|reader| {
+            let name = reader.string()?;
+            let kind = match reader.byte()? {
+                0 => CompositeDataType::Struct,
+                1 => CompositeDataType::Union,
+                tag => return Err(BitcodeError::InvalidTag("composite type", tag as u64)),
+            };
+            Ok((name, (kind, reader.typed_idents()?)))
+        })?;
+        Ok(types.into_iter().collect())
+    }
+
+    /// Reads a node that can contain nodes of the same kind
+    fn nested<T>(&mut self, read: impl FnOnce(&mut Self) -> ReadResult<T>) -> ReadResult<T> {
+        if self.depth == MAX_DEPTH {
+            return Err(BitcodeError::TooDeep);
+        }
+        self.depth += 1;
+        let node = read(self);
+        self.depth -= 1;
+        node
+    }
+
+    fn stmt(&mut self) -> ReadResult<IRStmt<'b>> {
+        self.nested(Self::stmt_node)
+    }
+
+    fn stmt_node(&mut self) -> ReadResult<IRStmt<'b>> {
+        Ok(match self.byte()? {
+            0 => IRStmt::Entry(self.block()?),
+            1 => IRStmt::DeclaredFunction(DeclFuncStmt {
+                name: self.typed_ident()?,
+                args: self.typed_idents()?,
+                loc: self.loc()?,
+            }),
+            2 => IRStmt::Function(FuncStmt {
+                name: self.typed_ident()?,
+                args: self.typed_idents()?,
+                block: self.block()?,
+                loc: self.loc()?,
+            }),
+            3 => IRStmt::Variable(VarStmt {
+                name: self.typed_ident()?,
+                val: self.expr()?,
+                is_const: self.bool()?,
+                loc: self.loc()?,
+            }),
+            4 => IRStmt::Assign(AssignStmt {
+                name: self.string()?,
+                val: self.expr()?,
+                loc: self.loc()?,
+            }),
+            5 => IRStmt::Label(LabelStmt {
+                name: self.string()?,
+                loc: self.loc()?,
+            }),
+            6 => IRStmt::Return(ReturnStmt {
+                ret_val: self.expr()?,
+                loc: self.loc()?,
+            }),
+            7 => IRStmt::Exit(ExitStmt {
+                exit_code: self.expr()?,
+                loc: self.loc()?,
+            }),
+            8 => IRStmt::Jump(JumpStmt {
+                label: self.string()?,
+                loc: self.loc()?,
+            }),
+            9 => IRStmt::Branch(BranchStmt {
+                condition: self.expr()?,
+                then_label: self.string()?,
+                else_label: self.string()?,
+                loc: self.loc()?,
+            }),
+            10 => IRStmt::Call(self.call()?),
+            11 => IRStmt::Store(StoreStmt {
+                val: self.expr()?,
+                ptr: self.expr()?,
+                loc: self.loc()?,
+            }),
+            12 => IRStmt::Struct(StructStmt {
+                name: self.string()?,
+                fields: self.typed_idents()?,
+                loc: self.loc()?,
+            }),
+            13 => IRStmt::Union(UnionStmt {
+                name: self.string()?,
+                variants: self.typed_idents()?,
+                loc: self.loc()?,
+            }),
+            tag => return Err(BitcodeError::InvalidTag("statement", tag as u64)),
+        })
+    }
+
+    fn expr(&mut self) -> ReadResult<IRExpr<'b>> {
+        self.nested(Self::expr_node)
+    }
+
+    fn expr_node(&mut self) -> ReadResult<IRExpr<'b>> {
+        Ok(match self.byte()? {
+            0 => IRExpr::Call(self.call()?),
+            1 => IRExpr::Literal(self.literal()?, self._type()?),
+            2 => IRExpr::Ident(self.string()?),
+            3 => IRExpr::ArithOp(ArithOpExpr {
+                op: self.index("operator", &OPERATORS)?,
+                values: (self.boxed_expr()?, self.boxed_expr()?),
+                loc: self.loc()?,
+            }),
+            4 => IRExpr::CmpOp(CmpOpExpr {
+                op: self.index("comparison", &CMP_OPERATORS)?,
+                values: (self.boxed_expr()?, self.boxed_expr()?),
+                loc: self.loc()?,
+            }),
+            5 => IRExpr::Not(NotExpr {
+                val: self.boxed_expr()?,
+                loc: self.loc()?,
+            }),
+            6 => IRExpr::Cast(CastExpr {
+                kind: self.index("cast", &CAST_KINDS)?,
+                _type: self._type()?,
+                val: self.boxed_expr()?,
+                loc: self.loc()?,
+            }),
+            7 => IRExpr::Alloca(AllocaExpr {
+                _type: self._type()?,
+                loc: self.loc()?,
+            }),
+            8 => IRExpr::Load(LoadExpr {
+                _type: self._type()?,
+                ptr: self.boxed_expr()?,
+                loc: self.loc()?,
+            }),
+            9 => IRExpr::AddrOf(AddrOfExpr {
+                val: self.boxed_expr()?,
+                loc: self.loc()?,
+            }),
+            10 => IRExpr::Field(FieldExpr {
+                base: self.boxed_expr()?,
+                field: self.string()?,
+                loc: self.loc()?,
+            }),
+            11 => IRExpr::Index(IndexExpr {
+                base: self.boxed_expr()?,
+                index: self.boxed_expr()?,
+                loc: self.loc()?,
+            }),
+            12 => IRExpr::StructInit(StructInitExpr {
+                name: self.string()?,
+                values: self.list(Self::expr)?,
+                loc: self.loc()?,
+            }),
+            13 => IRExpr::Extract(ExtractExpr {
+                vector: self.boxed_expr()?,
+                lane: self.uint()?,
+                loc: self.loc()?,
+            }),
+            14 => IRExpr::Insert(InsertExpr {
+                vector: self.boxed_expr()?,
+                val: self.boxed_expr()?,
+                lane: self.uint()?,
+                loc: self.loc()?,
+            }),
+            15 => IRExpr::Shuffle(ShuffleExpr {
+                values: (self.boxed_expr()?, self.boxed_expr()?),
+                mask: self.list(Self::uint)?,
+                loc: self.loc()?,
+            }),
+            tag => return Err(BitcodeError::InvalidTag("expression", tag as u64)),
+        })
+    }
+
+    fn boxed_expr(&mut self) -> ReadResult<Box<IRExpr<'b>>> {
+        Ok(Box::new(self.expr()?))
+    }
+
+    fn literal(&mut self) -> ReadResult<Literal<'b>> {
+        Ok(match self.byte()? {
+            0 => Literal::String(self.byte_string()?),
+            1 => Literal::Char(self.byte()?),
+            2 => Literal::Float32(f32::from_le_bytes(self.array()?)),
+            3 => Literal::Float64(f64::from_le_bytes(self.array()?)),
+            4 => Literal::Bool(self.bool()?),
+            5 => Literal::Int8(self.int()?),
+            6 => Literal::Int16(self.int()?),
+            7 => Literal::Int32(self.int()?),
+            8 => Literal::Int64(self.int()?),
+            9 => Literal::Int128(self.int()?),
+            10 => Literal::Array(self.uint()?, self.list(Self::expr)?),
+            11 => Literal::Vector(self.list(Self::expr)?),
+            12 => Literal::UInt8(self.uint()?),
+            13 => Literal::UInt16(self.uint()?),
+            14 => Literal::UInt32(self.uint()?),
+            15 => Literal::UInt64(self.uint()?),
+            16 => Literal::UInt128(self.uint()?),
+            tag => return Err(BitcodeError::InvalidTag("literal", tag as u64)),
+        })
+    }
+
+    fn _type(&mut self) -> ReadResult<Type<'b>> {
+        self.nested(Self::type_node)
+    }
+
+    fn type_node(&mut self) -> ReadResult<Type<'b>> {
+        Ok(match self.byte()? {
+            0 => Type::Bool,
+            1 => Type::Int(self.index("int type", &INT_TYPES)?),
+            2 => Type::Float(self.index("float type", &FLOAT_TYPES)?),
+            3 => Type::Void,
+            4 => Type::Ident(self.string()?),
+            5 => {
+                let elem = self._type()?;
+                Type::Array(self.arena.alloc(elem), self.uint()?)
+            }
+            6 => Type::Pointer(self.arena.alloc(self._type()?)),
+            7 => {
+                let elem = self._type()?;
+                Type::Vector(self.arena.alloc(elem), self.uint()?)
+            }
+            tag => return Err(BitcodeError::InvalidTag("type", tag as u64)),
+        })
+    }
+
+    fn loc(&mut self) -> ReadResult<Option<SourceLoc<'b>>> {
+        Ok(match self.byte()? {
+            0 => None,
+            1 => Some(SourceLoc {
+                file: self.string()?,
+                line: self.uint()?,
+                col: self.uint()?,
+            }),
+            tag => return Err(BitcodeError::InvalidTag("location", tag as u64)),
+        })
+    }
+
+    fn block(&mut self) -> ReadResult<BlockStmt<'b>> {
+        Ok(BlockStmt {
+            stmts: self.list(Self::stmt)?,
+        })
+    }
+
+    fn call(&mut self) -> ReadResult<CallExpr<'b>> {
+        Ok(CallExpr {
+            name: self.string()?,
+            args: self.list(Self::expr)?,
+            loc: self.loc()?,
+        })
+    }
+
+    fn typed_ident(&mut self) -> ReadResult<IRTypedIdent<'b>> {
+        Ok(IRTypedIdent {
+            ident: self.string()?,
+            _type: self._type()?,
+        })
+    }
+
+    fn typed_idents(&mut self) -> ReadResult<Vec<IRTypedIdent<'b>>> {
+        self.list(Self::typed_ident)
+    }
+}
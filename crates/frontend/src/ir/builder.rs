@@ -0,0 +1,628 @@
+//! A typed builder for generating the IR
+//!
+//! Unlike the [IRGenerator], which just collects statements, the [IRBuilder]
+//! keeps track of the function and block that instructions are inserted into,
+//! generates unique names for temporaries and labels and checks the types of
+//! operands as instructions are built.
+//!
+//! Instructions that produce a value store it in a new temporary variable and
+//! return an identifier referring to it, so they can be used as operands of
+//! other instructions. Temporaries are named `__t0`, `__t1`, ... and names
+//! starting with `__` are reserved for them, so they never clash with the
+//! variables of the frontend.
+//!
+//! Every node the builder creates is marked with the current source location,
+//! which frontends update using [IRBuilder::set_loc].
+
+use std::collections::HashMap;
+
+use bumpalo::Bump;
+
+use crate::util::errors::VerifyError;
+
+use super::{
+    irgen::{HIRStream, IRGenerator},
+    ArithOpExpr, BlockStmt, BranchStmt, CallExpr, CmpOpExpr, CmpOperator, DeclFuncStmt, FuncStmt,
+    ExitStmt, ExtractExpr, IRExpr, IRStmt, IRTypedIdent, Ident, InsertExpr, JumpStmt, LabelStmt,
+    Operator, ReturnStmt, ShuffleExpr, SourceLoc, Type, VarStmt, INT1_T, INT32_T,
+};
+
+/// Prefix of names that only the builder may declare
+const RESERVED_PREFIX: &str = "__";
+
+pub struct IRBuilder<'b> {
+    arena: &'b Bump,
+    out: IRGenerator<'b>,
+    /// Return and argument types of all functions
+    functions: HashMap<Ident<'b>, (Type<'b>, Vec<Type<'b>>)>,
+    globals: HashMap<Ident<'b>, Type<'b>>,
+    func: Option<FunctionCursor<'b>>,
+    temp_index: usize,
+    label_index: usize,
+    loc: Option<SourceLoc<'b>>,
+}
+
+/// The function that is currently being built
+struct FunctionCursor<'b> {
+    /// [None] for the entry of the program
+    name: Option<IRTypedIdent<'b>>,
+    args: Vec<IRTypedIdent<'b>>,
+    /// Blocks in the order they were appended. The first block has no label
+    blocks: Vec<(Option<Ident<'b>>, Vec<IRStmt<'b>>)>,
+    /// Index of the block that statements are appended to
+    block: usize,
+    variables: HashMap<Ident<'b>, Type<'b>>,
+    /// Labels used by jumps and branches
+    targets: Vec<Ident<'b>>,
+    /// Location of the function itself
+    loc: Option<SourceLoc<'b>>,
+}
+
+impl<'b> IRBuilder<'b> {
+    /// Names of temporaries and labels are allocated in the arena
+    pub fn new(arena: &'b Bump) -> Self {
+        Self {
+            arena,
+            out: IRGenerator::default(),
+            functions: HashMap::new(),
+            globals: HashMap::new(),
+            func: None,
+            temp_index: 0,
+            label_index: 0,
+            loc: None,
+        }
+    }
+
+    /// Sets the source location of the nodes that are built afterwards
+    pub fn set_loc(&mut self, loc: Option<SourceLoc<'b>>) {
+        self.loc = loc;
+    }
+
+    pub fn loc(&self) -> Option<SourceLoc<'b>> {
+        self.loc
+    }
+
+    /// Adds a statement that is not part of a function, like a struct or union
+    pub fn build_global_stmt(&mut self, stmt: IRStmt<'b>) {
+        if let IRStmt::Variable(var) = &stmt {
+            self.globals.insert(var.name.ident, var.name._type);
+        }
+        self.out.gen_ir(stmt);
+    }
+
+    pub fn declare_function(
+        &mut self,
+        name: Ident<'b>,
+        args: Vec<IRTypedIdent<'b>>,
+        ret_type: Type<'b>,
+    ) {
+        self.register_function(name, &args, ret_type);
+        self.out.gen_ir(IRStmt::DeclaredFunction(DeclFuncStmt {
+            name: IRTypedIdent {
+                ident: name,
+                _type: ret_type,
+            },
+            args,
+            loc: self.loc,
+        }));
+    }
+
+    /// Starts a new function. Instructions are appended to
+    /// its entry block until another block is selected
+    pub fn begin_function(
+        &mut self,
+        name: Ident<'b>,
+        args: Vec<IRTypedIdent<'b>>,
+        ret_type: Type<'b>,
+    ) {
+        self.register_function(name, &args, ret_type);
+        let name = IRTypedIdent {
+            ident: name,
+            _type: ret_type,
+        };
+        self.begin(Some(name), args);
+    }
+
+    /// Starts the entry of the program. It is finished using [IRBuilder::end_function]
+    pub fn begin_entry(&mut self) {
+        self.begin(None, Vec::new());
+    }
+
+    fn begin(&mut self, name: Option<IRTypedIdent<'b>>, args: Vec<IRTypedIdent<'b>>) {
+        if let Some(func) = &self.func {
+            panic!("Cannot begin a function before finishing {}", func.display_name());
+        }
+        self.func = Some(FunctionCursor {
+            name,
+            variables: args.iter().map(|arg| (arg.ident, arg._type)).collect(),
+            args,
+            blocks: vec![(None, Vec::new())],
+            block: 0,
+            targets: Vec::new(),
+            loc: self.loc,
+        });
+    }
+
+    /// Finishes the current function and adds it to the stream.
+    /// Fails if a jump or branch refers to a label that was not created
+    pub fn end_function(&mut self) -> Result<(), VerifyError> {
+        let func = self.func.take().expect("No function is being built");
+        if let Some(label) = func
+            .targets
+            .iter()
+            .find(|target| !func.blocks.iter().any(|(label, _)| label == &Some(**target)))
+        {
+            return Err(VerifyError::UnknownLabel(label.to_string()));
+        }
+        let mut stmts = Vec::new();
+        for (label, block) in func.blocks {
+            if let Some(name) = label {
+                stmts.push(IRStmt::Label(LabelStmt { name, loc: None }));
+            }
+            stmts.extend(block);
+        }
+        let block = BlockStmt { stmts };
+        self.out.gen_ir(match func.name {
+            Some(name) => IRStmt::Function(FuncStmt {
+                name,
+                args: func.args,
+                block,
+                loc: func.loc,
+            }),
+            None => IRStmt::Entry(block),
+        });
+        Ok(())
+    }
+
+    /// Appends a new block with a unique label, starting with `name`, to the current
+    /// function. This does not change the block that instructions are appended to
+    pub fn append_block(&mut self, name: &str) -> Ident<'b> {
+        let label = self.fresh_name(name, Counter::Label);
+        self.cursor().blocks.push((Some(label), Vec::new()));
+        label
+    }
+
+    /// Appends the following instructions to the end of the block with the label
+    pub fn position_at_end(&mut self, label: Ident<'b>) {
+        let func = self.cursor();
+        func.block = func
+            .blocks
+            .iter()
+            .position(|(name, _)| *name == Some(label))
+            .unwrap_or_else(|| panic!("Could not find label with name {label:?}"));
+    }
+
+    /// Returns the label of the block that instructions are appended to.
+    /// The entry block of a function has no label
+    pub fn current_block(&self) -> Option<Ident<'b>> {
+        let func = self.func.as_ref().expect("No function is being built");
+        func.blocks[func.block].0
+    }
+
+    /// Declares a variable in the current function. Fails if the name is already
+    /// used by another variable or starts with `__`, which is reserved for temporaries
+    pub fn build_var(
+        &mut self,
+        name: Ident<'b>,
+        _type: Type<'b>,
+        val: IRExpr<'b>,
+        is_const: bool,
+    ) -> Result<IRExpr<'b>, VerifyError> {
+        if name.starts_with(RESERVED_PREFIX) {
+            return Err(VerifyError::ReservedName(name.to_string()));
+        }
+        let val_type = self.type_of(&val)?;
+        expect_type(&_type, &val_type)?;
+        if self.cursor().variables.insert(name, _type).is_some() {
+            return Err(VerifyError::DuplicateDefinition(name.to_string()));
+        }
+        self.append(IRStmt::Variable(VarStmt {
+            name: IRTypedIdent { ident: name, _type },
+            val,
+            is_const,
+            loc: self.loc,
+        }));
+        Ok(IRExpr::Ident(name))
+    }
+
+    /// Builds an arithmetic or bitwise operation. Both operands need to have the same type.
+    /// Vectors are combined lane by lane
+    pub fn build_arith(
+        &mut self,
+        op: Operator,
+        lhs: IRExpr<'b>,
+        rhs: IRExpr<'b>,
+    ) -> Result<IRExpr<'b>, VerifyError> {
+        let _type = self.type_of(&lhs)?;
+        let rhs_type = self.type_of(&rhs)?;
+        if !_type.is_integer() && !_type.is_float() && !_type.is_vector() {
+            return Err(VerifyError::TypeMismatch {
+                expected: "integer or float".into(),
+                received: _type.to_string(),
+            });
+        }
+        expect_type(&_type, &rhs_type)?;
+        let val = IRExpr::ArithOp(ArithOpExpr {
+            op,
+            values: (Box::new(lhs), Box::new(rhs)),
+            loc: self.loc,
+        });
+        Ok(self.build_temp(_type, val))
+    }
+
+    pub fn build_add(&mut self, lhs: IRExpr<'b>, rhs: IRExpr<'b>) -> Result<IRExpr<'b>, VerifyError> {
+        self.build_arith(Operator::Add, lhs, rhs)
+    }
+
+    pub fn build_sub(&mut self, lhs: IRExpr<'b>, rhs: IRExpr<'b>) -> Result<IRExpr<'b>, VerifyError> {
+        self.build_arith(Operator::Sub, lhs, rhs)
+    }
+
+    pub fn build_mul(&mut self, lhs: IRExpr<'b>, rhs: IRExpr<'b>) -> Result<IRExpr<'b>, VerifyError> {
+        self.build_arith(Operator::Mul, lhs, rhs)
+    }
+
+    /// Reads a lane of a vector
+    pub fn build_extract(&mut self, vector: IRExpr<'b>, lane: u32) -> Result<IRExpr<'b>, VerifyError> {
+        let (elem, lanes) = self.vector_type(&vector)?;
+        expect_lane(lane, lanes)?;
+        let val = IRExpr::Extract(ExtractExpr {
+            vector: Box::new(vector),
+            lane,
+            loc: self.loc,
+        });
+        Ok(self.build_temp(*elem, val))
+    }
+
+    /// Builds a copy of the vector whose lane is replaced by the value
+    pub fn build_insert(
+        &mut self,
+        vector: IRExpr<'b>,
+        val: IRExpr<'b>,
+        lane: u32,
+    ) -> Result<IRExpr<'b>, VerifyError> {
+        let (elem, lanes) = self.vector_type(&vector)?;
+        expect_lane(lane, lanes)?;
+        expect_type(elem, &self.type_of(&val)?)?;
+        let val = IRExpr::Insert(InsertExpr {
+            vector: Box::new(vector),
+            val: Box::new(val),
+            lane,
+            loc: self.loc,
+        });
+        Ok(self.build_temp(Type::Vector(elem, lanes), val))
+    }
+
+    /// Builds a vector from the lanes of two vectors of the same type, see [ShuffleExpr]
+    pub fn build_shuffle(
+        &mut self,
+        lhs: IRExpr<'b>,
+        rhs: IRExpr<'b>,
+        mask: Vec<u32>,
+    ) -> Result<IRExpr<'b>, VerifyError> {
+        let (elem, lanes) = self.vector_type(&lhs)?;
+        expect_type(&Type::Vector(elem, lanes), &self.type_of(&rhs)?)?;
+        for lane in &mask {
+            expect_lane(*lane, lanes * 2)?;
+        }
+        let _type = Type::Vector(elem, mask.len() as u32);
+        let val = IRExpr::Shuffle(ShuffleExpr {
+            values: (Box::new(lhs), Box::new(rhs)),
+            mask,
+            loc: self.loc,
+        });
+        Ok(self.build_temp(_type, val))
+    }
+
+    /// Builds a comparison which produces an [`INT1_T`]
+    pub fn build_cmp(
+        &mut self,
+        op: CmpOperator,
+        lhs: IRExpr<'b>,
+        rhs: IRExpr<'b>,
+    ) -> Result<IRExpr<'b>, VerifyError> {
+        let lhs_type = self.type_of(&lhs)?;
+        let rhs_type = self.type_of(&rhs)?;
+        expect_type(&lhs_type, &rhs_type)?;
+        let val = IRExpr::CmpOp(CmpOpExpr {
+            op,
+            values: (Box::new(lhs), Box::new(rhs)),
+            loc: self.loc,
+        });
+        Ok(self.build_temp(INT1_T, val))
+    }
+
+    /// Builds a call of a declared or defined function. Returns
+    /// [None] if the function does not return a value
+    pub fn build_call(
+        &mut self,
+        name: Ident<'b>,
+        args: Vec<IRExpr<'b>>,
+    ) -> Result<Option<IRExpr<'b>>, VerifyError> {
+        let (ret_type, arg_types) = self
+            .functions
+            .get(name)
+            .cloned()
+            .ok_or_else(|| VerifyError::UnknownFunction(name.to_string()))?;
+        if args.len() != arg_types.len() {
+            return Err(VerifyError::ArgCount {
+                func: name.to_string(),
+                expected: arg_types.len(),
+                received: args.len(),
+            });
+        }
+        for (index, (arg, expected)) in args.iter().zip(&arg_types).enumerate() {
+            let received = self.type_of(arg)?;
+            if received != *expected {
+                return Err(VerifyError::ArgType {
+                    func: name.to_string(),
+                    index,
+                    expected: expected.to_string(),
+                    received: received.to_string(),
+                });
+            }
+        }
+        let call = CallExpr {
+            name,
+            args,
+            loc: self.loc,
+        };
+        if ret_type == Type::Void {
+            self.append(IRStmt::Call(call));
+            return Ok(None);
+        }
+        Ok(Some(self.build_temp(ret_type, IRExpr::Call(call))))
+    }
+
+    /// Returns the value from the current function
+    pub fn build_ret(&mut self, val: IRExpr<'b>) -> Result<(), VerifyError> {
+        let received = self.type_of(&val)?;
+        let func = self
+            .cursor()
+            .name
+            .expect("Cannot return from the entry, use an exit instead");
+        if received != func._type {
+            return Err(VerifyError::ReturnType {
+                func: func.ident.to_string(),
+                expected: func._type.to_string(),
+                received: received.to_string(),
+            });
+        }
+        self.append(IRStmt::Return(ReturnStmt {
+            ret_val: val,
+            loc: self.loc,
+        }));
+        Ok(())
+    }
+
+    /// Exits the program with the integer exit code
+    pub fn build_exit(&mut self, exit_code: IRExpr<'b>) -> Result<(), VerifyError> {
+        let _type = self.type_of(&exit_code)?;
+        if !_type.is_integer() {
+            return Err(VerifyError::TypeMismatch {
+                expected: INT32_T.to_string(),
+                received: _type.to_string(),
+            });
+        }
+        self.append(IRStmt::Exit(ExitStmt {
+            exit_code,
+            loc: self.loc,
+        }));
+        Ok(())
+    }
+
+    /// Branches to `then_label` if the integer condition is non-zero and
+    /// to `else_label` otherwise. The labels can be created later on
+    pub fn build_br(
+        &mut self,
+        condition: IRExpr<'b>,
+        then_label: Ident<'b>,
+        else_label: Ident<'b>,
+    ) -> Result<(), VerifyError> {
+        let _type = self.type_of(&condition)?;
+        if !_type.is_integer() {
+            return Err(VerifyError::TypeMismatch {
+                expected: INT1_T.to_string(),
+                received: _type.to_string(),
+            });
+        }
+        self.cursor().targets.extend([then_label, else_label]);
+        self.append(IRStmt::Branch(BranchStmt {
+            condition,
+            then_label,
+            else_label,
+            loc: self.loc,
+        }));
+        Ok(())
+    }
+
+    pub fn build_jmp(&mut self, label: Ident<'b>) {
+        self.cursor().targets.push(label);
+        self.append(IRStmt::Jump(JumpStmt {
+            label,
+            loc: self.loc,
+        }));
+    }
+
+    pub fn stream(self) -> HIRStream<'b> {
+        if let Some(func) = self.func {
+            panic!("{} was never finished", func.display_name());
+        }
+        self.out.stream()
+    }
+
+    fn build_temp(&mut self, _type: Type<'b>, val: IRExpr<'b>) -> IRExpr<'b> {
+        let name = self.fresh_name(&format!("{RESERVED_PREFIX}t"), Counter::Temp);
+        self.cursor().variables.insert(name, _type);
+        self.append(IRStmt::Variable(VarStmt {
+            name: IRTypedIdent { ident: name, _type },
+            val,
+            is_const: true,
+            loc: self.loc,
+        }));
+        IRExpr::Ident(name)
+    }
+
+    fn type_of(&self, expr: &IRExpr<'b>) -> Result<Type<'b>, VerifyError> {
+        match expr {
+            IRExpr::Literal(_, _type) => Ok(*_type),
+            IRExpr::Ident(ident) => self
+                .func
+                .as_ref()
+                .and_then(|func| func.variables.get(ident))
+                .or_else(|| self.globals.get(ident))
+                .copied()
+                .ok_or_else(|| VerifyError::UnknownIdent(ident.to_string())),
+            IRExpr::Call(call) => self
+                .functions
+                .get(call.name)
+                .map(|(ret_type, _)| *ret_type)
+                .ok_or_else(|| VerifyError::UnknownFunction(call.name.to_string())),
+            IRExpr::ArithOp(op) => self.type_of(&op.values.0),
+            IRExpr::CmpOp(_) => Ok(INT1_T),
+            IRExpr::Not(not) => self.type_of(&not.val),
+            IRExpr::Cast(cast) => Ok(cast._type),
+            IRExpr::Load(load) => Ok(load._type),
+            IRExpr::Alloca(alloca) => Ok(Type::Pointer(self.arena.alloc(alloca._type))),
+            IRExpr::AddrOf(addr_of) => {
+                let _type = self.type_of(&addr_of.val)?;
+                Ok(Type::Pointer(self.arena.alloc(_type)))
+            }
+            IRExpr::Field(field) => {
+                let name = match self.aggregate_type(&field.base)? {
+                    Type::Ident(name) => name,
+                    _type => {
+                        return Err(VerifyError::UnknownField {
+                            _type: _type.to_string(),
+                            field: field.field.to_string(),
+                        })
+                    }
+                };
+                let (_, fields) = self
+                    .out
+                    .stream_ref()
+                    .types
+                    .get(name)
+                    .ok_or_else(|| VerifyError::UnknownType(name.to_string()))?;
+                fields
+                    .iter()
+                    .find(|typed_ident| typed_ident.ident == field.field)
+                    .map(|typed_ident| typed_ident._type)
+                    .ok_or_else(|| VerifyError::UnknownField {
+                        _type: name.to_string(),
+                        field: field.field.to_string(),
+                    })
+            }
+            IRExpr::Index(index) => match self.aggregate_type(&index.base)? {
+                Type::Array(elem, _) => Ok(*elem),
+                _type => Err(VerifyError::TypeMismatch {
+                    expected: "array".into(),
+                    received: _type.to_string(),
+                }),
+            },
+            IRExpr::Extract(extract) => Ok(*self.vector_type(&extract.vector)?.0),
+            IRExpr::Insert(insert) => self.type_of(&insert.vector),
+            IRExpr::Shuffle(shuffle) => {
+                let (elem, _) = self.vector_type(&shuffle.values.0)?;
+                Ok(Type::Vector(elem, shuffle.mask.len() as u32))
+            }
+            IRExpr::StructInit(init) => Ok(Type::Ident(init.name)),
+        }
+    }
+
+    /// Returns the element type and the number of lanes of a vector
+    fn vector_type(&self, vector: &IRExpr<'b>) -> Result<(&'b Type<'b>, u32), VerifyError> {
+        match self.type_of(vector)? {
+            Type::Vector(elem, lanes) => Ok((elem, lanes)),
+            _type => Err(VerifyError::TypeMismatch {
+                expected: "vector".into(),
+                received: _type.to_string(),
+            }),
+        }
+    }
+
+    /// Returns the type of the struct or array that a field or element is accessed on
+    fn aggregate_type(&self, base: &IRExpr<'b>) -> Result<Type<'b>, VerifyError> {
+        match self.type_of(base)? {
+            Type::Pointer(_type) => Ok(*_type),
+            _type => Ok(_type),
+        }
+    }
+
+    fn register_function(&mut self, name: Ident<'b>, args: &[IRTypedIdent<'b>], ret_type: Type<'b>) {
+        let arg_types = args.iter().map(|arg| arg._type).collect();
+        self.functions.insert(name, (ret_type, arg_types));
+    }
+
+    /// Returns the prefix followed by the next index,
+    /// skipping names that the frontend already used for its own variables or labels
+    fn fresh_name(&mut self, prefix: &str, counter: Counter) -> Ident<'b> {
+        loop {
+            let index = match counter {
+                Counter::Temp => &mut self.temp_index,
+                Counter::Label => &mut self.label_index,
+            };
+            let name = format!("{prefix}{index}");
+            *index += 1;
+            if !self.is_defined(&name, counter) {
+                return self.arena.alloc_str(&name);
+            }
+        }
+    }
+
+    fn is_defined(&self, name: &str, counter: Counter) -> bool {
+        let func = self.func.as_ref();
+        match counter {
+            Counter::Temp => {
+                self.globals.contains_key(name)
+                    || func.is_some_and(|func| func.variables.contains_key(name))
+            }
+            Counter::Label => func.is_some_and(|func| {
+                func.blocks.iter().any(|(label, _)| *label == Some(name))
+            }),
+        }
+    }
+
+    fn cursor(&mut self) -> &mut FunctionCursor<'b> {
+        self.func.as_mut().expect("No function is being built")
+    }
+
+    fn append(&mut self, stmt: IRStmt<'b>) {
+        let func = self.cursor();
+        func.blocks[func.block].1.push(stmt);
+    }
+}
+
+impl FunctionCursor<'_> {
+    fn display_name(&self) -> String {
+        match self.name {
+            Some(name) => format!("the function {}", name.ident),
+            None => "the entry".into(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Counter {
+    Temp,
+    Label,
+}
+
+fn expect_type(expected: &Type, received: &Type) -> Result<(), VerifyError> {
+    if expected == received {
+        Ok(())
+    } else {
+        Err(VerifyError::TypeMismatch {
+            expected: expected.to_string(),
+            received: received.to_string(),
+        })
+    }
+}
+
+fn expect_lane(lane: u32, lanes: u32) -> Result<(), VerifyError> {
+    if lane < lanes {
+        Ok(())
+    } else {
+        Err(VerifyError::LaneOutOfRange { lane, lanes })
+    }
+}
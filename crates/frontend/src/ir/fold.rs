@@ -0,0 +1,445 @@
+//! Rebuilding the IR by value
+//!
+//! Every method of [Fold] takes ownership of a node and returns the node that
+//! replaces it. The default implementations call the `walk_*` function of the
+//! node, which folds all children and rebuilds the node from them. Passes that
+//! replace a node with one of a different kind, like turning an arithmetic
+//! operation into a literal, override [Fold::fold_expr] or [Fold::fold_stmt].
+
+use super::*;
+
+pub trait Fold<'ir> {
+    fn fold_stmt(&mut self, stmt: IRStmt<'ir>) -> IRStmt<'ir> {
+        walk_stmt(self, stmt)
+    }
+
+    fn fold_expr(&mut self, expr: IRExpr<'ir>) -> IRExpr<'ir> {
+        walk_expr(self, expr)
+    }
+
+    fn fold_block(&mut self, block: BlockStmt<'ir>) -> BlockStmt<'ir> {
+        walk_block(self, block)
+    }
+
+    fn fold_decl_func(&mut self, func: DeclFuncStmt<'ir>) -> DeclFuncStmt<'ir> {
+        walk_decl_func(self, func)
+    }
+
+    fn fold_func(&mut self, func: FuncStmt<'ir>) -> FuncStmt<'ir> {
+        walk_func(self, func)
+    }
+
+    fn fold_var(&mut self, var: VarStmt<'ir>) -> VarStmt<'ir> {
+        walk_var(self, var)
+    }
+
+    fn fold_assign(&mut self, assign: AssignStmt<'ir>) -> AssignStmt<'ir> {
+        walk_assign(self, assign)
+    }
+
+    fn fold_label(&mut self, label: LabelStmt<'ir>) -> LabelStmt<'ir> {
+        label
+    }
+
+    fn fold_return(&mut self, ret: ReturnStmt<'ir>) -> ReturnStmt<'ir> {
+        walk_return(self, ret)
+    }
+
+    fn fold_exit(&mut self, exit: ExitStmt<'ir>) -> ExitStmt<'ir> {
+        walk_exit(self, exit)
+    }
+
+    fn fold_jump(&mut self, jump: JumpStmt<'ir>) -> JumpStmt<'ir> {
+        jump
+    }
+
+    fn fold_branch(&mut self, branch: BranchStmt<'ir>) -> BranchStmt<'ir> {
+        walk_branch(self, branch)
+    }
+
+    fn fold_store(&mut self, store: StoreStmt<'ir>) -> StoreStmt<'ir> {
+        walk_store(self, store)
+    }
+
+    fn fold_struct(&mut self, node: StructStmt<'ir>) -> StructStmt<'ir> {
+        walk_struct(self, node)
+    }
+
+    fn fold_union(&mut self, node: UnionStmt<'ir>) -> UnionStmt<'ir> {
+        walk_union(self, node)
+    }
+
+    fn fold_call(&mut self, call: CallExpr<'ir>) -> CallExpr<'ir> {
+        walk_call(self, call)
+    }
+
+    fn fold_literal(&mut self, lit: Literal<'ir>, _type: Type<'ir>) -> (Literal<'ir>, Type<'ir>) {
+        walk_literal(self, lit, _type)
+    }
+
+    /// Folds a variable that is used as a value
+    fn fold_ident(&mut self, ident: Ident<'ir>) -> Ident<'ir> {
+        ident
+    }
+
+    fn fold_arith_op(&mut self, op: ArithOpExpr<'ir>) -> ArithOpExpr<'ir> {
+        walk_arith_op(self, op)
+    }
+
+    fn fold_cmp_op(&mut self, op: CmpOpExpr<'ir>) -> CmpOpExpr<'ir> {
+        walk_cmp_op(self, op)
+    }
+
+    fn fold_not(&mut self, not: NotExpr<'ir>) -> NotExpr<'ir> {
+        walk_not(self, not)
+    }
+
+    fn fold_cast(&mut self, cast: CastExpr<'ir>) -> CastExpr<'ir> {
+        walk_cast(self, cast)
+    }
+
+    fn fold_alloca(&mut self, alloca: AllocaExpr<'ir>) -> AllocaExpr<'ir> {
+        walk_alloca(self, alloca)
+    }
+
+    fn fold_load(&mut self, load: LoadExpr<'ir>) -> LoadExpr<'ir> {
+        walk_load(self, load)
+    }
+
+    fn fold_addr_of(&mut self, addr_of: AddrOfExpr<'ir>) -> AddrOfExpr<'ir> {
+        walk_addr_of(self, addr_of)
+    }
+
+    fn fold_field(&mut self, field: FieldExpr<'ir>) -> FieldExpr<'ir> {
+        walk_field(self, field)
+    }
+
+    fn fold_index(&mut self, index: IndexExpr<'ir>) -> IndexExpr<'ir> {
+        walk_index(self, index)
+    }
+
+    fn fold_extract(&mut self, extract: ExtractExpr<'ir>) -> ExtractExpr<'ir> {
+        walk_extract(self, extract)
+    }
+
+    fn fold_insert(&mut self, insert: InsertExpr<'ir>) -> InsertExpr<'ir> {
+        walk_insert(self, insert)
+    }
+
+    fn fold_shuffle(&mut self, shuffle: ShuffleExpr<'ir>) -> ShuffleExpr<'ir> {
+        walk_shuffle(self, shuffle)
+    }
+
+    fn fold_struct_init(&mut self, init: StructInitExpr<'ir>) -> StructInitExpr<'ir> {
+        walk_struct_init(self, init)
+    }
+
+    fn fold_typed_ident(&mut self, typed_ident: IRTypedIdent<'ir>) -> IRTypedIdent<'ir> {
+        walk_typed_ident(self, typed_ident)
+    }
+
+    /// The element types of arrays, pointers and vectors are shared references
+    /// into the arena, so they are not folded separately
+    fn fold_type(&mut self, _type: Type<'ir>) -> Type<'ir> {
+        _type
+    }
+}
+
+pub fn walk_stmt<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, stmt: IRStmt<'ir>) -> IRStmt<'ir> {
+    match stmt {
+        IRStmt::Entry(block) => IRStmt::Entry(f.fold_block(block)),
+        IRStmt::DeclaredFunction(func) => IRStmt::DeclaredFunction(f.fold_decl_func(func)),
+        IRStmt::Function(func) => IRStmt::Function(f.fold_func(func)),
+        IRStmt::Variable(var) => IRStmt::Variable(f.fold_var(var)),
+        IRStmt::Assign(assign) => IRStmt::Assign(f.fold_assign(assign)),
+        IRStmt::Label(label) => IRStmt::Label(f.fold_label(label)),
+        IRStmt::Return(ret) => IRStmt::Return(f.fold_return(ret)),
+        IRStmt::Exit(exit) => IRStmt::Exit(f.fold_exit(exit)),
+        IRStmt::Jump(jump) => IRStmt::Jump(f.fold_jump(jump)),
+        IRStmt::Branch(branch) => IRStmt::Branch(f.fold_branch(branch)),
+        IRStmt::Call(call) => IRStmt::Call(f.fold_call(call)),
+        IRStmt::Store(store) => IRStmt::Store(f.fold_store(store)),
+        IRStmt::Struct(node) => IRStmt::Struct(f.fold_struct(node)),
+        IRStmt::Union(node) => IRStmt::Union(f.fold_union(node)),
+    }
+}
+
+pub fn walk_expr<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, expr: IRExpr<'ir>) -> IRExpr<'ir> {
+    match expr {
+        IRExpr::Call(call) => IRExpr::Call(f.fold_call(call)),
+        IRExpr::Literal(lit, _type) => {
+            let (lit, _type) = f.fold_literal(lit, _type);
+            IRExpr::Literal(lit, _type)
+        }
+        IRExpr::Ident(ident) => IRExpr::Ident(f.fold_ident(ident)),
+        IRExpr::ArithOp(op) => IRExpr::ArithOp(f.fold_arith_op(op)),
+        IRExpr::CmpOp(op) => IRExpr::CmpOp(f.fold_cmp_op(op)),
+        IRExpr::Not(not) => IRExpr::Not(f.fold_not(not)),
+        IRExpr::Cast(cast) => IRExpr::Cast(f.fold_cast(cast)),
+        IRExpr::Alloca(alloca) => IRExpr::Alloca(f.fold_alloca(alloca)),
+        IRExpr::Load(load) => IRExpr::Load(f.fold_load(load)),
+        IRExpr::AddrOf(addr_of) => IRExpr::AddrOf(f.fold_addr_of(addr_of)),
+        IRExpr::Field(field) => IRExpr::Field(f.fold_field(field)),
+        IRExpr::Index(index) => IRExpr::Index(f.fold_index(index)),
+        IRExpr::Extract(extract) => IRExpr::Extract(f.fold_extract(extract)),
+        IRExpr::Insert(insert) => IRExpr::Insert(f.fold_insert(insert)),
+        IRExpr::Shuffle(shuffle) => IRExpr::Shuffle(f.fold_shuffle(shuffle)),
+        IRExpr::StructInit(init) => IRExpr::StructInit(f.fold_struct_init(init)),
+    }
+}
+
+pub fn walk_block<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, block: BlockStmt<'ir>) -> BlockStmt<'ir> {
+    BlockStmt {
+        stmts: fold_vec(block.stmts, |stmt| f.fold_stmt(stmt)),
+    }
+}
+
+pub fn walk_decl_func<'ir, F: Fold<'ir> + ?Sized>(
+    f: &mut F,
+    func: DeclFuncStmt<'ir>,
+) -> DeclFuncStmt<'ir> {
+    DeclFuncStmt {
+        name: f.fold_typed_ident(func.name),
+        args: fold_vec(func.args, |arg| f.fold_typed_ident(arg)),
+        loc: func.loc,
+    }
+}
+
+pub fn walk_func<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, func: FuncStmt<'ir>) -> FuncStmt<'ir> {
+    FuncStmt {
+        name: f.fold_typed_ident(func.name),
+        args: fold_vec(func.args, |arg| f.fold_typed_ident(arg)),
+        block: f.fold_block(func.block),
+        loc: func.loc,
+    }
+}
+
+pub fn walk_var<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, var: VarStmt<'ir>) -> VarStmt<'ir> {
+    VarStmt {
+        name: f.fold_typed_ident(var.name),
+        val: f.fold_expr(var.val),
+        is_const: var.is_const,
+        loc: var.loc,
+    }
+}
+
+pub fn walk_assign<'ir, F: Fold<'ir> + ?Sized>(
+    f: &mut F,
+    assign: AssignStmt<'ir>,
+) -> AssignStmt<'ir> {
+    AssignStmt {
+        name: assign.name,
+        val: f.fold_expr(assign.val),
+        loc: assign.loc,
+    }
+}
+
+pub fn walk_return<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, ret: ReturnStmt<'ir>) -> ReturnStmt<'ir> {
+    ReturnStmt {
+        ret_val: f.fold_expr(ret.ret_val),
+        loc: ret.loc,
+    }
+}
+
+pub fn walk_exit<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, exit: ExitStmt<'ir>) -> ExitStmt<'ir> {
+    ExitStmt {
+        exit_code: f.fold_expr(exit.exit_code),
+        loc: exit.loc,
+    }
+}
+
+pub fn walk_branch<'ir, F: Fold<'ir> + ?Sized>(
+    f: &mut F,
+    branch: BranchStmt<'ir>,
+) -> BranchStmt<'ir> {
+    BranchStmt {
+        condition: f.fold_expr(branch.condition),
+        ..branch
+    }
+}
+
+pub fn walk_store<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, store: StoreStmt<'ir>) -> StoreStmt<'ir> {
+    StoreStmt {
+        val: f.fold_expr(store.val),
+        ptr: f.fold_expr(store.ptr),
+        loc: store.loc,
+    }
+}
+
+pub fn walk_struct<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, node: StructStmt<'ir>) -> StructStmt<'ir> {
+    StructStmt {
+        name: node.name,
+        fields: fold_vec(node.fields, |field| f.fold_typed_ident(field)),
+        loc: node.loc,
+    }
+}
+
+pub fn walk_union<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, node: UnionStmt<'ir>) -> UnionStmt<'ir> {
+    UnionStmt {
+        name: node.name,
+        variants: fold_vec(node.variants, |variant| f.fold_typed_ident(variant)),
+        loc: node.loc,
+    }
+}
+
+pub fn walk_call<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, call: CallExpr<'ir>) -> CallExpr<'ir> {
+    CallExpr {
+        name: call.name,
+        args: fold_vec(call.args, |arg| f.fold_expr(arg)),
+        loc: call.loc,
+    }
+}
+
+pub fn walk_literal<'ir, F: Fold<'ir> + ?Sized>(
+    f: &mut F,
+    lit: Literal<'ir>,
+    _type: Type<'ir>,
+) -> (Literal<'ir>, Type<'ir>) {
+    let lit = match lit {
+        Literal::Array(len, vals) => Literal::Array(len, fold_vec(vals, |val| f.fold_expr(val))),
+        Literal::Vector(vals) => Literal::Vector(fold_vec(vals, |val| f.fold_expr(val))),
+        lit => lit,
+    };
+    (lit, f.fold_type(_type))
+}
+
+pub fn walk_arith_op<'ir, F: Fold<'ir> + ?Sized>(
+    f: &mut F,
+    op: ArithOpExpr<'ir>,
+) -> ArithOpExpr<'ir> {
+    let (lhs, rhs) = op.values;
+    ArithOpExpr {
+        op: op.op,
+        values: (fold_box(lhs, f), fold_box(rhs, f)),
+        loc: op.loc,
+    }
+}
+
+pub fn walk_cmp_op<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, op: CmpOpExpr<'ir>) -> CmpOpExpr<'ir> {
+    let (lhs, rhs) = op.values;
+    CmpOpExpr {
+        op: op.op,
+        values: (fold_box(lhs, f), fold_box(rhs, f)),
+        loc: op.loc,
+    }
+}
+
+pub fn walk_not<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, not: NotExpr<'ir>) -> NotExpr<'ir> {
+    NotExpr {
+        val: fold_box(not.val, f),
+        loc: not.loc,
+    }
+}
+
+pub fn walk_cast<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, cast: CastExpr<'ir>) -> CastExpr<'ir> {
+    CastExpr {
+        kind: cast.kind,
+        _type: f.fold_type(cast._type),
+        val: fold_box(cast.val, f),
+        loc: cast.loc,
+    }
+}
+
+pub fn walk_alloca<'ir, F: Fold<'ir> + ?Sized>(
+    f: &mut F,
+    alloca: AllocaExpr<'ir>,
+) -> AllocaExpr<'ir> {
+    AllocaExpr {
+        _type: f.fold_type(alloca._type),
+        loc: alloca.loc,
+    }
+}
+
+pub fn walk_load<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, load: LoadExpr<'ir>) -> LoadExpr<'ir> {
+    LoadExpr {
+        _type: f.fold_type(load._type),
+        ptr: fold_box(load.ptr, f),
+        loc: load.loc,
+    }
+}
+
+pub fn walk_addr_of<'ir, F: Fold<'ir> + ?Sized>(
+    f: &mut F,
+    addr_of: AddrOfExpr<'ir>,
+) -> AddrOfExpr<'ir> {
+    AddrOfExpr {
+        val: fold_box(addr_of.val, f),
+        loc: addr_of.loc,
+    }
+}
+
+pub fn walk_field<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, field: FieldExpr<'ir>) -> FieldExpr<'ir> {
+    FieldExpr {
+        base: fold_box(field.base, f),
+        field: field.field,
+        loc: field.loc,
+    }
+}
+
+pub fn walk_index<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, index: IndexExpr<'ir>) -> IndexExpr<'ir> {
+    IndexExpr {
+        base: fold_box(index.base, f),
+        index: fold_box(index.index, f),
+        loc: index.loc,
+    }
+}
+
+pub fn walk_extract<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, extract: ExtractExpr<'ir>) -> ExtractExpr<'ir> {
+    ExtractExpr {
+        vector: fold_box(extract.vector, f),
+        lane: extract.lane,
+        loc: extract.loc,
+    }
+}
+
+pub fn walk_insert<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, insert: InsertExpr<'ir>) -> InsertExpr<'ir> {
+    InsertExpr {
+        vector: fold_box(insert.vector, f),
+        val: fold_box(insert.val, f),
+        lane: insert.lane,
+        loc: insert.loc,
+    }
+}
+
+pub fn walk_shuffle<'ir, F: Fold<'ir> + ?Sized>(f: &mut F, shuffle: ShuffleExpr<'ir>) -> ShuffleExpr<'ir> {
+    let (lhs, rhs) = shuffle.values;
+    ShuffleExpr {
+        values: (fold_box(lhs, f), fold_box(rhs, f)),
+        mask: shuffle.mask,
+        loc: shuffle.loc,
+    }
+}
+
+pub fn walk_struct_init<'ir, F: Fold<'ir> + ?Sized>(
+    f: &mut F,
+    init: StructInitExpr<'ir>,
+) -> StructInitExpr<'ir> {
+    StructInitExpr {
+        name: init.name,
+        values: fold_vec(init.values, |val| f.fold_expr(val)),
+        loc: init.loc,
+    }
+}
+
+pub fn walk_typed_ident<'ir, F: Fold<'ir> + ?Sized>(
+    f: &mut F,
+    typed_ident: IRTypedIdent<'ir>,
+) -> IRTypedIdent<'ir> {
+    IRTypedIdent {
+        ident: typed_ident.ident,
+        _type: f.fold_type(typed_ident._type),
+    }
+}
+
+/// Folds the boxed expression and reuses its allocation
+fn fold_box<'ir, F: Fold<'ir> + ?Sized>(
+    mut expr: Box<IRExpr<'ir>>,
+    f: &mut F,
+) -> Box<IRExpr<'ir>> {
+    *expr = f.fold_expr(*expr);
+    expr
+}
+
+fn fold_vec<T>(items: Vec<T>, fold: impl FnMut(T) -> T) -> Vec<T> {
+    items.into_iter().map(fold).collect()
+}
@@ -8,7 +8,7 @@ use std::{collections::HashMap, fmt::Display};
 
 use crate::{ir::IRStmt, util::CompositeDataType};
 
-use super::{IRTypedIdent, Ident};
+use super::{IRTypedIdent, Ident, SourceLoc};
 
 pub type TypeTable<'t> = HashMap<Ident<'t>, (CompositeDataType, Vec<IRTypedIdent<'t>>)>;
 
@@ -44,6 +44,12 @@ impl<'g> IRGenerator<'g> {
         self.ir.stream.push(node);
     }
 
+    /// Generates the node and marks it with the location in the source
+    /// code of the frontend that it was generated from
+    pub fn gen_ir_at(&mut self, node: IRStmt<'g>, loc: SourceLoc<'g>) {
+        self.gen_ir(node.with_loc(Some(loc)));
+    }
+
     pub fn mut_stream_ref(&mut self) -> &mut HIRStream<'g> {
         &mut self.ir
     }
@@ -6,13 +6,28 @@
 
 pub mod traits;
 pub mod irgen;
+pub mod builder;
+pub mod bitcode;
+pub mod visit;
+pub mod visit_mut;
+pub mod fold;
+pub mod verifier;
 
-pub const INT8_T: &str = "i8";
-pub const INT16_T: &str = "i16";
-pub const INT32_T: &str = "i32";
-pub const INT64_T: &str = "i64";
-pub const FLOAT32_T: &str = "f32";
-pub const FLOAT64_T: &str = "f64";
+use crate::util::errors::InvalidCast;
+
+pub const INT1_T: Type = Type::Bool;
+pub const INT8_T: Type = Type::Int(IntType::I8);
+pub const INT16_T: Type = Type::Int(IntType::I16);
+pub const INT32_T: Type = Type::Int(IntType::I32);
+pub const INT64_T: Type = Type::Int(IntType::I64);
+pub const INT128_T: Type = Type::Int(IntType::I128);
+pub const UINT8_T: Type = Type::Int(IntType::U8);
+pub const UINT16_T: Type = Type::Int(IntType::U16);
+pub const UINT32_T: Type = Type::Int(IntType::U32);
+pub const UINT64_T: Type = Type::Int(IntType::U64);
+pub const UINT128_T: Type = Type::Int(IntType::U128);
+pub const FLOAT32_T: Type = Type::Float(FloatType::F32);
+pub const FLOAT64_T: Type = Type::Float(FloatType::F64);
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum IRStmt<'ir> {
@@ -21,12 +36,15 @@ pub enum IRStmt<'ir> {
     DeclaredFunction(DeclFuncStmt<'ir>),
     Function(FuncStmt<'ir>),
     Variable(VarStmt<'ir>),
+    Assign(AssignStmt<'ir>),
     Label(LabelStmt<'ir>),
 
     Return(ReturnStmt<'ir>),
     Exit(ExitStmt<'ir>),
     Jump(JumpStmt<'ir>),
+    Branch(BranchStmt<'ir>),
     Call(CallExpr<'ir>),
+    Store(StoreStmt<'ir>),
 
     Struct(StructStmt<'ir>),
     Union(UnionStmt<'ir>),
@@ -39,19 +57,62 @@ pub enum IRExpr<'ir> {
     Ident(Ident<'ir>),
 
     ArithOp(ArithOpExpr<'ir>),
+    CmpOp(CmpOpExpr<'ir>),
+    Not(NotExpr<'ir>),
+    Cast(CastExpr<'ir>),
+
+    Alloca(AllocaExpr<'ir>),
+    Load(LoadExpr<'ir>),
+    AddrOf(AddrOfExpr<'ir>),
+    Field(FieldExpr<'ir>),
+    Index(IndexExpr<'ir>),
+
+    Extract(ExtractExpr<'ir>),
+    Insert(InsertExpr<'ir>),
+    Shuffle(ShuffleExpr<'ir>),
 
     StructInit(StructInitExpr<'ir>),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Type<'ir> {
+    /// `i1`, the type comparisons evaluate to
+    Bool,
+    Int(IntType),
+    Float(FloatType),
+    Void,
+    /// A named struct or union, resolved through the [TypeTable](irgen::TypeTable)
     Ident(Ident<'ir>),
     Array(&'ir Type<'ir>, u32),
+    Pointer(&'ir Type<'ir>),
+    /// A fixed number of integer or float lanes that arithmetic operates on element-wise
+    Vector(&'ir Type<'ir>, u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntType {
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FloatType {
+    F32,
+    F64,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Literal<'ir> {
-    String(&'ir str),
+    /// The bytes of the string, which do not have to be valid UTF-8
+    String(&'ir [u8]),
     Char(u8),
 
     Float32(f32),
@@ -66,25 +127,86 @@ pub enum Literal<'ir> {
     Int64(i64),
     Int128(i128),
 
+    UInt8(u8),
+    UInt16(u16),
+    UInt32(u32),
+    UInt64(u64),
+    UInt128(u128),
+
     Array(usize, Vec<IRExpr<'ir>>),
     Vector(Vec<IRExpr<'ir>>),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Arithmetic operators. The `S` and `U` prefixes specify
+/// whether the values are treated as signed or unsigned
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Operator {
     Add,
     Sub,
     Mul,
-    Div,
+    SDiv,
+    UDiv,
+    SRem,
+    URem,
+    And,
+    Or,
+    XOr,
+    Shl,
+    /// Logical shift right, fills the vacated bits with zeros
+    LShr,
+    /// Arithmetic shift right, fills the vacated bits with the sign bit
+    AShr,
+}
+
+/// Conditions for comparing two integers. The `S` and `U` prefixes
+/// specify whether the values are treated as signed or unsigned
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CmpOperator {
+    Eq,
+    Ne,
+    SLt,
+    SLe,
+    SGt,
+    SGe,
+    ULt,
+    ULe,
+    UGt,
+    UGe,
+}
+
+/// The conversion that a cast performs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CastKind {
+    /// Sign extension to a larger integer
+    SExt,
+    /// Zero extension to a larger integer
+    ZExt,
+    /// Truncation to a smaller integer
+    Trunc,
+    /// Conversion from a signed integer to a float
+    IntToFloat,
+    /// Conversion from a float to a signed integer
+    FloatToInt,
+    /// Reinterprets the bits as a type of the same size
+    Bitcast,
 }
 
 pub type Ident<'ir> = &'ir str;
 
+/// The position in the source code that a statement or expression was generated from.
+/// Lines and columns start at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourceLoc<'ir> {
+    pub file: &'ir str,
+    pub line: u32,
+    pub col: u32,
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct DeclFuncStmt<'ir> {
     pub name: IRTypedIdent<'ir>,
     pub args: Vec<IRTypedIdent<'ir>>,
+    pub loc: Option<SourceLoc<'ir>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -92,6 +214,7 @@ pub struct FuncStmt<'ir> {
     pub name: IRTypedIdent<'ir>,
     pub args: Vec<IRTypedIdent<'ir>>,
     pub block: BlockStmt<'ir>,
+    pub loc: Option<SourceLoc<'ir>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -99,38 +222,71 @@ pub struct VarStmt<'ir> {
     pub name: IRTypedIdent<'ir>,
     pub val: IRExpr<'ir>,
     pub is_const: bool,
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+/// Assigns a new value to a variable. Constants (`$`) cannot be reassigned
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignStmt<'ir> {
+    pub name: Ident<'ir>,
+    pub val: IRExpr<'ir>,
+    pub loc: Option<SourceLoc<'ir>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructStmt<'ir> {
     pub name: Ident<'ir>,
     pub fields: Vec<IRTypedIdent<'ir>>,
+    pub loc: Option<SourceLoc<'ir>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct UnionStmt<'ir> {
     pub name: Ident<'ir>,
     pub variants: Vec<IRTypedIdent<'ir>>,
+    pub loc: Option<SourceLoc<'ir>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct LabelStmt<'ir> {
     pub name: Ident<'ir>,
+    pub loc: Option<SourceLoc<'ir>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ReturnStmt<'ir> {
     pub ret_val: IRExpr<'ir>,
+    pub loc: Option<SourceLoc<'ir>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ExitStmt<'ir> {
     pub exit_code: IRExpr<'ir>,
+    pub loc: Option<SourceLoc<'ir>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct JumpStmt<'ir> {
     pub label: Ident<'ir>,
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+/// Jumps to `then_label` if the condition is non-zero
+/// and to `else_label` otherwise
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchStmt<'ir> {
+    pub condition: IRExpr<'ir>,
+    pub then_label: Ident<'ir>,
+    pub else_label: Ident<'ir>,
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+/// Writes `val` to the memory that `ptr` points to
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoreStmt<'ir> {
+    pub val: IRExpr<'ir>,
+    pub ptr: IRExpr<'ir>,
+    pub loc: Option<SourceLoc<'ir>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -148,16 +304,324 @@ pub struct BlockStmt<'ir> {
 pub struct CallExpr<'ir> {
     pub name: Ident<'ir>,
     pub args: Vec<IRExpr<'ir>>,
+    pub loc: Option<SourceLoc<'ir>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructInitExpr<'ir> {
     pub name: Ident<'ir>,
     pub values: Vec<IRExpr<'ir>>,
+    pub loc: Option<SourceLoc<'ir>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ArithOpExpr<'ir> {
     pub op: Operator,
-    pub values: (Box<IRExpr<'ir>>, Box<IRExpr<'ir>>)
+    pub values: (Box<IRExpr<'ir>>, Box<IRExpr<'ir>>),
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+/// Compares two values and produces an [`INT1_T`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CmpOpExpr<'ir> {
+    pub op: CmpOperator,
+    pub values: (Box<IRExpr<'ir>>, Box<IRExpr<'ir>>),
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+/// Inverts every bit of a value
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotExpr<'ir> {
+    pub val: Box<IRExpr<'ir>>,
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+/// Reserves stack memory for a value of `_type` and produces a pointer to it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AllocaExpr<'ir> {
+    pub _type: Type<'ir>,
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+/// Reads a value of `_type` from the memory that `ptr` points to
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadExpr<'ir> {
+    pub _type: Type<'ir>,
+    pub ptr: Box<IRExpr<'ir>>,
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+/// Produces a pointer to a variable, field or element
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddrOfExpr<'ir> {
+    pub val: Box<IRExpr<'ir>>,
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+/// Accesses the field `field` of a struct or union. The base is
+/// either the struct itself or a pointer to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldExpr<'ir> {
+    pub base: Box<IRExpr<'ir>>,
+    pub field: Ident<'ir>,
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+/// Accesses the element at `index` of an array. The base is
+/// either the array itself or a pointer to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexExpr<'ir> {
+    pub base: Box<IRExpr<'ir>>,
+    pub index: Box<IRExpr<'ir>>,
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+/// Reads the lane `lane` of a vector
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractExpr<'ir> {
+    pub vector: Box<IRExpr<'ir>>,
+    pub lane: u32,
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+/// Produces a copy of a vector whose lane `lane` is replaced by `val`
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsertExpr<'ir> {
+    pub vector: Box<IRExpr<'ir>>,
+    pub val: Box<IRExpr<'ir>>,
+    pub lane: u32,
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+/// Builds a vector with one lane per entry of the mask from the lanes of two vectors
+/// of the same type. Entries below the lane count select a lane of the first vector,
+/// the others select the lane `entry - lanes` of the second one
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShuffleExpr<'ir> {
+    pub values: (Box<IRExpr<'ir>>, Box<IRExpr<'ir>>),
+    pub mask: Vec<u32>,
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+/// Converts a value to `_type`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CastExpr<'ir> {
+    pub kind: CastKind,
+    pub _type: Type<'ir>,
+    pub val: Box<IRExpr<'ir>>,
+    pub loc: Option<SourceLoc<'ir>>,
+}
+
+impl<'ir> IRStmt<'ir> {
+    /// Returns the source location of the statement. Entry blocks do not have one
+    pub fn loc(&self) -> Option<SourceLoc<'ir>> {
+        match self {
+            IRStmt::Entry(_) => None,
+            IRStmt::DeclaredFunction(node) => node.loc,
+            IRStmt::Function(node) => node.loc,
+            IRStmt::Variable(node) => node.loc,
+            IRStmt::Assign(node) => node.loc,
+            IRStmt::Label(node) => node.loc,
+            IRStmt::Return(node) => node.loc,
+            IRStmt::Exit(node) => node.loc,
+            IRStmt::Jump(node) => node.loc,
+            IRStmt::Branch(node) => node.loc,
+            IRStmt::Call(node) => node.loc,
+            IRStmt::Store(node) => node.loc,
+            IRStmt::Struct(node) => node.loc,
+            IRStmt::Union(node) => node.loc,
+        }
+    }
+
+    /// Sets the source location of the statement. Has no effect on entry blocks
+    pub fn with_loc(mut self, loc: Option<SourceLoc<'ir>>) -> Self {
+        match &mut self {
+            IRStmt::Entry(_) => (),
+            IRStmt::DeclaredFunction(node) => node.loc = loc,
+            IRStmt::Function(node) => node.loc = loc,
+            IRStmt::Variable(node) => node.loc = loc,
+            IRStmt::Assign(node) => node.loc = loc,
+            IRStmt::Label(node) => node.loc = loc,
+            IRStmt::Return(node) => node.loc = loc,
+            IRStmt::Exit(node) => node.loc = loc,
+            IRStmt::Jump(node) => node.loc = loc,
+            IRStmt::Branch(node) => node.loc = loc,
+            IRStmt::Call(node) => node.loc = loc,
+            IRStmt::Store(node) => node.loc = loc,
+            IRStmt::Struct(node) => node.loc = loc,
+            IRStmt::Union(node) => node.loc = loc,
+        }
+        self
+    }
+}
+
+impl<'ir> IRExpr<'ir> {
+    /// Returns the source location of the expression. Literals and identifiers
+    /// do not have one, they are located by the node they are part of
+    pub fn loc(&self) -> Option<SourceLoc<'ir>> {
+        match self {
+            IRExpr::Literal(..) | IRExpr::Ident(_) => None,
+            IRExpr::Call(node) => node.loc,
+            IRExpr::ArithOp(node) => node.loc,
+            IRExpr::CmpOp(node) => node.loc,
+            IRExpr::Not(node) => node.loc,
+            IRExpr::Cast(node) => node.loc,
+            IRExpr::Alloca(node) => node.loc,
+            IRExpr::Load(node) => node.loc,
+            IRExpr::AddrOf(node) => node.loc,
+            IRExpr::Field(node) => node.loc,
+            IRExpr::Index(node) => node.loc,
+            IRExpr::Extract(node) => node.loc,
+            IRExpr::Insert(node) => node.loc,
+            IRExpr::Shuffle(node) => node.loc,
+            IRExpr::StructInit(node) => node.loc,
+        }
+    }
+
+    /// Sets the source location of the expression. Has no effect on literals and identifiers
+    pub fn with_loc(mut self, loc: Option<SourceLoc<'ir>>) -> Self {
+        match &mut self {
+            IRExpr::Literal(..) | IRExpr::Ident(_) => (),
+            IRExpr::Call(node) => node.loc = loc,
+            IRExpr::ArithOp(node) => node.loc = loc,
+            IRExpr::CmpOp(node) => node.loc = loc,
+            IRExpr::Not(node) => node.loc = loc,
+            IRExpr::Cast(node) => node.loc = loc,
+            IRExpr::Alloca(node) => node.loc = loc,
+            IRExpr::Load(node) => node.loc = loc,
+            IRExpr::AddrOf(node) => node.loc = loc,
+            IRExpr::Field(node) => node.loc = loc,
+            IRExpr::Index(node) => node.loc = loc,
+            IRExpr::Extract(node) => node.loc = loc,
+            IRExpr::Insert(node) => node.loc = loc,
+            IRExpr::Shuffle(node) => node.loc = loc,
+            IRExpr::StructInit(node) => node.loc = loc,
+        }
+        self
+    }
+}
+
+impl CastKind {
+    /// Checks whether a value of type `from` can be converted to `to` using this kind of cast
+    pub fn check(&self, from: &Type, to: &Type) -> Result<(), InvalidCast> {
+        let valid = match self {
+            CastKind::SExt | CastKind::ZExt => {
+                from.is_integer() && to.is_integer() && from.bit_width() < to.bit_width()
+            }
+            CastKind::Trunc => {
+                from.is_integer() && to.is_integer() && from.bit_width() > to.bit_width()
+            }
+            CastKind::IntToFloat => from.is_integer() && to.is_float(),
+            CastKind::FloatToInt => from.is_float() && to.is_integer(),
+            CastKind::Bitcast => from.bit_width().is_some() && from.bit_width() == to.bit_width(),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(InvalidCast(format!("cast {self} {to}"), from.to_string()))
+        }
+    }
+}
+
+impl<'ir> Type<'ir> {
+    /// Resolves a type name to a primitive type
+    /// or to a named type if it is not one of the builtin ones
+    pub fn from_ident(ident: Ident<'ir>) -> Self {
+        match ident {
+            "i1" => Type::Bool,
+            "i8" => INT8_T,
+            "i16" => INT16_T,
+            "i32" => INT32_T,
+            "i64" => INT64_T,
+            "i128" => INT128_T,
+            "u8" => UINT8_T,
+            "u16" => UINT16_T,
+            "u32" => UINT32_T,
+            "u64" => UINT64_T,
+            "u128" => UINT128_T,
+            "f32" => FLOAT32_T,
+            "f64" => FLOAT64_T,
+            "void" => Type::Void,
+            _ => Type::Ident(ident),
+        }
+    }
+
+    /// Whether this is an integer type, this includes [`Type::Bool`]
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Type::Bool | Type::Int(_))
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Type::Float(_))
+    }
+
+    pub fn is_vector(&self) -> bool {
+        matches!(self, Type::Vector(..))
+    }
+
+    /// The width in bits of an integer or float type
+    pub fn bit_width(&self) -> Option<u32> {
+        match self {
+            Type::Bool => Some(1),
+            Type::Int(int) => Some(int.bit_width()),
+            Type::Float(float) => Some(float.bit_width()),
+            _ => None,
+        }
+    }
+
+    /// Whether the value fits into this integer type
+    pub fn fits(&self, val: i128) -> bool {
+        match self {
+            Type::Bool => (0..=1).contains(&val),
+            Type::Int(int) => match (int.is_signed(), int.bit_width()) {
+                (true, 128) => true,
+                (true, bits) => -(1i128 << (bits - 1)) <= val && val < (1i128 << (bits - 1)),
+                (false, 128) => val >= 0,
+                (false, bits) => (0..(1i128 << bits)).contains(&val),
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether this is a signed integer type. Floats are always signed
+    /// but are handled separately, so they return `false` like all non-integers
+    pub fn is_signed(&self) -> bool {
+        match self {
+            Type::Int(int) => int.is_signed(),
+            _ => false,
+        }
+    }
+}
+
+impl IntType {
+    pub fn bit_width(&self) -> u32 {
+        match self {
+            IntType::I8 => 8,
+            IntType::I16 => 16,
+            IntType::I32 => 32,
+            IntType::I64 => 64,
+            IntType::I128 => 128,
+            IntType::U8 => 8,
+            IntType::U16 => 16,
+            IntType::U32 => 32,
+            IntType::U64 => 64,
+            IntType::U128 => 128,
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            IntType::I8 | IntType::I16 | IntType::I32 | IntType::I64 | IntType::I128
+        )
+    }
+}
+
+impl FloatType {
+    pub fn bit_width(&self) -> u32 {
+        match self {
+            FloatType::F32 => 32,
+            FloatType::F64 => 64,
+        }
+    }
 }
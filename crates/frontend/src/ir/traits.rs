@@ -1,5 +1,5 @@
 //! This file contains trait implementations for the IR node and utility structs for the frontend ir representation.
-use crate::util::VecDisplay;
+use crate::util::{escape, VecDisplay};
 use std::fmt::Display;
 
 use super::*;
@@ -42,6 +42,12 @@ impl Display for VarStmt<'_> {
     }
 }
 
+impl Display for AssignStmt<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "%{} = {}", self.name, self.val)
+    }
+}
+
 impl Display for StructStmt<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -78,7 +84,17 @@ impl Display for ReturnStmt<'_> {
 
 impl Display for JumpStmt<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "jmp {}", self.label)
+        write!(f, "jmp '{}", self.label)
+    }
+}
+
+impl Display for BranchStmt<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "br {}, '{}, '{}",
+            self.condition, self.then_label, self.else_label
+        )
     }
 }
 
@@ -103,31 +119,152 @@ impl Display for CallExpr<'_> {
 
 impl Display for ArithOpExpr<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}, {}", self.op, self.values.0, self.values.1)
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Operator::Add => "add",
+            Operator::Sub => "sub",
+            Operator::Mul => "mul",
+            Operator::SDiv => "sdiv",
+            Operator::UDiv => "udiv",
+            Operator::SRem => "srem",
+            Operator::URem => "urem",
+            Operator::And => "and",
+            Operator::Or => "or",
+            Operator::XOr => "xor",
+            Operator::Shl => "shl",
+            Operator::LShr => "lshr",
+            Operator::AShr => "ashr",
+        })
+    }
+}
+
+impl Display for StoreStmt<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "store {}, {}", self.val, self.ptr)
+    }
+}
+
+impl Display for AllocaExpr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "alloca {}", self._type)
+    }
+}
+
+impl Display for LoadExpr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "load {}, {}", self._type, self.ptr)
+    }
+}
+
+impl Display for AddrOfExpr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "addr-of {}", self.val)
+    }
+}
+
+impl Display for FieldExpr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "getfield {}, {}", self.base, self.field)
+    }
+}
+
+impl Display for IndexExpr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "getelem {}, {}", self.base, self.index)
+    }
+}
+
+impl Display for ExtractExpr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "extractelem {}, {}", self.vector, self.lane)
+    }
+}
+
+impl Display for InsertExpr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "insertelem {}, {}, {}", self.vector, self.val, self.lane)
+    }
+}
+
+impl Display for ShuffleExpr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mask: Vec<String> = self.mask.iter().map(u32::to_string).collect();
         write!(
             f,
-            "{} {}, {}",
-            match self.op {
-                Operator::Add => "add",
-                Operator::Sub => "sub",
-                Operator::Mul => "mul",
-                Operator::Div => "div",
-            },
+            "shuffle {}, {}, [{}]",
             self.values.0,
-            self.values.1
+            self.values.1,
+            mask.join(",")
         )
     }
 }
 
+impl Display for NotExpr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "not {}", self.val)
+    }
+}
+
+impl Display for CmpOpExpr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cmp {} {}, {}", self.op, self.values.0, self.values.1)
+    }
+}
+
+impl Display for CmpOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CmpOperator::Eq => "eq",
+            CmpOperator::Ne => "ne",
+            CmpOperator::SLt => "slt",
+            CmpOperator::SLe => "sle",
+            CmpOperator::SGt => "sgt",
+            CmpOperator::SGe => "sge",
+            CmpOperator::ULt => "ult",
+            CmpOperator::ULe => "ule",
+            CmpOperator::UGt => "ugt",
+            CmpOperator::UGe => "uge",
+        })
+    }
+}
+
+impl Display for CastExpr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cast {} {}, {}", self.kind, self._type, self.val)
+    }
+}
+
+impl Display for CastKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CastKind::SExt => "sext",
+            CastKind::ZExt => "zext",
+            CastKind::Trunc => "trunc",
+            CastKind::IntToFloat => "itof",
+            CastKind::FloatToInt => "ftoi",
+            CastKind::Bitcast => "bitcast",
+        })
+    }
+}
+
 impl Display for IRStmt<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(&match self {
             IRStmt::DeclaredFunction(func) => func.to_string(),
             IRStmt::Function(func) => func.to_string(),
             IRStmt::Variable(var) => var.to_string(),
+            IRStmt::Assign(assign) => assign.to_string(),
             IRStmt::Label(label) => label.to_string(),
             IRStmt::Return(ret) => ret.to_string(),
             IRStmt::Exit(exit) => exit.to_string(),
             IRStmt::Jump(jump) => jump.to_string(),
+            IRStmt::Branch(branch) => branch.to_string(),
+            IRStmt::Store(store) => store.to_string(),
             IRStmt::Call(call) => call.to_string(),
             IRStmt::Struct(_struct) => _struct.to_string(),
             IRStmt::Union(union) => union.to_string(),
@@ -146,7 +283,18 @@ impl Display for IRExpr<'_> {
             IRExpr::Call(call) => call.to_string(),
             IRExpr::Literal(lit, _type) => format!("l{{{}:{}}}", lit, _type),
             IRExpr::ArithOp(op) => op.to_string(),
-            IRExpr::Ident(id) => id.to_string(),
+            IRExpr::CmpOp(op) => op.to_string(),
+            IRExpr::Not(not) => not.to_string(),
+            IRExpr::Alloca(alloca) => alloca.to_string(),
+            IRExpr::Load(load) => load.to_string(),
+            IRExpr::AddrOf(addr_of) => addr_of.to_string(),
+            IRExpr::Field(field) => field.to_string(),
+            IRExpr::Index(index) => index.to_string(),
+            IRExpr::Extract(extract) => extract.to_string(),
+            IRExpr::Insert(insert) => insert.to_string(),
+            IRExpr::Shuffle(shuffle) => shuffle.to_string(),
+            IRExpr::Cast(cast) => cast.to_string(),
+            IRExpr::Ident(id) => format!("%{id}"),
             IRExpr::StructInit(init) => init.to_string(),
         })
     }
@@ -164,16 +312,23 @@ impl Display for Literal<'_> {
             f,
             "{}",
             &match self {
-                Literal::String(string) => format!("\"{}\"", string),
-                Literal::Char(char) => char.to_string(),
-                Literal::Float32(val) => val.to_string(),
-                Literal::Float64(val) => val.to_string(),
+                Literal::String(string) => format!("\"{}\"", escape::escape_str(string)),
+                Literal::Char(char) => format!("'{}'", escape::escape_char(*char)),
+                // Debug always prints a fraction or exponent, so the
+                // value can't be mistaken for an integer
+                Literal::Float32(val) => format!("{val:?}"),
+                Literal::Float64(val) => format!("{val:?}"),
                 Literal::Bool(val) => val.to_string(),
                 Literal::Int8(val) => val.to_string(),
                 Literal::Int16(val) => val.to_string(),
                 Literal::Int32(val) => val.to_string(),
                 Literal::Int64(val) => val.to_string(),
                 Literal::Int128(val) => val.to_string(),
+                Literal::UInt8(val) => val.to_string(),
+                Literal::UInt16(val) => val.to_string(),
+                Literal::UInt32(val) => val.to_string(),
+                Literal::UInt64(val) => val.to_string(),
+                Literal::UInt128(val) => val.to_string(),
                 Literal::Array(len, val) => format!("[{}; {}]", val.to_string(), len),
                 Literal::Vector(val) => format!("<{}>", val.to_string()),
             }
@@ -184,14 +339,39 @@ impl Display for Literal<'_> {
 impl Display for Type<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Type::Bool => write!(f, "i1"),
+            Type::Int(int) => write!(f, "{int}"),
+            Type::Float(float) => write!(f, "{float}"),
+            Type::Void => write!(f, "void"),
             Type::Ident(ident) => write!(f, "{ident}"),
             Type::Array(_type, size) => write!(f, "[{_type}; {size}]"),
+            Type::Pointer(_type) => write!(f, "*{_type}"),
+            Type::Vector(_type, lanes) => write!(f, "<{_type}; {lanes}>"),
         }
     }
 }
 
+impl Display for IntType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let prefix = if self.is_signed() { 'i' } else { 'u' };
+        write!(f, "{prefix}{}", self.bit_width())
+    }
+}
+
+impl Display for FloatType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "f{}", self.bit_width())
+    }
+}
+
 impl Display for ExitStmt<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "exit {}", self.exit_code)
     }
 }
+
+impl Display for SourceLoc<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.col)
+    }
+}
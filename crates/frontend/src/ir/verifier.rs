@@ -0,0 +1,572 @@
+//! Semantic checks for an [HIRStream]. Running the verifier before
+//! handing the stream to a backend turns malformed IR into a list of
+//! errors instead of panics or wrong code further down the line
+
+use std::collections::{HashMap, HashSet};
+
+use crate::util::errors::VerifyError;
+
+use super::{
+    irgen::{HIRStream, TypeTable},
+    BlockStmt, CallExpr, IRExpr, IRStmt, IRTypedIdent, Ident, Literal, Operator, Type, FLOAT32_T,
+    FLOAT64_T, INT128_T, INT16_T, INT1_T, INT32_T, INT64_T, INT8_T, UINT128_T, UINT16_T, UINT32_T,
+    UINT64_T, UINT8_T,
+};
+
+/// Checks the stream and returns every error that was found
+pub fn verify<'v>(stream: &'v HIRStream<'v>) -> Result<(), Vec<VerifyError>> {
+    let mut verifier = Verifier {
+        types: &stream.types,
+        functions: BUILTINS.iter().map(|(name, ret_type)| (*name, (None, *ret_type))).collect(),
+        globals: HashMap::new(),
+        variables: HashMap::new(),
+        labels: HashSet::new(),
+        func: None,
+        errors: Vec::new(),
+    };
+    verifier.verify_program(&stream.stream);
+    if verifier.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(verifier.errors)
+    }
+}
+
+/// Functions that every backend provides, so they are called without being declared
+const BUILTINS: [(Ident, Type); 1] = [("print", Type::Void)];
+
+struct Verifier<'v> {
+    types: &'v TypeTable<'v>,
+    /// Arguments and return type of every function. Builtins have no arguments
+    /// listed since they are checked by [Verifier::verify_builtin_args]
+    functions: HashMap<Ident<'v>, (Option<&'v [IRTypedIdent<'v>]>, Type<'v>)>,
+    /// Variables that are defined outside of functions
+    globals: HashMap<Ident<'v>, (&'v Type<'v>, bool)>,
+    /// Type of every variable in the current function and whether it is a constant
+    variables: HashMap<Ident<'v>, (&'v Type<'v>, bool)>,
+    /// Labels of the current function
+    labels: HashSet<Ident<'v>>,
+    /// The function that is currently being checked, `None` in the entry block
+    func: Option<&'v IRTypedIdent<'v>>,
+    errors: Vec<VerifyError>,
+}
+
+impl<'v> Verifier<'v> {
+    fn verify_program(&mut self, stream: &'v [IRStmt<'v>]) {
+        let mut has_entry = false;
+        let mut types = HashSet::new();
+        // Functions, types and globals can be used before they are defined
+        for stmt in stream {
+            match stmt {
+                IRStmt::Entry(_) if has_entry => {
+                    self.error(VerifyError::DuplicateDefinition("entry".into()))
+                }
+                IRStmt::Entry(_) => has_entry = true,
+                IRStmt::Function(func) => self.declare_function(&func.name, &func.args),
+                IRStmt::DeclaredFunction(func) => self.declare_function(&func.name, &func.args),
+                IRStmt::Struct(_struct) if !types.insert(_struct.name) => {
+                    self.error(VerifyError::DuplicateDefinition(_struct.name.to_string()))
+                }
+                IRStmt::Union(union) if !types.insert(union.name) => {
+                    self.error(VerifyError::DuplicateDefinition(union.name.to_string()))
+                }
+                IRStmt::Variable(var)
+                    if self
+                        .globals
+                        .insert(var.name.ident, (&var.name._type, var.is_const))
+                        .is_some() =>
+                {
+                    self.error(VerifyError::DuplicateDefinition(var.name.ident.to_string()))
+                }
+                _ => (),
+            }
+        }
+        if !has_entry {
+            self.error(VerifyError::MissingEntry);
+        }
+
+        for stmt in stream {
+            match stmt {
+                IRStmt::Entry(block) => {
+                    self.func = None;
+                    self.verify_body(&[], block);
+                }
+                IRStmt::Function(func) => {
+                    self.func = Some(&func.name);
+                    self.verify_body(&func.args, &func.block);
+                }
+                IRStmt::DeclaredFunction(func) => {
+                    self.verify_type(&func.name._type);
+                    for arg in &func.args {
+                        self.verify_type(&arg._type);
+                    }
+                }
+                IRStmt::Struct(_struct) => {
+                    for field in &_struct.fields {
+                        self.verify_type(&field._type);
+                    }
+                }
+                IRStmt::Union(union) => {
+                    for variant in &union.variants {
+                        self.verify_type(&variant._type);
+                    }
+                }
+                IRStmt::Variable(var) => self.verify_var(&var.name._type, &var.val),
+                stmt => self.verify_stmt(stmt),
+            }
+        }
+    }
+
+    fn declare_function(&mut self, name: &'v IRTypedIdent<'v>, args: &'v [IRTypedIdent<'v>]) {
+        if self
+            .functions
+            .insert(name.ident, (Some(args), name._type))
+            .is_some()
+        {
+            self.error(VerifyError::DuplicateDefinition(name.ident.to_string()));
+        }
+    }
+
+    fn verify_body(&mut self, args: &'v [IRTypedIdent<'v>], block: &'v BlockStmt<'v>) {
+        self.variables.clear();
+        self.labels.clear();
+        for arg in args {
+            self.verify_type(&arg._type);
+            if self
+                .variables
+                .insert(arg.ident, (&arg._type, false))
+                .is_some()
+            {
+                self.error(VerifyError::DuplicateDefinition(arg.ident.to_string()));
+            }
+        }
+        // Labels can be jumped to before they are defined
+        for stmt in &block.stmts {
+            if let IRStmt::Label(label) = stmt {
+                if !self.labels.insert(label.name) {
+                    self.error(VerifyError::DuplicateDefinition(label.name.to_string()));
+                }
+            }
+        }
+        if let Some(func) = self.func {
+            self.verify_type(&func._type);
+        }
+        for stmt in &block.stmts {
+            self.verify_stmt(stmt);
+        }
+    }
+
+    fn verify_stmt(&mut self, stmt: &'v IRStmt<'v>) {
+        match stmt {
+            IRStmt::Variable(var) => {
+                self.verify_var(&var.name._type, &var.val);
+                if self
+                    .variables
+                    .insert(var.name.ident, (&var.name._type, var.is_const))
+                    .is_some()
+                {
+                    self.error(VerifyError::DuplicateDefinition(var.name.ident.to_string()));
+                }
+            }
+            IRStmt::Assign(assign) => {
+                let val = self.type_of(&assign.val);
+                match self.lookup_var(assign.name) {
+                    Some((_, true)) => {
+                        self.error(VerifyError::AssignToConstant(assign.name.to_string()))
+                    }
+                    Some((_type, false)) => self.expect_type(_type, val),
+                    None => (),
+                }
+            }
+            IRStmt::Return(ret) => {
+                let val = self.type_of(&ret.ret_val);
+                if let (Some(func), Some(val)) = (self.func, val) {
+                    if func._type != val {
+                        self.error(VerifyError::ReturnType {
+                            func: func.ident.to_string(),
+                            expected: func._type.to_string(),
+                            received: val.to_string(),
+                        });
+                    }
+                }
+            }
+            IRStmt::Exit(exit) => {
+                self.type_of(&exit.exit_code);
+            }
+            IRStmt::Jump(jump) => self.verify_label(jump.label),
+            IRStmt::Branch(branch) => {
+                match self.type_of(&branch.condition) {
+                    Some(_type) if !_type.is_integer() => self.error(VerifyError::TypeMismatch {
+                        expected: "i1 or integer".into(),
+                        received: _type.to_string(),
+                    }),
+                    _ => (),
+                }
+                self.verify_label(branch.then_label);
+                self.verify_label(branch.else_label);
+            }
+            IRStmt::Call(call) => {
+                self.verify_call(call);
+            }
+            IRStmt::Store(store) => {
+                let val = self.type_of(&store.val);
+                if let Some(Type::Pointer(_type)) = self.type_of(&store.ptr) {
+                    self.expect_type(_type, val);
+                }
+            }
+            IRStmt::Label(_) => (),
+            IRStmt::Entry(_)
+            | IRStmt::Function(_)
+            | IRStmt::DeclaredFunction(_)
+            | IRStmt::Struct(_)
+            | IRStmt::Union(_) => (),
+        }
+    }
+
+    fn verify_var(&mut self, _type: &Type<'v>, val: &'v IRExpr<'v>) {
+        self.verify_type(_type);
+        let val = self.type_of(val);
+        self.expect_type(_type, val);
+    }
+
+    fn verify_label(&mut self, label: Ident<'v>) {
+        if !self.labels.contains(label) {
+            self.error(VerifyError::UnknownLabel(label.to_string()));
+        }
+    }
+
+    /// Checks the call and returns the return type of the function
+    fn verify_call(&mut self, call: &'v CallExpr<'v>) -> Option<Type<'v>> {
+        let arg_types: Vec<Option<Type<'v>>> =
+            call.args.iter().map(|arg| self.type_of(arg)).collect();
+        let (args, ret_type) = match self.functions.get(call.name).copied() {
+            Some((Some(args), ret_type)) => (args, ret_type),
+            Some((None, ret_type)) => {
+                self.verify_builtin_args(call, arg_types);
+                return Some(ret_type);
+            }
+            None => {
+                self.error(VerifyError::UnknownFunction(call.name.to_string()));
+                return None;
+            }
+        };
+        if args.len() != call.args.len() {
+            self.error(VerifyError::ArgCount {
+                func: call.name.to_string(),
+                expected: args.len(),
+                received: call.args.len(),
+            });
+        }
+        for (index, (arg, received)) in args.iter().zip(arg_types).enumerate() {
+            match received {
+                Some(received) if received != arg._type => self.error(VerifyError::ArgType {
+                    func: call.name.to_string(),
+                    index,
+                    expected: arg._type.to_string(),
+                    received: received.to_string(),
+                }),
+                _ => (),
+            }
+        }
+        Some(ret_type)
+    }
+
+    /// `print` takes the string to print as an array or a pointer
+    fn verify_builtin_args(&mut self, call: &'v CallExpr<'v>, arg_types: Vec<Option<Type<'v>>>) {
+        if arg_types.len() != 1 {
+            self.error(VerifyError::ArgCount {
+                func: call.name.to_string(),
+                expected: 1,
+                received: arg_types.len(),
+            });
+        }
+        match arg_types.first() {
+            Some(Some(Type::Array(&INT8_T, _) | Type::Pointer(&INT8_T))) | Some(None) | None => (),
+            Some(Some(received)) => self.error(VerifyError::ArgType {
+                func: call.name.to_string(),
+                index: 0,
+                expected: "[i8; n] or *i8".into(),
+                received: received.to_string(),
+            }),
+        }
+    }
+
+    /// Returns the type of the expression or `None` if it could not be determined,
+    /// in which case the reason has already been reported
+    fn type_of(&mut self, expr: &'v IRExpr<'v>) -> Option<Type<'v>> {
+        match expr {
+            IRExpr::Literal(lit, _type) => {
+                if !self.verify_type(_type) {
+                    return None;
+                }
+                self.verify_literal(lit, _type);
+                Some(*_type)
+            }
+            IRExpr::Ident(_) | IRExpr::Field(_) | IRExpr::Index(_) => {
+                self.place_type(expr).copied()
+            }
+            IRExpr::Call(call) => self.verify_call(call),
+            IRExpr::ArithOp(op) => {
+                let left = self.type_of(&op.values.0);
+                let right = self.type_of(&op.values.1);
+                // Both sides need the same type, vectors are then combined lane by lane
+                match left {
+                    Some(left) => {
+                        let is_shift =
+                            matches!(op.op, Operator::Shl | Operator::LShr | Operator::AShr);
+                        // Shift counts only need to be integers, like the `i8` in `shl %x, %n`
+                        match right {
+                            Some(right) if is_shift && !left.is_vector() => {
+                                if !right.is_integer() {
+                                    self.error(VerifyError::TypeMismatch {
+                                        expected: "integer".into(),
+                                        received: right.to_string(),
+                                    });
+                                }
+                            }
+                            right => self.expect_type(&left, right),
+                        }
+                        Some(left)
+                    }
+                    None => right,
+                }
+            }
+            IRExpr::CmpOp(op) => {
+                let left = self.type_of(&op.values.0);
+                let right = self.type_of(&op.values.1);
+                if let Some(left) = left {
+                    self.expect_type(&left, right);
+                }
+                Some(INT1_T)
+            }
+            IRExpr::Not(not) => self.type_of(&not.val),
+            IRExpr::Cast(cast) => {
+                let from = self.type_of(&cast.val);
+                if self.verify_type(&cast._type) {
+                    if let Some(Err(err)) = from.map(|from| cast.kind.check(&from, &cast._type)) {
+                        self.error(VerifyError::InvalidCast(err.0, err.1));
+                    }
+                }
+                Some(cast._type)
+            }
+            IRExpr::Alloca(alloca) => {
+                self.verify_type(&alloca._type);
+                Some(Type::Pointer(&alloca._type))
+            }
+            IRExpr::Load(load) => {
+                self.type_of(&load.ptr);
+                self.verify_type(&load._type);
+                Some(load._type)
+            }
+            IRExpr::AddrOf(addr_of) => self.place_type(&addr_of.val).map(Type::Pointer),
+            IRExpr::Extract(extract) => {
+                let (elem, lanes) = self.vector_type(&extract.vector)?;
+                self.verify_lane(extract.lane, lanes);
+                Some(*elem)
+            }
+            IRExpr::Insert(insert) => {
+                let val = self.type_of(&insert.val);
+                let (elem, lanes) = self.vector_type(&insert.vector)?;
+                self.verify_lane(insert.lane, lanes);
+                self.expect_type(elem, val);
+                Some(Type::Vector(elem, lanes))
+            }
+            IRExpr::Shuffle(shuffle) => {
+                let right = self.type_of(&shuffle.values.1);
+                let (elem, lanes) = self.vector_type(&shuffle.values.0)?;
+                self.expect_type(&Type::Vector(elem, lanes), right);
+                for lane in &shuffle.mask {
+                    self.verify_lane(*lane, lanes * 2);
+                }
+                Some(Type::Vector(elem, shuffle.mask.len() as u32))
+            }
+            IRExpr::StructInit(init) => {
+                let types = self.types;
+                let Some((_, fields)) = types.get(init.name) else {
+                    self.error(VerifyError::UnknownType(init.name.to_string()));
+                    return None;
+                };
+                for (val, field) in init.values.iter().zip(fields) {
+                    let val = self.type_of(val);
+                    self.expect_type(&field._type, val);
+                }
+                Some(Type::Ident(init.name))
+            }
+        }
+    }
+
+    /// Returns the type of a variable, field or element
+    fn place_type(&mut self, expr: &'v IRExpr<'v>) -> Option<&'v Type<'v>> {
+        match expr {
+            IRExpr::Ident(ident) => {
+                let var = self.lookup_var(ident);
+                var.map(|(_type, _)| _type)
+            }
+            IRExpr::Field(field) => {
+                let name = match self.aggregate_type(&field.base)? {
+                    Type::Ident(name) => name,
+                    _type => {
+                        self.error(VerifyError::UnknownField {
+                            _type: _type.to_string(),
+                            field: field.field.to_string(),
+                        });
+                        return None;
+                    }
+                };
+                let types = self.types;
+                let (_, fields) = types.get(name)?;
+                let found = fields.iter().find(|typed_ident| typed_ident.ident == field.field);
+                if found.is_none() {
+                    self.error(VerifyError::UnknownField {
+                        _type: name.to_string(),
+                        field: field.field.to_string(),
+                    });
+                }
+                found.map(|typed_ident| &typed_ident._type)
+            }
+            IRExpr::Index(index) => {
+                self.type_of(&index.index);
+                match self.aggregate_type(&index.base)? {
+                    Type::Array(_type, _) => Some(_type),
+                    _type => {
+                        self.error(VerifyError::TypeMismatch {
+                            expected: "array".into(),
+                            received: _type.to_string(),
+                        });
+                        None
+                    }
+                }
+            }
+            _ => {
+                self.type_of(expr);
+                None
+            }
+        }
+    }
+
+    /// Returns the element type and the number of lanes of a vector
+    fn vector_type(&mut self, expr: &'v IRExpr<'v>) -> Option<(&'v Type<'v>, u32)> {
+        match self.type_of(expr)? {
+            Type::Vector(elem, lanes) => Some((elem, lanes)),
+            _type => {
+                self.error(VerifyError::TypeMismatch {
+                    expected: "vector".into(),
+                    received: _type.to_string(),
+                });
+                None
+            }
+        }
+    }
+
+    fn verify_lane(&mut self, lane: u32, lanes: u32) {
+        if lane >= lanes {
+            self.error(VerifyError::LaneOutOfRange { lane, lanes });
+        }
+    }
+
+    /// Returns the type of a struct or array, looking through a pointer to it
+    fn aggregate_type(&mut self, expr: &'v IRExpr<'v>) -> Option<Type<'v>> {
+        match self.type_of(expr)? {
+            Type::Pointer(_type) => Some(*_type),
+            _type => Some(_type),
+        }
+    }
+
+    fn lookup_var(&mut self, ident: Ident<'v>) -> Option<(&'v Type<'v>, bool)> {
+        let var = self
+            .variables
+            .get(ident)
+            .or_else(|| self.globals.get(ident))
+            .copied();
+        if var.is_none() {
+            self.error(VerifyError::UnknownIdent(ident.to_string()));
+        }
+        var
+    }
+
+    /// Reports an error if the type is not a builtin type or defined in the type table
+    fn verify_type(&mut self, _type: &Type<'v>) -> bool {
+        match _type {
+            Type::Ident(ident) => {
+                let known = self.types.contains_key(ident);
+                if !known {
+                    self.error(VerifyError::UnknownType(ident.to_string()));
+                }
+                known
+            }
+            Type::Array(_type, _) | Type::Pointer(_type) => self.verify_type(_type),
+            Type::Vector(_type, _) if _type.is_integer() || _type.is_float() => true,
+            Type::Vector(_type, _) => {
+                self.error(VerifyError::TypeMismatch {
+                    expected: "integer or float".into(),
+                    received: _type.to_string(),
+                });
+                false
+            }
+            Type::Bool | Type::Int(_) | Type::Float(_) | Type::Void => true,
+        }
+    }
+
+    fn verify_literal(&mut self, lit: &'v Literal<'v>, _type: &Type<'v>) {
+        let fits = |val: i128| _type.fits(val);
+        let valid = match lit {
+            Literal::String(val) => match _type {
+                Type::Array(&INT8_T, len) => val.len() <= *len as usize,
+                _type => *_type == Type::Pointer(&INT8_T),
+            },
+            Literal::Char(_) => *_type == INT8_T,
+            Literal::Bool(_) => *_type == INT1_T,
+            Literal::Float32(_) => *_type == FLOAT32_T,
+            Literal::Float64(_) => *_type == FLOAT64_T,
+            Literal::Int8(val) => *_type == INT8_T && fits(*val as i128),
+            Literal::Int16(val) => *_type == INT16_T && fits(*val as i128),
+            Literal::Int32(val) => *_type == INT32_T && fits(*val as i128),
+            Literal::Int64(val) => *_type == INT64_T && fits(*val as i128),
+            Literal::Int128(val) => *_type == INT128_T && fits(*val),
+            Literal::UInt8(val) => *_type == UINT8_T && fits(*val as i128),
+            Literal::UInt16(val) => *_type == UINT16_T && fits(*val as i128),
+            Literal::UInt32(val) => *_type == UINT32_T && fits(*val as i128),
+            Literal::UInt64(val) => *_type == UINT64_T && fits(*val as i128),
+            Literal::UInt128(_) => *_type == UINT128_T,
+            Literal::Array(len, vals) => match _type {
+                Type::Array(elem, size) if *len == *size as usize => {
+                    for val in vals {
+                        let val = self.type_of(val);
+                        self.expect_type(elem, val);
+                    }
+                    true
+                }
+                _ => false,
+            },
+            Literal::Vector(vals) => match _type {
+                Type::Vector(elem, lanes) if vals.len() == *lanes as usize => {
+                    for val in vals {
+                        let val = self.type_of(val);
+                        self.expect_type(elem, val);
+                    }
+                    true
+                }
+                _ => false,
+            },
+        };
+        if !valid {
+            self.error(VerifyError::LiteralMismatch(lit.to_string(), _type.to_string()));
+        }
+    }
+
+    /// Reports a mismatch if the type of a value is known and differs from the expected type
+    fn expect_type(&mut self, expected: &Type<'v>, received: Option<Type<'v>>) {
+        match received {
+            Some(received) if received != *expected => self.error(VerifyError::TypeMismatch {
+                expected: expected.to_string(),
+                received: received.to_string(),
+            }),
+            _ => (),
+        }
+    }
+
+    #[inline(always)]
+    fn error(&mut self, error: VerifyError) {
+        self.errors.push(error);
+    }
+}
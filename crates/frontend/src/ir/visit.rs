@@ -0,0 +1,332 @@
+//! Traversal of the IR by shared reference
+//!
+//! Every method of [Visitor] calls the `walk_*` function of the same node by
+//! default, which visits all children of the node. Implementors only override
+//! the methods of the nodes they are interested in and can call the `walk_*`
+//! function themselves to continue the traversal into the children.
+//!
+//! See [visit_mut](super::visit_mut) for a visitor that can modify
+//! nodes in place and [fold](super::fold) for one that rebuilds them.
+
+use super::*;
+
+pub trait Visitor<'ir> {
+    fn visit_stmt(&mut self, stmt: &'ir IRStmt<'ir>) {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &'ir IRExpr<'ir>) {
+        walk_expr(self, expr)
+    }
+
+    fn visit_block(&mut self, block: &'ir BlockStmt<'ir>) {
+        walk_block(self, block)
+    }
+
+    fn visit_decl_func(&mut self, func: &'ir DeclFuncStmt<'ir>) {
+        walk_decl_func(self, func)
+    }
+
+    fn visit_func(&mut self, func: &'ir FuncStmt<'ir>) {
+        walk_func(self, func)
+    }
+
+    fn visit_var(&mut self, var: &'ir VarStmt<'ir>) {
+        walk_var(self, var)
+    }
+
+    fn visit_assign(&mut self, assign: &'ir AssignStmt<'ir>) {
+        walk_assign(self, assign)
+    }
+
+    fn visit_label(&mut self, _label: &'ir LabelStmt<'ir>) {}
+
+    fn visit_return(&mut self, ret: &'ir ReturnStmt<'ir>) {
+        walk_return(self, ret)
+    }
+
+    fn visit_exit(&mut self, exit: &'ir ExitStmt<'ir>) {
+        walk_exit(self, exit)
+    }
+
+    fn visit_jump(&mut self, _jump: &'ir JumpStmt<'ir>) {}
+
+    fn visit_branch(&mut self, branch: &'ir BranchStmt<'ir>) {
+        walk_branch(self, branch)
+    }
+
+    fn visit_store(&mut self, store: &'ir StoreStmt<'ir>) {
+        walk_store(self, store)
+    }
+
+    fn visit_struct(&mut self, node: &'ir StructStmt<'ir>) {
+        walk_struct(self, node)
+    }
+
+    fn visit_union(&mut self, node: &'ir UnionStmt<'ir>) {
+        walk_union(self, node)
+    }
+
+    fn visit_call(&mut self, call: &'ir CallExpr<'ir>) {
+        walk_call(self, call)
+    }
+
+    fn visit_literal(&mut self, lit: &'ir Literal<'ir>, _type: &'ir Type<'ir>) {
+        walk_literal(self, lit, _type)
+    }
+
+    /// Visits a variable that is used as a value
+    fn visit_ident(&mut self, _ident: Ident<'ir>) {}
+
+    fn visit_arith_op(&mut self, op: &'ir ArithOpExpr<'ir>) {
+        walk_arith_op(self, op)
+    }
+
+    fn visit_cmp_op(&mut self, op: &'ir CmpOpExpr<'ir>) {
+        walk_cmp_op(self, op)
+    }
+
+    fn visit_not(&mut self, not: &'ir NotExpr<'ir>) {
+        walk_not(self, not)
+    }
+
+    fn visit_cast(&mut self, cast: &'ir CastExpr<'ir>) {
+        walk_cast(self, cast)
+    }
+
+    fn visit_alloca(&mut self, alloca: &'ir AllocaExpr<'ir>) {
+        walk_alloca(self, alloca)
+    }
+
+    fn visit_load(&mut self, load: &'ir LoadExpr<'ir>) {
+        walk_load(self, load)
+    }
+
+    fn visit_addr_of(&mut self, addr_of: &'ir AddrOfExpr<'ir>) {
+        walk_addr_of(self, addr_of)
+    }
+
+    fn visit_field(&mut self, field: &'ir FieldExpr<'ir>) {
+        walk_field(self, field)
+    }
+
+    fn visit_index(&mut self, index: &'ir IndexExpr<'ir>) {
+        walk_index(self, index)
+    }
+
+    fn visit_extract(&mut self, extract: &'ir ExtractExpr<'ir>) {
+        walk_extract(self, extract)
+    }
+
+    fn visit_insert(&mut self, insert: &'ir InsertExpr<'ir>) {
+        walk_insert(self, insert)
+    }
+
+    fn visit_shuffle(&mut self, shuffle: &'ir ShuffleExpr<'ir>) {
+        walk_shuffle(self, shuffle)
+    }
+
+    fn visit_struct_init(&mut self, init: &'ir StructInitExpr<'ir>) {
+        walk_struct_init(self, init)
+    }
+
+    fn visit_typed_ident(&mut self, typed_ident: &'ir IRTypedIdent<'ir>) {
+        walk_typed_ident(self, typed_ident)
+    }
+
+    fn visit_type(&mut self, _type: &'ir Type<'ir>) {
+        walk_type(self, _type)
+    }
+}
+
+pub fn walk_stmt<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, stmt: &'ir IRStmt<'ir>) {
+    match stmt {
+        IRStmt::Entry(block) => v.visit_block(block),
+        IRStmt::DeclaredFunction(func) => v.visit_decl_func(func),
+        IRStmt::Function(func) => v.visit_func(func),
+        IRStmt::Variable(var) => v.visit_var(var),
+        IRStmt::Assign(assign) => v.visit_assign(assign),
+        IRStmt::Label(label) => v.visit_label(label),
+        IRStmt::Return(ret) => v.visit_return(ret),
+        IRStmt::Exit(exit) => v.visit_exit(exit),
+        IRStmt::Jump(jump) => v.visit_jump(jump),
+        IRStmt::Branch(branch) => v.visit_branch(branch),
+        IRStmt::Call(call) => v.visit_call(call),
+        IRStmt::Store(store) => v.visit_store(store),
+        IRStmt::Struct(node) => v.visit_struct(node),
+        IRStmt::Union(node) => v.visit_union(node),
+    }
+}
+
+pub fn walk_expr<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, expr: &'ir IRExpr<'ir>) {
+    match expr {
+        IRExpr::Call(call) => v.visit_call(call),
+        IRExpr::Literal(lit, _type) => v.visit_literal(lit, _type),
+        IRExpr::Ident(ident) => v.visit_ident(ident),
+        IRExpr::ArithOp(op) => v.visit_arith_op(op),
+        IRExpr::CmpOp(op) => v.visit_cmp_op(op),
+        IRExpr::Not(not) => v.visit_not(not),
+        IRExpr::Cast(cast) => v.visit_cast(cast),
+        IRExpr::Alloca(alloca) => v.visit_alloca(alloca),
+        IRExpr::Load(load) => v.visit_load(load),
+        IRExpr::AddrOf(addr_of) => v.visit_addr_of(addr_of),
+        IRExpr::Field(field) => v.visit_field(field),
+        IRExpr::Index(index) => v.visit_index(index),
+        IRExpr::Extract(extract) => v.visit_extract(extract),
+        IRExpr::Insert(insert) => v.visit_insert(insert),
+        IRExpr::Shuffle(shuffle) => v.visit_shuffle(shuffle),
+        IRExpr::StructInit(init) => v.visit_struct_init(init),
+    }
+}
+
+pub fn walk_block<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, block: &'ir BlockStmt<'ir>) {
+    for stmt in &block.stmts {
+        v.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_decl_func<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, func: &'ir DeclFuncStmt<'ir>) {
+    v.visit_typed_ident(&func.name);
+    for arg in &func.args {
+        v.visit_typed_ident(arg);
+    }
+}
+
+pub fn walk_func<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, func: &'ir FuncStmt<'ir>) {
+    v.visit_typed_ident(&func.name);
+    for arg in &func.args {
+        v.visit_typed_ident(arg);
+    }
+    v.visit_block(&func.block);
+}
+
+pub fn walk_var<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, var: &'ir VarStmt<'ir>) {
+    v.visit_typed_ident(&var.name);
+    v.visit_expr(&var.val);
+}
+
+pub fn walk_assign<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, assign: &'ir AssignStmt<'ir>) {
+    v.visit_expr(&assign.val);
+}
+
+pub fn walk_return<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, ret: &'ir ReturnStmt<'ir>) {
+    v.visit_expr(&ret.ret_val);
+}
+
+pub fn walk_exit<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, exit: &'ir ExitStmt<'ir>) {
+    v.visit_expr(&exit.exit_code);
+}
+
+pub fn walk_branch<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, branch: &'ir BranchStmt<'ir>) {
+    v.visit_expr(&branch.condition);
+}
+
+pub fn walk_store<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, store: &'ir StoreStmt<'ir>) {
+    v.visit_expr(&store.val);
+    v.visit_expr(&store.ptr);
+}
+
+pub fn walk_struct<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, node: &'ir StructStmt<'ir>) {
+    for field in &node.fields {
+        v.visit_typed_ident(field);
+    }
+}
+
+pub fn walk_union<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, node: &'ir UnionStmt<'ir>) {
+    for variant in &node.variants {
+        v.visit_typed_ident(variant);
+    }
+}
+
+pub fn walk_call<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, call: &'ir CallExpr<'ir>) {
+    for arg in &call.args {
+        v.visit_expr(arg);
+    }
+}
+
+pub fn walk_literal<'ir, V: Visitor<'ir> + ?Sized>(
+    v: &mut V,
+    lit: &'ir Literal<'ir>,
+    _type: &'ir Type<'ir>,
+) {
+    if let Literal::Array(_, vals) | Literal::Vector(vals) = lit {
+        for val in vals {
+            v.visit_expr(val);
+        }
+    }
+    v.visit_type(_type);
+}
+
+pub fn walk_arith_op<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, op: &'ir ArithOpExpr<'ir>) {
+    v.visit_expr(&op.values.0);
+    v.visit_expr(&op.values.1);
+}
+
+pub fn walk_cmp_op<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, op: &'ir CmpOpExpr<'ir>) {
+    v.visit_expr(&op.values.0);
+    v.visit_expr(&op.values.1);
+}
+
+pub fn walk_not<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, not: &'ir NotExpr<'ir>) {
+    v.visit_expr(&not.val);
+}
+
+pub fn walk_cast<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, cast: &'ir CastExpr<'ir>) {
+    v.visit_type(&cast._type);
+    v.visit_expr(&cast.val);
+}
+
+pub fn walk_alloca<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, alloca: &'ir AllocaExpr<'ir>) {
+    v.visit_type(&alloca._type);
+}
+
+pub fn walk_load<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, load: &'ir LoadExpr<'ir>) {
+    v.visit_type(&load._type);
+    v.visit_expr(&load.ptr);
+}
+
+pub fn walk_addr_of<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, addr_of: &'ir AddrOfExpr<'ir>) {
+    v.visit_expr(&addr_of.val);
+}
+
+pub fn walk_field<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, field: &'ir FieldExpr<'ir>) {
+    v.visit_expr(&field.base);
+}
+
+pub fn walk_index<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, index: &'ir IndexExpr<'ir>) {
+    v.visit_expr(&index.base);
+    v.visit_expr(&index.index);
+}
+
+pub fn walk_extract<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, extract: &'ir ExtractExpr<'ir>) {
+    v.visit_expr(&extract.vector);
+}
+
+pub fn walk_insert<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, insert: &'ir InsertExpr<'ir>) {
+    v.visit_expr(&insert.vector);
+    v.visit_expr(&insert.val);
+}
+
+pub fn walk_shuffle<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, shuffle: &'ir ShuffleExpr<'ir>) {
+    v.visit_expr(&shuffle.values.0);
+    v.visit_expr(&shuffle.values.1);
+}
+
+pub fn walk_struct_init<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, init: &'ir StructInitExpr<'ir>) {
+    for val in &init.values {
+        v.visit_expr(val);
+    }
+}
+
+pub fn walk_typed_ident<'ir, V: Visitor<'ir> + ?Sized>(
+    v: &mut V,
+    typed_ident: &'ir IRTypedIdent<'ir>,
+) {
+    v.visit_type(&typed_ident._type);
+}
+
+pub fn walk_type<'ir, V: Visitor<'ir> + ?Sized>(v: &mut V, _type: &'ir Type<'ir>) {
+    if let Type::Array(_type, _) | Type::Pointer(_type) | Type::Vector(_type, _) = _type {
+        v.visit_type(_type);
+    }
+}
@@ -0,0 +1,320 @@
+//! Traversal of the IR by mutable reference
+//!
+//! Works like [visit](super::visit), but every node can be modified in place.
+
+use super::*;
+
+pub trait VisitorMut<'ir> {
+    fn visit_stmt(&mut self, stmt: &mut IRStmt<'ir>) {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &mut IRExpr<'ir>) {
+        walk_expr(self, expr)
+    }
+
+    fn visit_block(&mut self, block: &mut BlockStmt<'ir>) {
+        walk_block(self, block)
+    }
+
+    fn visit_decl_func(&mut self, func: &mut DeclFuncStmt<'ir>) {
+        walk_decl_func(self, func)
+    }
+
+    fn visit_func(&mut self, func: &mut FuncStmt<'ir>) {
+        walk_func(self, func)
+    }
+
+    fn visit_var(&mut self, var: &mut VarStmt<'ir>) {
+        walk_var(self, var)
+    }
+
+    fn visit_assign(&mut self, assign: &mut AssignStmt<'ir>) {
+        walk_assign(self, assign)
+    }
+
+    fn visit_label(&mut self, _label: &mut LabelStmt<'ir>) {}
+
+    fn visit_return(&mut self, ret: &mut ReturnStmt<'ir>) {
+        walk_return(self, ret)
+    }
+
+    fn visit_exit(&mut self, exit: &mut ExitStmt<'ir>) {
+        walk_exit(self, exit)
+    }
+
+    fn visit_jump(&mut self, _jump: &mut JumpStmt<'ir>) {}
+
+    fn visit_branch(&mut self, branch: &mut BranchStmt<'ir>) {
+        walk_branch(self, branch)
+    }
+
+    fn visit_store(&mut self, store: &mut StoreStmt<'ir>) {
+        walk_store(self, store)
+    }
+
+    fn visit_struct(&mut self, node: &mut StructStmt<'ir>) {
+        walk_struct(self, node)
+    }
+
+    fn visit_union(&mut self, node: &mut UnionStmt<'ir>) {
+        walk_union(self, node)
+    }
+
+    fn visit_call(&mut self, call: &mut CallExpr<'ir>) {
+        walk_call(self, call)
+    }
+
+    fn visit_literal(&mut self, lit: &mut Literal<'ir>, _type: &mut Type<'ir>) {
+        walk_literal(self, lit, _type)
+    }
+
+    /// Visits a variable that is used as a value
+    fn visit_ident(&mut self, _ident: &mut Ident<'ir>) {}
+
+    fn visit_arith_op(&mut self, op: &mut ArithOpExpr<'ir>) {
+        walk_arith_op(self, op)
+    }
+
+    fn visit_cmp_op(&mut self, op: &mut CmpOpExpr<'ir>) {
+        walk_cmp_op(self, op)
+    }
+
+    fn visit_not(&mut self, not: &mut NotExpr<'ir>) {
+        walk_not(self, not)
+    }
+
+    fn visit_cast(&mut self, cast: &mut CastExpr<'ir>) {
+        walk_cast(self, cast)
+    }
+
+    fn visit_alloca(&mut self, alloca: &mut AllocaExpr<'ir>) {
+        walk_alloca(self, alloca)
+    }
+
+    fn visit_load(&mut self, load: &mut LoadExpr<'ir>) {
+        walk_load(self, load)
+    }
+
+    fn visit_addr_of(&mut self, addr_of: &mut AddrOfExpr<'ir>) {
+        walk_addr_of(self, addr_of)
+    }
+
+    fn visit_field(&mut self, field: &mut FieldExpr<'ir>) {
+        walk_field(self, field)
+    }
+
+    fn visit_index(&mut self, index: &mut IndexExpr<'ir>) {
+        walk_index(self, index)
+    }
+
+    fn visit_extract(&mut self, extract: &mut ExtractExpr<'ir>) {
+        walk_extract(self, extract)
+    }
+
+    fn visit_insert(&mut self, insert: &mut InsertExpr<'ir>) {
+        walk_insert(self, insert)
+    }
+
+    fn visit_shuffle(&mut self, shuffle: &mut ShuffleExpr<'ir>) {
+        walk_shuffle(self, shuffle)
+    }
+
+    fn visit_struct_init(&mut self, init: &mut StructInitExpr<'ir>) {
+        walk_struct_init(self, init)
+    }
+
+    fn visit_typed_ident(&mut self, typed_ident: &mut IRTypedIdent<'ir>) {
+        walk_typed_ident(self, typed_ident)
+    }
+
+    /// The element types of arrays, pointers and vectors are shared references
+    /// into the arena, so only the outermost type can be replaced
+    fn visit_type(&mut self, _type: &mut Type<'ir>) {}
+}
+
+pub fn walk_stmt<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, stmt: &mut IRStmt<'ir>) {
+    match stmt {
+        IRStmt::Entry(block) => v.visit_block(block),
+        IRStmt::DeclaredFunction(func) => v.visit_decl_func(func),
+        IRStmt::Function(func) => v.visit_func(func),
+        IRStmt::Variable(var) => v.visit_var(var),
+        IRStmt::Assign(assign) => v.visit_assign(assign),
+        IRStmt::Label(label) => v.visit_label(label),
+        IRStmt::Return(ret) => v.visit_return(ret),
+        IRStmt::Exit(exit) => v.visit_exit(exit),
+        IRStmt::Jump(jump) => v.visit_jump(jump),
+        IRStmt::Branch(branch) => v.visit_branch(branch),
+        IRStmt::Call(call) => v.visit_call(call),
+        IRStmt::Store(store) => v.visit_store(store),
+        IRStmt::Struct(node) => v.visit_struct(node),
+        IRStmt::Union(node) => v.visit_union(node),
+    }
+}
+
+pub fn walk_expr<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, expr: &mut IRExpr<'ir>) {
+    match expr {
+        IRExpr::Call(call) => v.visit_call(call),
+        IRExpr::Literal(lit, _type) => v.visit_literal(lit, _type),
+        IRExpr::Ident(ident) => v.visit_ident(ident),
+        IRExpr::ArithOp(op) => v.visit_arith_op(op),
+        IRExpr::CmpOp(op) => v.visit_cmp_op(op),
+        IRExpr::Not(not) => v.visit_not(not),
+        IRExpr::Cast(cast) => v.visit_cast(cast),
+        IRExpr::Alloca(alloca) => v.visit_alloca(alloca),
+        IRExpr::Load(load) => v.visit_load(load),
+        IRExpr::AddrOf(addr_of) => v.visit_addr_of(addr_of),
+        IRExpr::Field(field) => v.visit_field(field),
+        IRExpr::Index(index) => v.visit_index(index),
+        IRExpr::Extract(extract) => v.visit_extract(extract),
+        IRExpr::Insert(insert) => v.visit_insert(insert),
+        IRExpr::Shuffle(shuffle) => v.visit_shuffle(shuffle),
+        IRExpr::StructInit(init) => v.visit_struct_init(init),
+    }
+}
+
+pub fn walk_block<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, block: &mut BlockStmt<'ir>) {
+    for stmt in &mut block.stmts {
+        v.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_decl_func<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, func: &mut DeclFuncStmt<'ir>) {
+    v.visit_typed_ident(&mut func.name);
+    for arg in &mut func.args {
+        v.visit_typed_ident(arg);
+    }
+}
+
+pub fn walk_func<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, func: &mut FuncStmt<'ir>) {
+    v.visit_typed_ident(&mut func.name);
+    for arg in &mut func.args {
+        v.visit_typed_ident(arg);
+    }
+    v.visit_block(&mut func.block);
+}
+
+pub fn walk_var<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, var: &mut VarStmt<'ir>) {
+    v.visit_typed_ident(&mut var.name);
+    v.visit_expr(&mut var.val);
+}
+
+pub fn walk_assign<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, assign: &mut AssignStmt<'ir>) {
+    v.visit_expr(&mut assign.val);
+}
+
+pub fn walk_return<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, ret: &mut ReturnStmt<'ir>) {
+    v.visit_expr(&mut ret.ret_val);
+}
+
+pub fn walk_exit<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, exit: &mut ExitStmt<'ir>) {
+    v.visit_expr(&mut exit.exit_code);
+}
+
+pub fn walk_branch<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, branch: &mut BranchStmt<'ir>) {
+    v.visit_expr(&mut branch.condition);
+}
+
+pub fn walk_store<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, store: &mut StoreStmt<'ir>) {
+    v.visit_expr(&mut store.val);
+    v.visit_expr(&mut store.ptr);
+}
+
+pub fn walk_struct<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, node: &mut StructStmt<'ir>) {
+    for field in &mut node.fields {
+        v.visit_typed_ident(field);
+    }
+}
+
+pub fn walk_union<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, node: &mut UnionStmt<'ir>) {
+    for variant in &mut node.variants {
+        v.visit_typed_ident(variant);
+    }
+}
+
+pub fn walk_call<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, call: &mut CallExpr<'ir>) {
+    for arg in &mut call.args {
+        v.visit_expr(arg);
+    }
+}
+
+pub fn walk_literal<'ir, V: VisitorMut<'ir> + ?Sized>(
+    v: &mut V,
+    lit: &mut Literal<'ir>,
+    _type: &mut Type<'ir>,
+) {
+    if let Literal::Array(_, vals) | Literal::Vector(vals) = lit {
+        for val in vals {
+            v.visit_expr(val);
+        }
+    }
+    v.visit_type(_type);
+}
+
+pub fn walk_arith_op<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, op: &mut ArithOpExpr<'ir>) {
+    v.visit_expr(&mut op.values.0);
+    v.visit_expr(&mut op.values.1);
+}
+
+pub fn walk_cmp_op<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, op: &mut CmpOpExpr<'ir>) {
+    v.visit_expr(&mut op.values.0);
+    v.visit_expr(&mut op.values.1);
+}
+
+pub fn walk_not<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, not: &mut NotExpr<'ir>) {
+    v.visit_expr(&mut not.val);
+}
+
+pub fn walk_cast<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, cast: &mut CastExpr<'ir>) {
+    v.visit_type(&mut cast._type);
+    v.visit_expr(&mut cast.val);
+}
+
+pub fn walk_alloca<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, alloca: &mut AllocaExpr<'ir>) {
+    v.visit_type(&mut alloca._type);
+}
+
+pub fn walk_load<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, load: &mut LoadExpr<'ir>) {
+    v.visit_type(&mut load._type);
+    v.visit_expr(&mut load.ptr);
+}
+
+pub fn walk_addr_of<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, addr_of: &mut AddrOfExpr<'ir>) {
+    v.visit_expr(&mut addr_of.val);
+}
+
+pub fn walk_field<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, field: &mut FieldExpr<'ir>) {
+    v.visit_expr(&mut field.base);
+}
+
+pub fn walk_index<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, index: &mut IndexExpr<'ir>) {
+    v.visit_expr(&mut index.base);
+    v.visit_expr(&mut index.index);
+}
+
+pub fn walk_extract<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, extract: &mut ExtractExpr<'ir>) {
+    v.visit_expr(&mut extract.vector);
+}
+
+pub fn walk_insert<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, insert: &mut InsertExpr<'ir>) {
+    v.visit_expr(&mut insert.vector);
+    v.visit_expr(&mut insert.val);
+}
+
+pub fn walk_shuffle<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, shuffle: &mut ShuffleExpr<'ir>) {
+    v.visit_expr(&mut shuffle.values.0);
+    v.visit_expr(&mut shuffle.values.1);
+}
+
+pub fn walk_struct_init<'ir, V: VisitorMut<'ir> + ?Sized>(v: &mut V, init: &mut StructInitExpr<'ir>) {
+    for val in &mut init.values {
+        v.visit_expr(val);
+    }
+}
+
+pub fn walk_typed_ident<'ir, V: VisitorMut<'ir> + ?Sized>(
+    v: &mut V,
+    typed_ident: &mut IRTypedIdent<'ir>,
+) {
+    v.visit_type(&mut typed_ident._type);
+}
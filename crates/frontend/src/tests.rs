@@ -1,8 +1,20 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
-    use crate::ir::{
-        self, irgen::IRGenerator, DeclFuncStmt, IRExpr, IRStmt, IRTypedIdent, LabelStmt, Literal,
-        ReturnStmt, INT8_T,
+    use bumpalo::Bump;
+
+    use crate::{
+        ir::{
+            self, bitcode, builder::IRBuilder, fold::{self, Fold}, irgen::IRGenerator, verifier,
+            visit::{self, Visitor}, visit_mut::VisitorMut, ArithOpExpr, AssignStmt, BlockStmt,
+            BranchStmt, CallExpr, CastExpr, CastKind, CmpOpExpr, CmpOperator, DeclFuncStmt, ExitStmt,
+            FuncStmt, IRExpr, IRStmt, IRTypedIdent, Ident, JumpStmt, LabelStmt, Literal, Operator,
+            ReturnStmt, SourceLoc, VarStmt, FLOAT32_T, INT1_T, INT32_T, INT64_T, INT8_T, UINT128_T, UINT8_T,
+        },
+        util::{
+            errors::{BitcodeError, VerifyError},
+            escape,
+        },
     };
 
     #[test]
@@ -13,9 +25,10 @@ mod tests {
         let binding = IRStmt::DeclaredFunction(DeclFuncStmt {
             name: IRTypedIdent {
                 ident: "myFuncName",
-                _type: ir::Type::Ident("void"),
+                _type: ir::Type::Void,
             },
             args: Vec::new(),
+            loc: None,
         });
         code_gen.gen_ir(binding);
 
@@ -27,12 +40,13 @@ mod tests {
         let mut code_gen = IRGenerator::default();
 
         let ir_stream = [
-            IRStmt::Label(LabelStmt { name: "myLabel" }),
+            IRStmt::Label(LabelStmt { name: "myLabel", loc: None }),
             IRStmt::Return(ReturnStmt {
                 ret_val: IRExpr::Literal(
-                    Literal::String("test".into()),
-                    ir::Type::Array(&ir::Type::Ident(INT8_T), 4),
+                    Literal::String(b"test"),
+                    ir::Type::Array(&INT8_T, 4),
                 ),
+                loc: None,
             }),
         ];
 
@@ -40,11 +54,651 @@ mod tests {
             code_gen.gen_ir(ir_stmt);
         }
 
-        println!("{}", code_gen.stream_ref().to_string());
+        println!("{}", code_gen.stream_ref());
 
         assert_eq!(
             code_gen.stream().to_string(),
-            "'myLabel: {\n    ret l{\"test\"}\n}"
+            "'myLabel:\nret l{\"test\":[i8; 4]}"
         )
     }
+
+    #[test]
+    fn test_cast_check() {
+        let i8_t = INT8_T;
+        let i32_t = INT32_T;
+        let i64_t = INT64_T;
+        let f32_t = FLOAT32_T;
+
+        assert!(CastKind::SExt.check(&i8_t, &i64_t).is_ok());
+        assert!(CastKind::SExt.check(&i64_t, &i8_t).is_err());
+        assert!(CastKind::Trunc.check(&i64_t, &i32_t).is_ok());
+        assert!(CastKind::ZExt.check(&i32_t, &i32_t).is_err());
+        assert!(CastKind::IntToFloat.check(&i32_t, &f32_t).is_ok());
+        assert!(CastKind::FloatToInt.check(&i32_t, &f32_t).is_err());
+        assert!(CastKind::Bitcast.check(&f32_t, &i32_t).is_ok());
+        assert!(CastKind::Bitcast.check(&f32_t, &i64_t).is_err());
+    }
+
+    fn main_func<'a>(stmts: Vec<IRStmt<'a>>) -> IRStmt<'a> {
+        IRStmt::Function(FuncStmt {
+            name: IRTypedIdent {
+                ident: "main",
+                _type: INT32_T,
+            },
+            args: Vec::new(),
+            block: BlockStmt { stmts },
+            loc: None,
+        })
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut code_gen = IRGenerator::default();
+        code_gen.gen_ir(IRStmt::Entry(BlockStmt {
+            stmts: vec![IRStmt::Exit(ExitStmt {
+                exit_code: IRExpr::Call(CallExpr {
+                    name: "main",
+                    args: Vec::new(),
+                    loc: None,
+                }),
+                loc: None,
+            })],
+        }));
+        code_gen.gen_ir(main_func(vec![
+            IRStmt::Variable(VarStmt {
+                name: IRTypedIdent {
+                    ident: "x",
+                    _type: INT32_T,
+                },
+                val: IRExpr::Literal(Literal::Int32(1), INT32_T),
+                is_const: true,
+                loc: None,
+            }),
+            IRStmt::Call(CallExpr {
+                name: "print",
+                args: vec![IRExpr::Literal(Literal::String(b"hi"), ir::Type::Array(&INT8_T, 2))],
+                loc: None,
+            }),
+            IRStmt::Assign(AssignStmt {
+                name: "counter",
+                val: IRExpr::Ident("x"),
+                loc: None,
+            }),
+            IRStmt::Return(ReturnStmt {
+                ret_val: IRExpr::Ident("x"),
+                loc: None,
+            }),
+        ]));
+        // Globals can be used by functions that come before them
+        code_gen.gen_ir(IRStmt::Variable(VarStmt {
+            name: IRTypedIdent {
+                ident: "counter",
+                _type: INT32_T,
+            },
+            val: IRExpr::Literal(Literal::Int32(0), INT32_T),
+            is_const: false,
+            loc: None,
+        }));
+        let stream = code_gen.stream();
+        assert_eq!(verifier::verify(&stream), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_errors() {
+        let mut code_gen = IRGenerator::default();
+        code_gen.gen_ir(main_func(vec![
+            IRStmt::Variable(VarStmt {
+                name: IRTypedIdent {
+                    ident: "x",
+                    _type: INT8_T,
+                },
+                val: IRExpr::Literal(Literal::Int32(300), INT8_T),
+                is_const: true,
+                loc: None,
+            }),
+            IRStmt::Call(CallExpr {
+                name: "main",
+                args: vec![IRExpr::Ident("y")],
+                loc: None,
+            }),
+            IRStmt::Jump(JumpStmt { label: "end", loc: None }),
+            IRStmt::Return(ReturnStmt {
+                ret_val: IRExpr::Literal(Literal::Int32(0), ir::Type::Ident("Point")),
+                loc: None,
+            }),
+            IRStmt::Return(ReturnStmt {
+                ret_val: IRExpr::Ident("x"),
+                loc: None,
+            }),
+            IRStmt::Call(CallExpr {
+                name: "print",
+                args: vec![IRExpr::Ident("x")],
+                loc: None,
+            }),
+            IRStmt::Label(LabelStmt { name: "done", loc: None }),
+            IRStmt::Variable(VarStmt {
+                name: IRTypedIdent {
+                    ident: "f",
+                    _type: FLOAT32_T,
+                },
+                val: IRExpr::Cast(CastExpr {
+                    kind: CastKind::Trunc,
+                    _type: FLOAT32_T,
+                    val: Box::new(IRExpr::Ident("x")),
+                    loc: None,
+                }),
+                is_const: true,
+                loc: None,
+            }),
+            IRStmt::Branch(BranchStmt {
+                condition: IRExpr::Ident("f"),
+                then_label: "done",
+                else_label: "done",
+                loc: None,
+            }),
+            IRStmt::Variable(VarStmt {
+                name: IRTypedIdent {
+                    ident: "s",
+                    _type: ir::Type::Array(&INT8_T, 2),
+                },
+                val: IRExpr::Literal(Literal::String(b"abc"), ir::Type::Array(&INT8_T, 2)),
+                is_const: true,
+                loc: None,
+            }),
+            IRStmt::Variable(VarStmt {
+                name: IRTypedIdent {
+                    ident: "l",
+                    _type: INT64_T,
+                },
+                val: IRExpr::Literal(Literal::Int32(1), INT64_T),
+                is_const: true,
+                loc: None,
+            }),
+            IRStmt::Variable(VarStmt {
+                name: IRTypedIdent {
+                    ident: "a",
+                    _type: INT8_T,
+                },
+                val: IRExpr::ArithOp(ArithOpExpr {
+                    op: Operator::Add,
+                    values: (
+                        Box::new(IRExpr::Ident("x")),
+                        Box::new(IRExpr::Literal(Literal::Int32(1), INT32_T)),
+                    ),
+                    loc: None,
+                }),
+                is_const: true,
+                loc: None,
+            }),
+            IRStmt::Variable(VarStmt {
+                name: IRTypedIdent {
+                    ident: "c",
+                    _type: INT1_T,
+                },
+                val: IRExpr::CmpOp(CmpOpExpr {
+                    op: CmpOperator::Eq,
+                    values: (Box::new(IRExpr::Ident("x")), Box::new(IRExpr::Ident("l"))),
+                    loc: None,
+                }),
+                is_const: true,
+                loc: None,
+            }),
+            IRStmt::Variable(VarStmt {
+                name: IRTypedIdent {
+                    ident: "sh",
+                    _type: INT8_T,
+                },
+                val: IRExpr::ArithOp(ArithOpExpr {
+                    op: Operator::Shl,
+                    values: (
+                        Box::new(IRExpr::ArithOp(ArithOpExpr {
+                            op: Operator::Shl,
+                            values: (Box::new(IRExpr::Ident("x")), Box::new(IRExpr::Ident("l"))),
+                            loc: None,
+                        })),
+                        Box::new(IRExpr::Ident("f")),
+                    ),
+                    loc: None,
+                }),
+                is_const: true,
+                loc: None,
+            }),
+        ]));
+        code_gen.gen_ir(main_func(Vec::new()));
+        let stream = code_gen.stream();
+        assert_eq!(
+            verifier::verify(&stream),
+            Err(vec![
+                VerifyError::DuplicateDefinition("main".into()),
+                VerifyError::MissingEntry,
+                VerifyError::LiteralMismatch("300".into(), "i8".into()),
+                VerifyError::UnknownIdent("y".into()),
+                VerifyError::ArgCount {
+                    func: "main".into(),
+                    expected: 0,
+                    received: 1
+                },
+                VerifyError::UnknownLabel("end".into()),
+                VerifyError::UnknownType("Point".into()),
+                VerifyError::ReturnType {
+                    func: "main".into(),
+                    expected: "i32".into(),
+                    received: "i8".into()
+                },
+                VerifyError::ArgType {
+                    func: "print".into(),
+                    index: 0,
+                    expected: "[i8; n] or *i8".into(),
+                    received: "i8".into()
+                },
+                VerifyError::InvalidCast("cast trunc f32".into(), "i8".into()),
+                VerifyError::TypeMismatch {
+                    expected: "i1 or integer".into(),
+                    received: "f32".into()
+                },
+                VerifyError::LiteralMismatch("\"abc\"".into(), "[i8; 2]".into()),
+                VerifyError::LiteralMismatch("1".into(), "i64".into()),
+                VerifyError::TypeMismatch {
+                    expected: "i8".into(),
+                    received: "i32".into()
+                },
+                VerifyError::TypeMismatch {
+                    expected: "i8".into(),
+                    received: "i64".into()
+                },
+                VerifyError::TypeMismatch {
+                    expected: "integer".into(),
+                    received: "f32".into()
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_type_helpers() {
+        assert_eq!(ir::Type::from_ident("i16"), ir::Type::Int(ir::IntType::I16));
+        assert_eq!(ir::Type::from_ident("void"), ir::Type::Void);
+        assert_eq!(ir::Type::from_ident("Point"), ir::Type::Ident("Point"));
+
+        assert!(INT1_T.is_integer());
+        assert!(!FLOAT32_T.is_integer());
+        assert_eq!(INT1_T.bit_width(), Some(1));
+        assert_eq!(INT64_T.bit_width(), Some(64));
+        assert_eq!(ir::Type::Ident("Point").bit_width(), None);
+        assert!(INT8_T.is_signed());
+        assert!(!INT1_T.is_signed());
+        assert!(!UINT8_T.is_signed());
+        assert!(!FLOAT32_T.is_signed());
+        assert_eq!(UINT128_T.bit_width(), Some(128));
+
+        assert!(INT8_T.fits(-128) && !INT8_T.fits(128));
+        assert!(UINT8_T.fits(255) && !UINT8_T.fits(-1));
+        assert!(UINT128_T.fits(i128::MAX) && !INT1_T.fits(2));
+
+        assert_eq!(ir::Type::from_ident("f64").to_string(), "f64");
+        assert_eq!(ir::Type::from_ident("u16").to_string(), "u16");
+        assert_eq!(INT1_T.to_string(), "i1");
+    }
+
+    #[test]
+    fn test_builder() {
+        let arena = Bump::new();
+        let mut builder = IRBuilder::new(&arena);
+        let arg = IRTypedIdent {
+            ident: "x",
+            _type: INT32_T,
+        };
+        builder.begin_function("square", vec![arg], INT32_T);
+        let small = builder.append_block("small");
+        let large = builder.append_block("large");
+        let limit = IRExpr::Literal(Literal::Int32(100), INT32_T);
+        let cond = builder.build_cmp(CmpOperator::SLt, IRExpr::Ident("x"), limit).unwrap();
+        builder.build_br(cond, small, large).unwrap();
+        builder.position_at_end(small);
+        let square = builder.build_mul(IRExpr::Ident("x"), IRExpr::Ident("x")).unwrap();
+        builder.build_ret(square).unwrap();
+        builder.position_at_end(large);
+        builder.build_ret(IRExpr::Ident("x")).unwrap();
+        builder.end_function().unwrap();
+
+        builder.begin_entry();
+        let code = builder.build_call("square", vec![IRExpr::Literal(Literal::Int32(3), INT32_T)]);
+        builder.build_exit(code.unwrap().unwrap()).unwrap();
+        builder.end_function().unwrap();
+
+        let stream = builder.stream();
+        assert_eq!(verifier::verify(&stream), Ok(()));
+        assert_eq!(
+            stream.stream[0].to_string(),
+            "func @square($x i32) i32 {
+    $__t0 i1 = cmp slt %x, l{100:i32}
+    br %__t0, 'small0, 'large1
+    'small0:
+    $__t1 i32 = mul %x, %x
+    ret %__t1
+    'large1:
+    ret %x
+}"
+        );
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        let arena = Bump::new();
+        let mut builder = IRBuilder::new(&arena);
+        builder.begin_function("main", Vec::new(), INT32_T);
+        let byte = IRExpr::Literal(Literal::Int8(1), INT8_T);
+        let int = IRExpr::Literal(Literal::Int32(1), INT32_T);
+        // Temporaries live in a reserved namespace, so they never clash with user variables
+        let user = builder.build_var("_t0", INT32_T, int.clone(), false).unwrap();
+        assert_eq!(builder.build_add(user, int.clone()), Ok(IRExpr::Ident("__t0")));
+        assert!(builder.build_var("_t1", INT32_T, int.clone(), false).is_ok());
+        assert_eq!(
+            builder.build_var("__t1", INT32_T, int.clone(), false),
+            Err(VerifyError::ReservedName("__t1".into()))
+        );
+        assert_eq!(
+            builder.build_add(int.clone(), byte.clone()),
+            Err(VerifyError::TypeMismatch {
+                expected: "i32".into(),
+                received: "i8".into()
+            })
+        );
+        assert_eq!(
+            builder.build_call("main", vec![int]),
+            Err(VerifyError::ArgCount {
+                func: "main".into(),
+                expected: 0,
+                received: 1
+            })
+        );
+        assert_eq!(
+            builder.build_ret(byte),
+            Err(VerifyError::ReturnType {
+                func: "main".into(),
+                expected: "i32".into(),
+                received: "i8".into()
+            })
+        );
+        builder.build_jmp("end");
+        assert_eq!(
+            builder.end_function(),
+            Err(VerifyError::UnknownLabel("end".into()))
+        );
+    }
+
+    #[test]
+    fn test_builder_vectors() {
+        let arena = Bump::new();
+        let mut builder = IRBuilder::new(&arena);
+        let vec4 = ir::Type::Vector(&INT32_T, 4);
+        let arg = IRTypedIdent {
+            ident: "v",
+            _type: vec4,
+        };
+        builder.begin_function("lanes", vec![arg], INT32_T);
+        let sum = builder.build_add(IRExpr::Ident("v"), IRExpr::Ident("v")).unwrap();
+        let swapped = builder.build_shuffle(sum, IRExpr::Ident("v"), vec![1, 0, 7, 6]).unwrap();
+        let five = IRExpr::Literal(Literal::Int32(5), INT32_T);
+        let set = builder.build_insert(swapped, five, 3).unwrap();
+        let lane = builder.build_extract(set, 3).unwrap();
+        assert_eq!(
+            builder.build_extract(IRExpr::Ident("v"), 4),
+            Err(VerifyError::LaneOutOfRange { lane: 4, lanes: 4 })
+        );
+        builder.build_ret(lane).unwrap();
+        builder.end_function().unwrap();
+
+        builder.begin_entry();
+        let lanes = (1..=4)
+            .map(|val| IRExpr::Literal(Literal::Int32(val), INT32_T))
+            .collect();
+        let code = builder.build_call("lanes", vec![IRExpr::Literal(Literal::Vector(lanes), vec4)]);
+        builder.build_exit(code.unwrap().unwrap()).unwrap();
+        builder.end_function().unwrap();
+
+        let stream = builder.stream();
+        assert_eq!(verifier::verify(&stream), Ok(()));
+        assert_eq!(
+            stream.stream[0].to_string(),
+            "func @lanes($v <i32; 4>) i32 {
+    $__t0 <i32; 4> = add %v, %v
+    $__t1 <i32; 4> = shuffle %__t0, %v, [1,0,7,6]
+    $__t2 <i32; 4> = insertelem %__t1, l{5:i32}, 3
+    $__t3 i32 = extractelem %__t2, 3
+    ret %__t3
+}"
+        );
+        let read_arena = Bump::new();
+        let read = bitcode::read(&bitcode::write(&stream), &read_arena).unwrap();
+        assert_eq!(read.stream, stream.stream);
+    }
+
+    /// `$x i32 = add l{1:i32}, l{2:i32}` followed by `ret x`
+    fn visit_func<'ir>() -> IRStmt<'ir> {
+        let lit = |val| Box::new(IRExpr::Literal(Literal::Int32(val), INT32_T));
+        IRStmt::Function(FuncStmt {
+            name: IRTypedIdent {
+                ident: "f",
+                _type: INT32_T,
+            },
+            args: Vec::new(),
+            block: BlockStmt {
+                stmts: vec![
+                    IRStmt::Variable(VarStmt {
+                        name: IRTypedIdent {
+                            ident: "x",
+                            _type: INT32_T,
+                        },
+                        val: IRExpr::ArithOp(ArithOpExpr {
+                            op: Operator::Add,
+                            values: (lit(1), lit(2)),
+                            loc: None,
+                        }),
+                        is_const: true,
+                        loc: None,
+                    }),
+                    IRStmt::Return(ReturnStmt {
+                        ret_val: IRExpr::Ident("x"),
+                        loc: None,
+                    }),
+                ],
+            },
+            loc: None,
+        })
+    }
+
+    #[test]
+    fn test_visit() {
+        #[derive(Default)]
+        struct Uses<'ir> {
+            idents: Vec<Ident<'ir>>,
+            literals: usize,
+        }
+
+        impl<'ir> Visitor<'ir> for Uses<'ir> {
+            fn visit_ident(&mut self, ident: Ident<'ir>) {
+                self.idents.push(ident);
+            }
+
+            fn visit_literal(&mut self, lit: &'ir Literal<'ir>, _type: &'ir ir::Type<'ir>) {
+                self.literals += 1;
+                visit::walk_literal(self, lit, _type);
+            }
+        }
+
+        let func = visit_func();
+        let mut uses = Uses::default();
+        uses.visit_stmt(&func);
+        assert_eq!(uses.idents, vec!["x"]);
+        assert_eq!(uses.literals, 2);
+    }
+
+    #[test]
+    fn test_visit_mut_and_fold() {
+        struct Rename;
+
+        impl<'ir> VisitorMut<'ir> for Rename {
+            fn visit_ident(&mut self, ident: &mut Ident<'ir>) {
+                *ident = "y";
+            }
+        }
+
+        struct ConstFold;
+
+        impl<'ir> Fold<'ir> for ConstFold {
+            fn fold_expr(&mut self, expr: IRExpr<'ir>) -> IRExpr<'ir> {
+                match fold::walk_expr(self, expr) {
+                    IRExpr::ArithOp(ArithOpExpr {
+                        op: Operator::Add,
+                        values: (lhs, rhs),
+                        loc,
+                    }) => match (*lhs, *rhs) {
+                        (
+                            IRExpr::Literal(Literal::Int32(lhs), _type),
+                            IRExpr::Literal(Literal::Int32(rhs), _),
+                        ) => IRExpr::Literal(Literal::Int32(lhs + rhs), _type),
+                        (lhs, rhs) => IRExpr::ArithOp(ArithOpExpr {
+                            op: Operator::Add,
+                            values: (Box::new(lhs), Box::new(rhs)),
+                            loc,
+                        }),
+                    },
+                    expr => expr,
+                }
+            }
+        }
+
+        let mut func = visit_func();
+        Rename.visit_stmt(&mut func);
+        let func = ConstFold.fold_stmt(func);
+        assert_eq!(
+            func.to_string(),
+            "func @f() i32 {
+    $x i32 = l{3:i32}
+    ret %y
+}"
+        );
+    }
+
+    #[test]
+    fn test_bitcode() {
+        let arena = Bump::new();
+        let mut gen = IRGenerator::default();
+        let point = IRTypedIdent {
+            ident: "x",
+            _type: ir::Type::Pointer(arena.alloc(ir::Type::Array(arena.alloc(INT8_T), 4))),
+        };
+        gen.gen_ir(IRStmt::Struct(ir::StructStmt {
+            name: "Point",
+            fields: vec![point],
+            loc: None,
+        }));
+        let loc = SourceLoc {
+            file: "main.tl",
+            line: 3,
+            col: 5,
+        };
+        gen.gen_ir_at(visit_func(), loc);
+        gen.gen_ir(IRStmt::Entry(BlockStmt {
+            stmts: vec![
+                IRStmt::Call(CallExpr {
+                    name: "print",
+                    args: vec![
+                        IRExpr::Literal(Literal::String(b"hell\xff"), ir::Type::Ident("Point")),
+                        IRExpr::Literal(Literal::Float64(-0.5), ir::FLOAT64_T),
+                        IRExpr::Literal(Literal::Int64(i64::MIN), INT64_T),
+                    ],
+                    loc: None,
+                }),
+                IRStmt::Exit(ExitStmt {
+                    exit_code: IRExpr::Call(CallExpr {
+                        name: "f",
+                        args: Vec::new(),
+                        loc: None,
+                    }),
+                    loc: None,
+                }),
+            ],
+        }));
+        let stream = gen.stream();
+
+        let bytes = bitcode::write(&stream);
+        assert_eq!(bytes[..4], bitcode::MAGIC);
+        let read_arena = Bump::new();
+        let read = bitcode::read(&bytes, &read_arena).unwrap();
+        assert_eq!(read.stream, stream.stream);
+        assert_eq!(read.stream[1].loc(), Some(loc));
+        assert_eq!(read.types, stream.types);
+        assert_eq!(bitcode::write(&read), bytes);
+    }
+
+    #[test]
+    fn test_bitcode_errors() {
+        let arena = Bump::new();
+        let mut gen = IRGenerator::default();
+        gen.gen_ir(visit_func());
+        let bytes = bitcode::write(&gen.stream());
+
+        assert_eq!(
+            bitcode::read(b"CHIR", &arena).unwrap_err(),
+            BitcodeError::InvalidMagic
+        );
+        let mut newer = bytes.clone();
+        newer[4] += 1;
+        assert_eq!(
+            bitcode::read(&newer, &arena).unwrap_err(),
+            BitcodeError::UnsupportedVersion(bitcode::VERSION + 1)
+        );
+        // Older versions used a different encoding
+        let mut older = bytes.clone();
+        older[4] -= 1;
+        assert_eq!(
+            bitcode::read(&older, &arena).unwrap_err(),
+            BitcodeError::UnsupportedVersion(bitcode::VERSION - 1)
+        );
+        assert_eq!(
+            bitcode::read(&bytes[..bytes.len() - 1], &arena).unwrap_err(),
+            BitcodeError::UnexpectedEof
+        );
+        // An exit whose code is a long chain of `not` expressions
+        let mut nested = bitcode::MAGIC.to_vec();
+        nested.extend(bitcode::VERSION.to_le_bytes());
+        nested.extend([0, 0, 1, 7]);
+        nested.extend([5; 100_000]);
+        assert_eq!(
+            bitcode::read(&nested, &arena).unwrap_err(),
+            BitcodeError::TooDeep
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape::unescape_str(r#"a\n\t\r\0\\\"\'\x41"#).unwrap(),
+            b"a\n\t\r\0\\\"'A"
+        );
+        // Strings and chars both treat `\xNN` as a byte and other characters as UTF-8
+        assert_eq!(escape::unescape_str(r"\xffé").unwrap(), b"\xff\xc3\xa9");
+        assert_eq!(escape::unescape_char(r"\xff").unwrap(), 0xff);
+        assert!(escape::unescape_char("é").is_err());
+        assert!(escape::unescape_str(r"\q").is_err());
+        assert!(escape::unescape_char("ab").is_err());
+
+        assert_eq!(
+            escape::escape_str(b"\"\xc3\xa4\\\n\x7f'"),
+            r#"\"ä\\\n\x7f'"#
+        );
+        assert_eq!(escape::escape_str(b"\xff\xc3"), r"\xff\xc3");
+        assert_eq!(escape::escape_char(b'\''), r"\'");
+        assert_eq!(escape::escape_char(0xff), r"\xff");
+        assert_eq!(
+            IRExpr::Literal(
+                Literal::String(b"say \"hi\"\n"),
+                ir::Type::Array(&INT8_T, 9)
+            )
+            .to_string(),
+            r#"l{"say \"hi\"\n":[i8; 9]}"#
+        );
+    }
 }
@@ -12,3 +12,168 @@ impl Display for InvalidLiteral {
         f.write_str(format!("The provided literal \"{}\" is invalid", self.0).as_str())
     }
 }
+
+/// A cast (first value) that cannot be applied to a value of the type (second value)
+#[derive(Debug)]
+pub struct InvalidCast(pub String, pub String);
+
+impl Error for InvalidCast {}
+
+impl Display for InvalidCast {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The cast \"{}\" cannot be applied to a value of type {}",
+            self.0, self.1
+        )
+    }
+}
+
+/// A problem in an [HIRStream](crate::ir::irgen::HIRStream) that was found by the
+/// [verifier](crate::ir::verifier) or the [builder](crate::ir::builder)
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyError {
+    UnknownIdent(String),
+    UnknownFunction(String),
+    UnknownLabel(String),
+    UnknownType(String),
+    UnknownField {
+        _type: String,
+        field: String,
+    },
+    /// The literal (first value) cannot be represented by its type suffix (second value)
+    LiteralMismatch(String, String),
+    ArgCount {
+        func: String,
+        expected: usize,
+        received: usize,
+    },
+    ArgType {
+        func: String,
+        index: usize,
+        expected: String,
+        received: String,
+    },
+    ReturnType {
+        func: String,
+        expected: String,
+        received: String,
+    },
+    TypeMismatch {
+        expected: String,
+        received: String,
+    },
+    AssignToConstant(String),
+    /// The cast (first value) cannot be applied to a value of the type (second value)
+    InvalidCast(String, String),
+    LaneOutOfRange {
+        lane: u32,
+        lanes: u32,
+    },
+    MissingEntry,
+    DuplicateDefinition(String),
+    /// The name starts with `__`, which is reserved for temporaries of the IR builder
+    ReservedName(String),
+}
+
+impl Error for VerifyError {}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::UnknownIdent(ident) => write!(f, "Could not find ident with name {ident}"),
+            VerifyError::UnknownFunction(func) => {
+                write!(f, "Could not find function with name {func}")
+            }
+            VerifyError::UnknownLabel(label) => write!(f, "Could not find label with name {label}"),
+            VerifyError::UnknownType(_type) => write!(f, "Could not find type with name {_type}"),
+            VerifyError::UnknownField { _type, field } => {
+                write!(f, "Type {_type} has no field with name {field}")
+            }
+            VerifyError::LiteralMismatch(lit, _type) => {
+                write!(f, "The literal {lit} cannot be represented as {_type}")
+            }
+            VerifyError::ArgCount {
+                func,
+                expected,
+                received,
+            } => write!(
+                f,
+                "Function {func} expects {expected} arguments, received {received} instead"
+            ),
+            VerifyError::ArgType {
+                func,
+                index,
+                expected,
+                received,
+            } => write!(
+                f,
+                "Argument {index} of function {func} is of type {expected}, received {received} instead"
+            ),
+            VerifyError::ReturnType {
+                func,
+                expected,
+                received,
+            } => write!(
+                f,
+                "Function {func} returns {expected}, received {received} instead"
+            ),
+            VerifyError::TypeMismatch { expected, received } => {
+                write!(f, "Expected a value of type {expected}, received {received} instead")
+            }
+            VerifyError::AssignToConstant(name) => {
+                write!(f, "Cannot assign a new value to the constant {name}")
+            }
+            VerifyError::InvalidCast(cast, _type) => InvalidCast(cast.clone(), _type.clone()).fmt(f),
+            VerifyError::LaneOutOfRange { lane, lanes } => {
+                write!(f, "Lane {lane} is out of range for a vector with {lanes} lanes")
+            }
+            VerifyError::MissingEntry => write!(f, "The program does not have an entry block"),
+            VerifyError::DuplicateDefinition(name) => write!(f, "{name} is defined more than once"),
+            VerifyError::ReservedName(name) => {
+                write!(f, "{name} is reserved for temporaries generated by the IR builder")
+            }
+        }
+    }
+}
+
+/// A problem that was found while reading [bitcode](crate::ir::bitcode)
+#[derive(Debug, Clone, PartialEq)]
+pub enum BitcodeError {
+    /// The input does not start with the bitcode magic number
+    InvalidMagic,
+    /// The input was written by a different version of the format
+    UnsupportedVersion(u16),
+    UnexpectedEof,
+    /// An integer does not fit into the type it is read as
+    InvalidInt,
+    /// The tag (second value) does not belong to any variant of the node (first value)
+    InvalidTag(&'static str, u64),
+    /// The index does not refer to an entry of the string table
+    InvalidString(u64),
+    /// An identifier is not valid UTF-8. String literals can hold any bytes
+    InvalidUtf8,
+    /// Nodes are nested deeper than [MAX_DEPTH](crate::ir::bitcode::MAX_DEPTH)
+    TooDeep,
+}
+
+impl Error for BitcodeError {}
+
+impl Display for BitcodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BitcodeError::InvalidMagic => write!(f, "The input is not citadel bitcode"),
+            BitcodeError::UnsupportedVersion(version) => {
+                write!(f, "Bitcode version {version} is not supported")
+            }
+            BitcodeError::UnexpectedEof => write!(f, "The bitcode ended unexpectedly"),
+            BitcodeError::InvalidInt => write!(f, "An integer of the bitcode is out of range"),
+            BitcodeError::InvalidTag(node, tag) => write!(f, "Invalid tag {tag} for {node}"),
+            BitcodeError::InvalidString(index) => {
+                write!(f, "Could not find string with index {index}")
+            }
+            BitcodeError::InvalidUtf8 => write!(f, "An identifier of the bitcode is not valid UTF-8"),
+            BitcodeError::TooDeep => write!(f, "The bitcode nests nodes too deeply"),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Escape sequences of string and char literals.
+//!
+//! Supported sequences are `\n`, `\t`, `\r`, `\\`, `\"`, `\'`, `\0` and `\xNN`.
+//! Literals are byte strings: `\xNN` is a single byte and any other character
+//! becomes its UTF-8 bytes, so char literals can only hold ASCII characters.
+
+use std::str::Chars;
+
+use super::errors::InvalidLiteral;
+
+/// Replaces the escape sequences in the contents of a string literal (without the quotes)
+pub fn unescape_str(input: &str) -> Result<Vec<u8>, InvalidLiteral> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            out.extend_from_slice(char.encode_utf8(&mut [0; 4]).as_bytes());
+            continue;
+        }
+        match unescape_seq(&mut chars) {
+            Some(byte) => out.push(byte),
+            None => return Err(InvalidLiteral(format!("\"{input}\""))),
+        }
+    }
+    Ok(out)
+}
+
+/// Converts the contents of a char literal (without the quotes) to its byte
+pub fn unescape_char(input: &str) -> Result<u8, InvalidLiteral> {
+    let mut chars = input.chars();
+    let byte = match chars.next() {
+        Some('\\') => unescape_seq(&mut chars),
+        Some(char) if char.is_ascii() => Some(char as u8),
+        _ => None,
+    };
+    match byte {
+        Some(byte) if chars.next().is_none() => Ok(byte),
+        _ => Err(InvalidLiteral(format!("'{input}'"))),
+    }
+}
+
+/// Reads the escape sequence after a backslash
+fn unescape_seq(chars: &mut Chars) -> Option<u8> {
+    Some(match chars.next()? {
+        'n' => b'\n',
+        't' => b'\t',
+        'r' => b'\r',
+        '0' => b'\0',
+        '\\' => b'\\',
+        '"' => b'"',
+        '\'' => b'\'',
+        'x' => {
+            let digits = [chars.next()?, chars.next()?];
+            u8::from_str_radix(&String::from_iter(digits), 16).ok()?
+        }
+        _ => return None,
+    })
+}
+
+/// Escapes a string so it can be written between the quotes of a string literal.
+/// Bytes that are not valid UTF-8 are written as `\xNN`
+pub fn escape_str(input: &[u8]) -> String {
+    let mut out = String::with_capacity(input.len());
+    for chunk in input.utf8_chunks() {
+        for char in chunk.valid().chars() {
+            match char {
+                '"' => out.push_str("\\\""),
+                char if char.is_ascii() => out.push_str(&escape_byte(char as u8)),
+                char => out.push(char),
+            }
+        }
+        for byte in chunk.invalid() {
+            out.push_str(&escape_byte(*byte));
+        }
+    }
+    out
+}
+
+/// Escapes a byte so it can be written between the quotes of a char literal
+pub fn escape_char(char: u8) -> String {
+    match char {
+        b'\'' => "\\'".into(),
+        char => escape_byte(char),
+    }
+}
+
+fn escape_byte(byte: u8) -> String {
+    match byte {
+        b'\n' => "\\n".into(),
+        b'\t' => "\\t".into(),
+        b'\r' => "\\r".into(),
+        b'\0' => "\\0".into(),
+        b'\\' => "\\\\".into(),
+        b' '..=b'~' => (byte as char).into(),
+        byte => format!("\\x{byte:02x}"),
+    }
+}
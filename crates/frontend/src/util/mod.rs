@@ -3,8 +3,9 @@ use std::fmt::Debug;
 use crate::ir::{IRExpr, IRTypedIdent};
 
 pub mod errors;
+pub mod escape;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum CompositeDataType {
     Struct,
     Union,
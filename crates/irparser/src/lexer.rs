@@ -1,20 +1,71 @@
+use std::ops::Range;
+
+use citadel_frontend::ir::SourceLoc;
 use logos::Logos;
 
 use crate::tokens::Token;
 
 pub struct Lexer<'l> {
     pub tokens: Vec<Token<'l>>,
+    /// Byte range of every token in the source
+    pub spans: Vec<Range<usize>>,
     pub source: &'l str,
+    /// Name of the file the source was read from, used for source locations
+    pub file: &'l str,
+    /// Byte offset of the start of every line
+    line_starts: Vec<usize>,
 }
 
 impl<'l> Lexer<'l> {
     pub fn new(source: &'l str) -> Self {
-        let tokens = Token::lexer(source)
-            .map(|tok| match tok {
-                Ok(tok) => tok,
-                Err(()) => panic!(),
+        Self::with_file(source, "<input>")
+    }
+
+    pub fn with_file(source: &'l str, file: &'l str) -> Self {
+        let line_starts = Self::line_starts(source);
+        let (tokens, spans) = Token::lexer(source)
+            .spanned()
+            .map(|(tok, span)| match tok {
+                Ok(tok) => (tok, span),
+                Err(()) => panic!(
+                    "Lexer Error at {}: Invalid token {:?}",
+                    Self::loc_of(source, file, &line_starts, span.start),
+                    &source[span]
+                ),
             })
-            .collect();
-        Self { tokens, source }
+            .unzip();
+        Self {
+            tokens,
+            spans,
+            source,
+            file,
+            line_starts,
+        }
+    }
+
+    /// Returns the location of the token at the index. The end of
+    /// the source is used for indices after the last token
+    pub fn loc(&self, tok_index: usize) -> SourceLoc<'l> {
+        let offset = self
+            .spans
+            .get(tok_index)
+            .map_or(self.source.len(), |span| span.start);
+        Self::loc_of(self.source, self.file, &self.line_starts, offset)
+    }
+
+    fn loc_of(source: &str, file: &'l str, line_starts: &[usize], offset: usize) -> SourceLoc<'l> {
+        let line = line_starts.partition_point(|start| *start <= offset);
+        let col = source[line_starts[line - 1]..offset].chars().count() + 1;
+        SourceLoc {
+            file,
+            line: line as u32,
+            col: col as u32,
+        }
+    }
+
+    fn line_starts(source: &str) -> Vec<usize> {
+        std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect()
     }
 }
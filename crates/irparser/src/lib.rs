@@ -2,6 +2,7 @@
 
 mod lexer;
 mod parser;
+mod tests;
 mod tokens;
 mod utils;
 
@@ -6,10 +6,11 @@ use bumpalo::Bump;
 use citadel_frontend::ir::{
     self,
     irgen::{HIRStream, IRGenerator},
-    ArithOpExpr, BlockStmt, CallExpr, DeclFuncStmt, ExitStmt, FuncStmt, IRExpr, IRStmt,
-    IRTypedIdent, Ident, JumpStmt, LabelStmt, Literal, Operator, ReturnStmt, StructInitExpr,
-    StructStmt, UnionStmt, VarStmt,
+    AddrOfExpr, AllocaExpr, ArithOpExpr, AssignStmt, BlockStmt, BranchStmt, CallExpr, CastExpr, CastKind, CmpOpExpr, CmpOperator, DeclFuncStmt, ExitStmt, ExtractExpr, FieldExpr, FloatType, FuncStmt, IRExpr, IRStmt, IndexExpr,
+    IRTypedIdent, Ident, InsertExpr, IntType, JumpStmt, LabelStmt, Literal, LoadExpr, NotExpr, Operator, ReturnStmt, StoreStmt, StructInitExpr,
+    ShuffleExpr, SourceLoc, StructStmt, UnionStmt, VarStmt,
 };
+use citadel_frontend::util::escape;
 
 use crate::{expect_tok, lexer::Lexer, parser_error, tokens::Token};
 
@@ -40,44 +41,74 @@ impl<'p> Parser<'p> {
         ir_gen.stream()
     }
 
+    /// Parses the statement at the current token and marks it with its location
     pub fn parse_stmt(&mut self) -> Option<IRStmt<'p>> {
-        match self.cur_tok()? {
+        let loc = self.cur_loc();
+        let stmt = match self.cur_tok()? {
             Token::Entry => self.parse_entry(),
             Token::DollarSign => self.parse_variable(true),
             Token::QuestionMark => self.parse_variable(false),
+            Token::PercentSign => self.parse_assign(),
             Token::Func => self.parse_function(),
             Token::Apostrophe => self.parse_label(),
             Token::Decl => self.parse_function_decl(),
-            Token::Call => self.parse_call().map(|call| IRStmt::Call(call)),
+            Token::Call => self.parse_call().map(IRStmt::Call),
             Token::Ret => self.parse_return(),
             Token::Exit => self.parse_exit(),
             Token::Jump => self.parse_jump(),
+            Token::Branch => self.parse_branch(),
+            Token::Store => self.parse_store(),
             Token::Struct => self.parse_struct(),
             Token::Union => self.parse_union(),
-            tok => panic!(
+            tok => parser_error!(
+                self,
                 "Cannot parse statement from token: {tok:?} (peek: {:?})",
                 self.peek_tok()
             ),
-        }
+        };
+        Some(stmt?.with_loc(Some(loc)))
     }
 
+    /// Parses the expression at the current token and marks it with its location
     pub fn parse_expr(&mut self) -> Option<IRExpr<'p>> {
-        match self.cur_tok()? {
-            Token::Call => self.parse_call().map(|call| IRExpr::Call(call)),
+        let loc = self.cur_loc();
+        let expr = match self.cur_tok()? {
+            Token::Call => self.parse_call().map(IRExpr::Call),
             Token::Add => self.parse_arith_op_expr(Operator::Add),
             Token::Sub => self.parse_arith_op_expr(Operator::Sub),
             Token::Mul => self.parse_arith_op_expr(Operator::Mul),
-            Token::Div => self.parse_arith_op_expr(Operator::Div),
+            Token::SDiv => self.parse_arith_op_expr(Operator::SDiv),
+            Token::UDiv => self.parse_arith_op_expr(Operator::UDiv),
+            Token::SRem => self.parse_arith_op_expr(Operator::SRem),
+            Token::URem => self.parse_arith_op_expr(Operator::URem),
+            Token::And => self.parse_arith_op_expr(Operator::And),
+            Token::Or => self.parse_arith_op_expr(Operator::Or),
+            Token::XOr => self.parse_arith_op_expr(Operator::XOr),
+            Token::Shl => self.parse_arith_op_expr(Operator::Shl),
+            Token::LShr => self.parse_arith_op_expr(Operator::LShr),
+            Token::AShr => self.parse_arith_op_expr(Operator::AShr),
+            Token::Not => self.parse_not_expr(),
+            Token::Cmp => self.parse_cmp_op_expr(),
+            Token::Cast => self.parse_cast_expr(),
+            Token::Alloca => self.parse_alloca(),
+            Token::Load => self.parse_load(),
+            Token::AddrOf => self.parse_addr_of(),
+            Token::GetField => self.parse_field(),
+            Token::GetElem => self.parse_index(),
+            Token::ExtractElem => self.parse_extract(),
+            Token::InsertElem => self.parse_insert(),
+            Token::Shuffle => self.parse_shuffle(),
             Token::Ident("l") if *self.peek_tok()? == Token::LCurly => self.parse_lit(),
             Token::PercentSign => self.parse_ident(),
             Token::Struct => self.parse_struct_init(),
-            tok => todo!("cur tok: {tok:?}"),
-        }
+            tok => parser_error!(self, "Cannot parse expression from token: {tok:?}"),
+        };
+        Some(expr?.with_loc(Some(loc)))
     }
 
     fn parse_entry(&mut self) -> Option<IRStmt<'p>> {
         expect_tok!(self.peek_tok()?, Token::LCurly, |tok| {
-            parser_error!(
+            parser_error!(self, 
                 "Expected left curly starting block after entry keyword, received {tok:?} instead"
             );
         });
@@ -90,12 +121,12 @@ impl<'p> Parser<'p> {
         self.next_tok();
         match self.cur_tok()? {
             Token::Ident(ident) => Some(IRExpr::Ident(ident)),
-            _ => panic!(),
+            tok => parser_error!(self, "Expected identifier after %, received {tok:?} instead"),
         }
     }
 
     fn parse_variable(&mut self, is_const: bool) -> Option<IRStmt<'p>> {
-        expect_tok!(self.peek_tok(), Some(Token::Ident(_)), |tok| parser_error!(
+        expect_tok!(self.peek_tok(), Some(Token::Ident(_)), |tok| parser_error!(self, 
             "Expected peek token to be an identifier specifying the name, received {tok:?} instead"
         ));
 
@@ -110,7 +141,7 @@ impl<'p> Parser<'p> {
 
         let _type = self.parse_type()?;
 
-        expect_tok!(self.peek_tok(), Some(Token::Assign), |tok| parser_error!(
+        expect_tok!(self.peek_tok(), Some(Token::Assign), |tok| parser_error!(self, 
             "Expected peek token to be Assign, received {tok:?} instead"
         ));
 
@@ -121,22 +152,40 @@ impl<'p> Parser<'p> {
         let val = self.parse_expr()?;
         let var = IRStmt::Variable(VarStmt {
             name: IRTypedIdent {
-                ident: ident,
+                ident,
                 _type,
             },
             val,
             is_const,
+            loc: None,
         });
         self.symbols.insert(ident, var.clone());
         Some(var)
     }
 
+    fn parse_assign(&mut self) -> Option<IRStmt<'p>> {
+        let name = match self.parse_ident()? {
+            IRExpr::Ident(ident) => ident,
+            _ => unreachable!(),
+        };
+        if let Some(IRStmt::Variable(VarStmt { is_const: true, .. })) = self.symbols.get(name) {
+            parser_error!(self, "Cannot assign a new value to the constant {name}");
+        }
+        expect_tok!(self.peek_tok(), Some(Token::Assign), |tok| parser_error!(self, 
+            "Expected peek token to be Assign, received {tok:?} instead"
+        ));
+        self.next_tok();
+        self.next_tok();
+        let val = self.parse_expr()?;
+        Some(IRStmt::Assign(AssignStmt { name, val, loc: None }))
+    }
+
     fn parse_struct(&mut self) -> Option<IRStmt<'p>> {
-        expect_tok!(self.peek_tok()?, Token::At, |tok| parser_error!(
+        expect_tok!(self.peek_tok()?, Token::At, |tok| parser_error!(self, 
             "Expected peek token to be an @, received {tok:?} instead"
         ));
         self.next_tok();
-        expect_tok!(self.peek_tok()?, Token::Ident(_), |tok| parser_error!(
+        expect_tok!(self.peek_tok()?, Token::Ident(_), |tok| parser_error!(self, 
             "Expected peek token to be an identifier specifying the name, received {tok:?} instead"
         ));
         self.next_tok();
@@ -147,27 +196,26 @@ impl<'p> Parser<'p> {
         };
 
         expect_tok!(self.peek_tok()?, Token::LCurly, |tok| {
-            parser_error!(
+            parser_error!(self, 
             "Expected peek token to be a lcurly declaring the block containing the struct fields, received {tok:?} instead"
         )
         });
         self.next_tok();
 
         let fields = self.parse_arg_list(Token::RCurly)?;
-        dbg!(self.cur_tok());
 
-        Some(IRStmt::Struct(StructStmt { name, fields }))
+        Some(IRStmt::Struct(StructStmt { name, fields, loc: None }))
     }
 
     fn parse_struct_init(&mut self) -> Option<IRExpr<'p>> {
         expect_tok!(
             self.peek_tok(),
             Some(Token::PercentSign),
-            |tok| parser_error!("Expected peek token to be an %, received {tok:?} instead")
+            |tok| parser_error!(self, "Expected peek token to be an %, received {tok:?} instead")
         );
         self.next_tok();
         expect_tok!(self.peek_tok(), Some(Token::Ident(_)), |tok| {
-            parser_error!(
+            parser_error!(self, 
             "Expected peek token to be an ident specifying the name of the struct that is being initialized, received {tok:?} instead"
         )
         });
@@ -179,21 +227,21 @@ impl<'p> Parser<'p> {
         };
 
         expect_tok!(self.peek_tok(), Some(Token::LCurly), |tok| {
-            parser_error!("Expected peek token to be a left curly brace, received {tok:?} instead")
+            parser_error!(self, "Expected peek token to be a left curly brace, received {tok:?} instead")
         });
         self.next_tok();
 
         let values = self.parse_expr_list(Token::RCurly)?;
 
-        Some(IRExpr::StructInit(StructInitExpr { name, values }))
+        Some(IRExpr::StructInit(StructInitExpr { name, values, loc: None }))
     }
 
     fn parse_union(&mut self) -> Option<IRStmt<'p>> {
-        expect_tok!(self.peek_tok()?, Token::At, |tok| parser_error!(
+        expect_tok!(self.peek_tok()?, Token::At, |tok| parser_error!(self, 
             "Expected peek token to be an @, received {tok:?} instead"
         ));
         self.next_tok();
-        expect_tok!(self.peek_tok()?, Token::Ident(_), |tok| parser_error!(
+        expect_tok!(self.peek_tok()?, Token::Ident(_), |tok| parser_error!(self, 
             "Expected peek token to be an identifier specifying the name, received {tok:?} instead"
         ));
         self.next_tok();
@@ -204,7 +252,7 @@ impl<'p> Parser<'p> {
         };
 
         expect_tok!(self.peek_tok()?, Token::LCurly, |tok| {
-            parser_error!(
+            parser_error!(self, 
             "Expected peek token to be a lcurly declaring the block containing the struct fields, received {tok:?} instead"
         )
         });
@@ -212,15 +260,15 @@ impl<'p> Parser<'p> {
 
         let variants = self.parse_arg_list(Token::RCurly)?;
 
-        Some(IRStmt::Union(UnionStmt { name, variants }))
+        Some(IRStmt::Union(UnionStmt { name, variants, loc: None }))
     }
 
     fn parse_function(&mut self) -> Option<IRStmt<'p>> {
-        expect_tok!(self.peek_tok(), Some(Token::At), |tok| parser_error!(
+        expect_tok!(self.peek_tok(), Some(Token::At), |tok| parser_error!(self, 
             "Expected peek token to be an @, received {tok:?} instead"
         ));
         self.next_tok();
-        expect_tok!(self.peek_tok(), Some(Token::Ident(_)), |tok| parser_error!(
+        expect_tok!(self.peek_tok(), Some(Token::Ident(_)), |tok| parser_error!(self, 
             "Expected peek token to be an identifier specifying the name, received {tok:?} instead"
         ));
         self.next_tok();
@@ -231,7 +279,7 @@ impl<'p> Parser<'p> {
         };
 
         expect_tok!(self.peek_tok(), Some(Token::LParent), |tok| {
-            parser_error!(
+            parser_error!(self, 
             "Expected peek token to be a left parenthesis for declaring function arguments, received {tok:?} instead"
         )
         });
@@ -240,16 +288,12 @@ impl<'p> Parser<'p> {
 
         let args = self.parse_arg_list(Token::RParent);
 
-        expect_tok!(self.peek_tok(), Some(Token::Ident(_)), |tok| parser_error!(
-            "Expected peek token to be an identifier specifying the type, received {tok:?} instead"
-        ));
-
         self.next_tok();
 
         let _type = self.parse_type()?;
 
         expect_tok!(self.peek_tok(), Some(Token::LCurly), |tok| {
-            parser_error!(
+            parser_error!(self, 
             "Expected peek token to be a left curly bracket specifying the function block, received {tok:?} instead"
         )
         });
@@ -265,37 +309,39 @@ impl<'p> Parser<'p> {
             },
             args: args?,
             block: block?,
+            loc: None,
         });
         self.symbols.insert(name, func.clone());
         Some(func)
     }
 
     fn parse_function_decl(&mut self) -> Option<IRStmt<'p>> {
+        expect_tok!(self.peek_tok(), Some(Token::Func), |tok| parser_error!(self, 
+            "Expected peek token to be func after decl, received {tok:?} instead"
+        ));
+        self.next_tok();
         expect_tok!(self.peek_tok(), Some(Token::At), |tok| {
-            parser_error!(
+            parser_error!(self, 
             "Expected peek token to be an At specifying that this is a function, received {tok:?} instead"
         )
         });
         self.next_tok();
-        expect_tok!(self.peek_tok(), Some(Token::Ident(_)), |tok| parser_error!(
+        expect_tok!(self.peek_tok(), Some(Token::Ident(_)), |tok| parser_error!(self, 
             "Expected peek token to be an identifier specifying the name, received {tok:?} instead"
         ));
+        self.next_tok();
         let ident = match self.cur_tok() {
             Some(Token::Ident(ident)) => *ident,
             _ => unreachable!(),
         };
         expect_tok!(self.peek_tok(), Some(Token::LParent), |tok| {
-            parser_error!(
+            parser_error!(self, 
             "Expected peek token to be a left parenthesis declaring the arguments, received {tok:?} instead"
         )
         });
         self.next_tok();
         let args = self.parse_arg_list(Token::RParent);
         self.next_tok();
-        expect_tok!(self.peek_tok(), Some(Token::Ident(_)), |tok| parser_error!(
-            "Expected peek token to be an identifier specifying the type, received {tok:?} instead"
-        ));
-        self.next_tok();
         let _type = self.parse_type()?;
         Some(IRStmt::DeclaredFunction(DeclFuncStmt {
             name: IRTypedIdent {
@@ -303,12 +349,13 @@ impl<'p> Parser<'p> {
                 _type,
             },
             args: args?,
+            loc: None,
         }))
     }
 
     fn parse_label(&mut self) -> Option<IRStmt<'p>> {
         expect_tok!(self.peek_tok(), Some(Token::Ident(_)), |tok| {
-            parser_error!(
+            parser_error!(self, 
             "Expected peek token to be an identifier specifying the label name, received {tok:?} instead"
         )
         });
@@ -317,11 +364,11 @@ impl<'p> Parser<'p> {
             Some(Token::Ident(ident)) => *ident,
             _ => unreachable!(),
         };
-        expect_tok!(self.peek_tok(), Some(Token::Colon), |tok| parser_error!(
+        expect_tok!(self.peek_tok(), Some(Token::Colon), |tok| parser_error!(self, 
             "Expected peek token to be a colon, received {tok:?} instead"
         ));
         self.next_tok();
-        let label = LabelStmt { name: name };
+        let label = LabelStmt { name, loc: None };
         self.symbols.insert(name, IRStmt::Label(label));
         Some(IRStmt::Label(label))
     }
@@ -330,20 +377,20 @@ impl<'p> Parser<'p> {
         expect_tok!(
             self.peek_tok(),
             Some(Token::PercentSign),
-            |tok| parser_error!(
+            |tok| parser_error!(self, 
                 "Expected peek token to be a percent sign, received {tok:?} instead"
             )
         );
         self.next_tok();
         expect_tok!(self.peek_tok(), Some(Token::Ident(_)), |tok| {
-            parser_error!(
+            parser_error!(self, 
             "Expected peek token to be an identifier specifying the function name, received {tok:?} instead"
         )
         });
         self.next_tok();
         let name = self.parse_identifier();
         expect_tok!(self.peek_tok(), Some(Token::LParent), |tok| {
-            parser_error!(
+            parser_error!(self, 
             "Expected peek token to be a left parenthesis for declaring the call arguments, received {tok:?} instead"
         )
         });
@@ -352,6 +399,7 @@ impl<'p> Parser<'p> {
         Some(CallExpr {
             name: name?,
             args: args?,
+            loc: None,
         })
     }
 
@@ -365,31 +413,29 @@ impl<'p> Parser<'p> {
     fn parse_type(&mut self) -> Option<ir::Type<'p>> {
         match self.cur_tok()? {
             Token::LSquare => self.parse_arr_type(),
-            Token::Ident(ident) => Some(ir::Type::Ident(ident)),
-            tok => parser_error!("Failed to parse type from token: {tok:?}"),
+            Token::Asterisk => self.parse_ptr_type(),
+            Token::LAngle => self.parse_vec_type(),
+            Token::Ident(ident) => Some(ir::Type::from_ident(ident)),
+            tok => parser_error!(self, "Failed to parse type from token: {tok:?}"),
         }
     }
 
     fn parse_arr_type(&mut self) -> Option<ir::Type<'p>> {
         self.next_tok();
-        let type_ = match *self.cur_tok()? {
-            Token::LSquare => self.parse_arr_type()?,
-            Token::Ident(ident) => ir::Type::Ident(ident),
-            tok => parser_error!("Failed to parse type for array from token: {tok:?}"),
-        };
+        let type_ = self.parse_type()?;
         expect_tok!(self.peek_tok()?, Token::Semicolon, |tok| {
-            parser_error!("Expected semicolon after type for array, received: {tok:?} instead")
+            parser_error!(self, "Expected semicolon after type for array, received: {tok:?} instead")
         });
         self.next_tok();
         let size = match *self.peek_tok()? {
             Token::LitInt(int) => int.parse::<u32>().unwrap(),
             tok => {
-                parser_error!("Expected integer literal for array size, received {tok:?} instead")
+                parser_error!(self, "Expected integer literal for array size, received {tok:?} instead")
             }
         };
         self.next_tok();
         expect_tok!(self.peek_tok()?, Token::RSquare, |tok| {
-            parser_error!(
+            parser_error!(self, 
                 "Expected right square bracket after array size, received {tok:?} instead"
             );
         });
@@ -398,43 +444,261 @@ impl<'p> Parser<'p> {
         Some(ir::Type::Array(type_ref, size))
     }
 
+    /// First token is the left angle bracket, for example `<i32; 4>`
+    fn parse_vec_type(&mut self) -> Option<ir::Type<'p>> {
+        self.next_tok();
+        let type_ = self.parse_type()?;
+        expect_tok!(self.peek_tok()?, Token::Semicolon, |tok| {
+            parser_error!(self, "Expected semicolon after type for vector, received: {tok:?} instead")
+        });
+        self.next_tok();
+        self.next_tok();
+        let lanes = self.parse_lane()?;
+        expect_tok!(self.peek_tok()?, Token::RAngle, |tok| {
+            parser_error!(self, 
+                "Expected right angle bracket after the number of lanes, received {tok:?} instead"
+            );
+        });
+        self.next_tok();
+        let type_ref = self.arena.alloc(type_);
+        Some(ir::Type::Vector(type_ref, lanes))
+    }
+
+    fn parse_ptr_type(&mut self) -> Option<ir::Type<'p>> {
+        self.next_tok();
+        let type_ = self.parse_type()?;
+        let type_ref = self.arena.alloc(type_);
+        Some(ir::Type::Pointer(type_ref))
+    }
+
     fn parse_return(&mut self) -> Option<IRStmt<'p>> {
         self.next_tok();
         let expr = self.parse_expr();
-        Some(IRStmt::Return(ReturnStmt { ret_val: expr? }))
+        Some(IRStmt::Return(ReturnStmt { ret_val: expr?, loc: None }))
     }
 
     fn parse_exit(&mut self) -> Option<IRStmt<'p>> {
         self.next_tok();
         let code = self.parse_expr()?;
-        Some(IRStmt::Exit(ExitStmt { exit_code: code }))
+        Some(IRStmt::Exit(ExitStmt { exit_code: code, loc: None }))
     }
 
     fn parse_jump(&mut self) -> Option<IRStmt<'p>> {
-        expect_tok!(
-            self.peek_tok(),
-            Some(Token::Apostrophe),
-            |tok| parser_error!(
-                "Expected peek token to be an apostrophe, received {tok:?} instead"
-            )
-        );
         self.next_tok();
-        expect_tok!(self.peek_tok(), Some(Token::Ident(_)), |tok| parser_error!(
-            "Expected peek token to be an ident specifying the label name, received {tok:?} instead"
+        let label = self.parse_label_ref()?;
+        Some(IRStmt::Jump(JumpStmt { label, loc: None }))
+    }
+
+    fn parse_store(&mut self) -> Option<IRStmt<'p>> {
+        self.next_tok();
+        let (val, ptr) = self.parse_operands()?;
+        Some(IRStmt::Store(StoreStmt {
+            val: *val,
+            ptr: *ptr,
+            loc: None,
+        }))
+    }
+
+    fn parse_branch(&mut self) -> Option<IRStmt<'p>> {
+        self.next_tok();
+        let condition = self.parse_expr()?;
+        expect_tok!(self.peek_tok(), Some(Token::Comma), |tok| parser_error!(self, 
+            "Expected peek token to be a comma after the branch condition, received {tok:?} instead"
         ));
         self.next_tok();
-        let label = match self.cur_tok()? {
-            Token::Ident(id) => id,
-            _ => unreachable!()
-        };
-        Some(IRStmt::Jump(JumpStmt { label }))
+        self.next_tok();
+        let then_label = self.parse_label_ref()?;
+        expect_tok!(self.peek_tok(), Some(Token::Comma), |tok| parser_error!(self, 
+            "Expected peek token to be a comma after the first branch label, received {tok:?} instead"
+        ));
+        self.next_tok();
+        self.next_tok();
+        let else_label = self.parse_label_ref()?;
+        Some(IRStmt::Branch(BranchStmt {
+            condition,
+            then_label,
+            else_label,
+            loc: None,
+        }))
     }
 
     fn parse_arith_op_expr(&mut self, op: Operator) -> Option<IRExpr<'p>> {
         self.next_tok();
+        let values = self.parse_operands()?;
+        Some(IRExpr::ArithOp(ArithOpExpr { op, values, loc: None }))
+    }
+
+    fn parse_not_expr(&mut self) -> Option<IRExpr<'p>> {
+        self.next_tok();
+        let val = Box::new(self.parse_expr()?);
+        Some(IRExpr::Not(NotExpr { val, loc: None }))
+    }
+
+    fn parse_cmp_op_expr(&mut self) -> Option<IRExpr<'p>> {
+        self.next_tok();
+        let op = match self.cur_tok()? {
+            Token::Ident("eq") => CmpOperator::Eq,
+            Token::Ident("ne") => CmpOperator::Ne,
+            Token::Ident("slt") => CmpOperator::SLt,
+            Token::Ident("sle") => CmpOperator::SLe,
+            Token::Ident("sgt") => CmpOperator::SGt,
+            Token::Ident("sge") => CmpOperator::SGe,
+            Token::Ident("ult") => CmpOperator::ULt,
+            Token::Ident("ule") => CmpOperator::ULe,
+            Token::Ident("ugt") => CmpOperator::UGt,
+            Token::Ident("uge") => CmpOperator::UGe,
+            tok => parser_error!(self, 
+                "Expected comparison condition after `cmp`, received {tok:?} instead"
+            ),
+        };
+        self.next_tok();
+        let values = self.parse_operands()?;
+        Some(IRExpr::CmpOp(CmpOpExpr { op, values, loc: None }))
+    }
+
+    fn parse_cast_expr(&mut self) -> Option<IRExpr<'p>> {
+        self.next_tok();
+        let kind = match self.cur_tok()? {
+            Token::Ident("sext") => CastKind::SExt,
+            Token::Ident("zext") => CastKind::ZExt,
+            Token::Ident("trunc") => CastKind::Trunc,
+            Token::Ident("itof") => CastKind::IntToFloat,
+            Token::Ident("ftoi") => CastKind::FloatToInt,
+            Token::Ident("bitcast") => CastKind::Bitcast,
+            tok => parser_error!(self, "Expected cast kind after `cast`, received {tok:?} instead"),
+        };
+        self.next_tok();
+        let _type = self.parse_type()?;
+        expect_tok!(self.peek_tok(), Some(Token::Comma), |tok| parser_error!(self, 
+            "Expected peek token to be a comma after the cast type, received {tok:?} instead"
+        ));
+        self.next_tok();
+        self.next_tok();
+        let val = self.parse_expr()?;
+        Some(IRExpr::Cast(CastExpr {
+            kind,
+            _type,
+            val: Box::new(val),
+            loc: None,
+        }))
+    }
+
+    fn parse_alloca(&mut self) -> Option<IRExpr<'p>> {
+        self.next_tok();
+        let _type = self.parse_type()?;
+        Some(IRExpr::Alloca(AllocaExpr { _type, loc: None }))
+    }
+
+    fn parse_load(&mut self) -> Option<IRExpr<'p>> {
+        self.next_tok();
+        let _type = self.parse_type()?;
+        expect_tok!(self.peek_tok(), Some(Token::Comma), |tok| parser_error!(self, 
+            "Expected peek token to be a comma after the loaded type, received {tok:?} instead"
+        ));
+        self.next_tok();
+        self.next_tok();
+        let ptr = self.parse_expr()?;
+        Some(IRExpr::Load(LoadExpr {
+            _type,
+            ptr: Box::new(ptr),
+            loc: None,
+        }))
+    }
+
+    fn parse_addr_of(&mut self) -> Option<IRExpr<'p>> {
+        self.next_tok();
+        let val = Box::new(self.parse_expr()?);
+        Some(IRExpr::AddrOf(AddrOfExpr { val, loc: None }))
+    }
+
+    fn parse_field(&mut self) -> Option<IRExpr<'p>> {
+        self.next_tok();
+        let base = Box::new(self.parse_expr()?);
+        expect_tok!(self.peek_tok(), Some(Token::Comma), |tok| parser_error!(self, 
+            "Expected peek token to be a comma after the struct, received {tok:?} instead"
+        ));
+        self.next_tok();
+        self.next_tok();
+        let field = match self.cur_tok()? {
+            Token::Ident(ident) => *ident,
+            tok => parser_error!(self, "Expected the name of the field, received {tok:?} instead"),
+        };
+        Some(IRExpr::Field(FieldExpr { base, field, loc: None }))
+    }
+
+    fn parse_index(&mut self) -> Option<IRExpr<'p>> {
+        self.next_tok();
+        let (base, index) = self.parse_operands()?;
+        Some(IRExpr::Index(IndexExpr { base, index, loc: None }))
+    }
+
+    fn parse_extract(&mut self) -> Option<IRExpr<'p>> {
+        self.next_tok();
+        let vector = Box::new(self.parse_expr()?);
+        expect_tok!(self.peek_tok(), Some(Token::Comma), |tok| parser_error!(self, 
+            "Expected peek token to be a comma after the vector, received {tok:?} instead"
+        ));
+        self.next_tok();
+        self.next_tok();
+        let lane = self.parse_lane()?;
+        Some(IRExpr::Extract(ExtractExpr { vector, lane, loc: None }))
+    }
+
+    fn parse_insert(&mut self) -> Option<IRExpr<'p>> {
+        self.next_tok();
+        let (vector, val) = self.parse_operands()?;
+        expect_tok!(self.peek_tok(), Some(Token::Comma), |tok| parser_error!(self, 
+            "Expected peek token to be a comma after the value, received {tok:?} instead"
+        ));
+        self.next_tok();
+        self.next_tok();
+        let lane = self.parse_lane()?;
+        Some(IRExpr::Insert(InsertExpr { vector, val, lane, loc: None }))
+    }
+
+    fn parse_shuffle(&mut self) -> Option<IRExpr<'p>> {
+        self.next_tok();
+        let values = self.parse_operands()?;
+        expect_tok!(self.peek_tok(), Some(Token::Comma), |tok| parser_error!(self, 
+            "Expected peek token to be a comma after the vectors, received {tok:?} instead"
+        ));
+        self.next_tok();
+        expect_tok!(self.peek_tok(), Some(Token::LSquare), |tok| parser_error!(self, 
+            "Expected the shuffle mask to start with a left square bracket, received {tok:?} instead"
+        ));
+        self.next_tok();
+        let mut mask = Vec::new();
+        self.next_tok();
+        while *self.cur_tok()? != Token::RSquare {
+            mask.push(self.parse_lane()?);
+            self.next_tok();
+            match self.cur_tok()? {
+                Token::Comma => self.next_tok(),
+                Token::RSquare => (),
+                tok => parser_error!(self, 
+                    "Expected a comma or a right square bracket after the lane, received {tok:?} instead"
+                ),
+            }
+        }
+        Some(IRExpr::Shuffle(ShuffleExpr { values, mask, loc: None }))
+    }
+
+    /// Parses the index of a lane or the number of lanes of a vector
+    fn parse_lane(&mut self) -> Option<u32> {
+        match *self.cur_tok()? {
+            Token::LitInt(int) => match int.parse::<u32>() {
+                Ok(lane) => Some(lane),
+                Err(_) => parser_error!(self, "Lane {int} is out of range"),
+            },
+            tok => parser_error!(self, "Expected an integer lane, received {tok:?} instead"),
+        }
+    }
+
+    /// Parses two comma separated expressions. First token is the start of the first expression
+    fn parse_operands(&mut self) -> Option<(Box<IRExpr<'p>>, Box<IRExpr<'p>>)> {
         let left = self.parse_expr();
 
-        expect_tok!(self.peek_tok(), Some(Token::Comma), |tok| parser_error!(
+        expect_tok!(self.peek_tok(), Some(Token::Comma), |tok| parser_error!(self, 
             "Expected peek token to be a comma, received {tok:?} instead"
         ));
 
@@ -442,19 +706,21 @@ impl<'p> Parser<'p> {
         self.next_tok();
         let right = self.parse_expr();
 
-        Some(IRExpr::ArithOp(ArithOpExpr {
-            op,
-            values: (Box::from(left?), Box::from(right?)),
-        }))
+        Some((Box::from(left?), Box::from(right?)))
     }
 
+    /// First token is the apostrophe marking the label
     fn parse_label_ref(&mut self) -> Option<Ident<'p>> {
-        let name = match self.cur_tok()? {
-            Token::Ident(ident) => *ident,
-            _ => unreachable!(),
-        };
+        expect_tok!(
+            self.cur_tok(),
+            Some(Token::Apostrophe),
+            |tok| parser_error!(self, "Expected an apostrophe marking a label, received {tok:?} instead")
+        );
+        expect_tok!(self.peek_tok(), Some(Token::Ident(_)), |tok| parser_error!(self, 
+            "Expected peek token to be an ident specifying the label name, received {tok:?} instead"
+        ));
         self.next_tok();
-        Some(name)
+        self.parse_identifier()
     }
 
     /// First token is left curly
@@ -476,19 +742,17 @@ impl<'p> Parser<'p> {
         let mut args = Vec::new();
         self.next_tok();
         loop {
-            args.push(match self.parse_expr() {
-                Some(expr) => expr,
-                None => return None,
-            });
+            args.push(self.parse_expr()?);
             if let Some(Token::Comma) = self.peek_tok() {
                 self.next_tok();
                 self.next_tok();
             } else if self.peek_tok() == Some(&end) {
                 break;
             } else {
-                expect_tok!(self.peek_tok(), Some(Token::RParent), |tok| parser_error!(
-                    "Expected peek token to be a right parent, received {tok:?} instead"
-                ));
+                parser_error!(self, 
+                    "Expected peek token to be a comma or {end:?}, received {:?} instead",
+                    self.peek_tok()
+                );
             }
         }
         self.next_tok();
@@ -511,20 +775,17 @@ impl<'p> Parser<'p> {
             expect_tok!(
                 self.cur_tok(),
                 Some(Token::DollarSign),
-                |tok| parser_error!("Expected dollar sign, received {tok:?} instead")
+                |tok| parser_error!(self, "Expected dollar sign, received {tok:?} instead")
             );
             self.next_tok();
-            args.push(match self.parse_typed_ident() {
-                Some(ident) => ident,
-                None => return None,
-            });
+            args.push(self.parse_typed_ident()?);
             if let Some(Token::Comma) = self.peek_tok() {
                 self.next_tok();
                 self.next_tok();
             } else if self.peek_tok() == Some(&end) {
                 break;
             } else {
-                expect_tok!(self.peek_tok(), Some(Token::RParent), |tok| parser_error!(
+                expect_tok!(self.peek_tok(), Some(Token::RParent), |tok| parser_error!(self, 
                     "Expected peek token to be a right parent, received {tok:?} instead"
                 ));
             }
@@ -542,7 +803,7 @@ impl<'p> Parser<'p> {
         self.next_tok();
         let _type = self.parse_type()?;
         Some(IRTypedIdent {
-            ident: ident,
+            ident,
             _type,
         })
     }
@@ -552,21 +813,16 @@ impl<'p> Parser<'p> {
         self.next_tok();
         // `{`
         self.next_tok();
-        let lit = match self.cur_tok()? {
-            Token::LitString(string) => Literal::String(string.trim_matches('"')),
-            Token::LitInt(int) => Literal::Int32(int.parse().unwrap()),
-            Token::LitFloat(float) => Literal::Float32(float.parse().unwrap()),
-            Token::LitChar(char) => Literal::Char(char.parse().unwrap()),
-            _ => parser_error!("Expected literal after `l{{`"),
-        };
+        let val = self.parse_lit_value()?;
         expect_tok!(self.peek_tok()?, Token::Colon, |tok| {
-            parser_error!("Expected colon to seperate literal from type suffix, received {tok:?}");
+            parser_error!(self, "Expected colon to seperate literal from type suffix, received {tok:?}");
         });
         self.next_tok();
         self.next_tok();
         let type_ = self.parse_type()?;
+        let lit = self.literal(val, &type_);
         expect_tok!(self.peek_tok()?, Token::RCurly, |tok| {
-            parser_error!(
+            parser_error!(self, 
                 "Expected right curly brackets after type suffix, received {tok:?} instead"
             );
         });
@@ -574,11 +830,170 @@ impl<'p> Parser<'p> {
         Some(IRExpr::Literal(lit, type_))
     }
 
+    /// Parses the value of a literal. Its variant is picked later from the type suffix
+    fn parse_lit_value(&mut self) -> Option<LitValue<'p>> {
+        Some(match *self.cur_tok()? {
+            Token::LitString(string) => match escape::unescape_str(&string[1..string.len() - 1]) {
+                Ok(string) => LitValue::String(self.arena.alloc_slice_copy(&string)),
+                Err(err) => parser_error!(self, "{err}"),
+            },
+            Token::LitInt(int) => LitValue::Int(int),
+            Token::LitFloat(float) => LitValue::Float(float),
+            Token::LitChar(char) => match escape::unescape_char(&char[1..char.len() - 1]) {
+                Ok(char) => LitValue::Char(char),
+                Err(err) => parser_error!(self, "{err}"),
+            },
+            Token::Ident("true") => LitValue::Bool(true),
+            Token::Ident("false") => LitValue::Bool(false),
+            Token::LSquare => self.parse_array_value()?,
+            Token::LAngle => LitValue::Vector(self.parse_lit_elems(&[Token::RAngle])?),
+            tok => parser_error!(self, "Expected literal after `l{{`, received {tok:?} instead"),
+        })
+    }
+
+    /// First token is the left square bracket, the length after the elements is optional
+    fn parse_array_value(&mut self) -> Option<LitValue<'p>> {
+        let elems = self.parse_lit_elems(&[Token::Semicolon, Token::RSquare])?;
+        if *self.cur_tok()? == Token::RSquare {
+            return Some(LitValue::Array(elems, None));
+        }
+        self.next_tok();
+        let len = match *self.cur_tok()? {
+            Token::LitInt(int) => int.parse::<usize>().ok(),
+            _ => None,
+        };
+        let Some(len) = len else {
+            parser_error!(self, "Expected the length of the array literal, received {:?} instead", self.cur_tok())
+        };
+        expect_tok!(self.peek_tok()?, Token::RSquare, |tok| {
+            parser_error!(self, 
+                "Expected right square bracket after array length, received {tok:?} instead"
+            );
+        });
+        self.next_tok();
+        Some(LitValue::Array(elems, Some(len)))
+    }
+
+    /// Parses the comma separated elements of an aggregate literal. Elements can either be
+    /// values that take the element type or typed expressions. First token is the opening
+    /// bracket, the last one is the token that ended the list
+    fn parse_lit_elems(&mut self, ends: &[Token<'p>]) -> Option<Vec<LitValue<'p>>> {
+        let mut elems = Vec::new();
+        self.next_tok();
+        while !ends.contains(self.cur_tok()?) {
+            elems.push(match self.cur_tok()? {
+                Token::LitString(_)
+                | Token::LitInt(_)
+                | Token::LitFloat(_)
+                | Token::LitChar(_)
+                | Token::Ident("true" | "false")
+                | Token::LSquare
+                | Token::LAngle => self.parse_lit_value()?,
+                _ => LitValue::Expr(self.parse_expr()?),
+            });
+            self.next_tok();
+            match self.cur_tok()? {
+                Token::Comma => self.next_tok(),
+                tok if ends.contains(tok) => (),
+                tok => parser_error!(self, 
+                    "Expected a comma or one of {ends:?} after the literal element, received {tok:?} instead"
+                ),
+            }
+        }
+        Some(elems)
+    }
+
+    /// Creates the literal variant that matches the type suffix and checks that the value fits into it
+    fn literal(&self, val: LitValue<'p>, _type: &ir::Type<'p>) -> Literal<'p> {
+        match (val, _type) {
+            (LitValue::Int(int), ir::Type::Int(IntType::U128)) => match int.parse::<u128>() {
+                Ok(val) => Literal::UInt128(val),
+                Err(_) => parser_error!(self, "Integer literal {int} does not fit into {_type}"),
+            },
+            (LitValue::Int(int), ir::Type::Bool | ir::Type::Int(_)) => {
+                let val = match int.parse::<i128>() {
+                    Ok(val) if _type.fits(val) => val,
+                    _ => parser_error!(self, "Integer literal {int} does not fit into {_type}"),
+                };
+                match _type {
+                    ir::Type::Int(IntType::I8) => Literal::Int8(val as i8),
+                    ir::Type::Int(IntType::I16) => Literal::Int16(val as i16),
+                    ir::Type::Int(IntType::I32) => Literal::Int32(val as i32),
+                    ir::Type::Int(IntType::I64) => Literal::Int64(val as i64),
+                    ir::Type::Int(IntType::I128) => Literal::Int128(val),
+                    ir::Type::Int(IntType::U8) => Literal::UInt8(val as u8),
+                    ir::Type::Int(IntType::U16) => Literal::UInt16(val as u16),
+                    ir::Type::Int(IntType::U32) => Literal::UInt32(val as u32),
+                    ir::Type::Int(IntType::U64) => Literal::UInt64(val as u64),
+                    _ => Literal::Bool(val != 0),
+                }
+            }
+            (LitValue::Int(float) | LitValue::Float(float), ir::Type::Float(float_type)) => {
+                let lit = match float_type {
+                    FloatType::F32 => float.parse().ok().filter(|val: &f32| val.is_finite()).map(Literal::Float32),
+                    FloatType::F64 => float.parse().ok().filter(|val: &f64| val.is_finite()).map(Literal::Float64),
+                };
+                match lit {
+                    Some(lit) => lit,
+                    None => parser_error!(self, "Float literal {float} does not fit into {_type}"),
+                }
+            }
+            (LitValue::Bool(bool), ir::Type::Bool) => Literal::Bool(bool),
+            (LitValue::Char(char), _) if _type.is_integer() => Literal::Char(char),
+            (LitValue::String(string), _) => Literal::String(string),
+            (LitValue::Array(elems, len), ir::Type::Array(elem_type, size)) => {
+                let len = len.unwrap_or(elems.len());
+                if len != elems.len() || len != *size as usize {
+                    parser_error!(self, 
+                        "Array literal with {} elements and length {len} does not match the type {_type}",
+                        elems.len()
+                    );
+                }
+                let elems = elems
+                    .into_iter()
+                    .map(|elem| self.lit_elem(elem, Some(elem_type)))
+                    .collect();
+                Literal::Array(len, elems)
+            }
+            (LitValue::Vector(elems), ir::Type::Vector(elem_type, lanes)) => {
+                if elems.len() != *lanes as usize {
+                    parser_error!(self, 
+                        "Vector literal with {} elements does not match the type {_type}",
+                        elems.len()
+                    );
+                }
+                let elems = elems
+                    .into_iter()
+                    .map(|elem| self.lit_elem(elem, Some(elem_type)))
+                    .collect();
+                Literal::Vector(elems)
+            }
+            (val, _) => parser_error!(self, "Cannot use {} literal for the type {_type}", val.kind()),
+        }
+    }
+
+    /// Turns an element of an aggregate literal into an expression. Untyped values
+    /// take the element type of the aggregate, if it is known
+    fn lit_elem(&self, elem: LitValue<'p>, elem_type: Option<&ir::Type<'p>>) -> IRExpr<'p> {
+        match (elem, elem_type) {
+            (LitValue::Expr(expr), _) => expr,
+            (val, Some(elem_type)) => IRExpr::Literal(self.literal(val, elem_type), *elem_type),
+            (val, None) => parser_error!(self, 
+                "Cannot infer the type of the {} literal element, use a typed literal instead",
+                val.kind()
+            ),
+        }
+    }
+
     #[inline(always)]
     fn cur_tok(&self) -> Option<&Token<'p>> {
         self.lexer.tokens.get(self.tok_index)
     }
 
+    fn cur_loc(&self) -> SourceLoc<'p> {
+        self.lexer.loc(self.tok_index)
+    }
+
     #[inline(always)]
     fn peek_tok(&self) -> Option<&Token<'p>> {
         self.lexer.tokens.get(self.tok_index + 1)
@@ -589,3 +1004,32 @@ impl<'p> Parser<'p> {
         self.tok_index += 1;
     }
 }
+
+/// The value of a literal before the type suffix has been parsed
+enum LitValue<'p> {
+    Int(&'p str),
+    Float(&'p str),
+    Bool(bool),
+    String(&'p [u8]),
+    Char(u8),
+    /// Elements and the optionally specified length
+    Array(Vec<LitValue<'p>>, Option<usize>),
+    Vector(Vec<LitValue<'p>>),
+    /// An element of an aggregate literal that already is a typed expression
+    Expr(IRExpr<'p>),
+}
+
+impl LitValue<'_> {
+    fn kind(&self) -> &'static str {
+        match self {
+            LitValue::Int(_) => "integer",
+            LitValue::Float(_) => "float",
+            LitValue::Bool(_) => "bool",
+            LitValue::String(_) => "string",
+            LitValue::Char(_) => "char",
+            LitValue::Array(..) => "array",
+            LitValue::Vector(_) => "vector",
+            LitValue::Expr(_) => "expression",
+        }
+    }
+}
@@ -0,0 +1,416 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests {
+    use bumpalo::Bump;
+    use citadel_frontend::ir::{
+        fold::{self, Fold},
+        irgen::{HIRStream, IRGenerator},
+        AddrOfExpr, AllocaExpr, ArithOpExpr, AssignStmt, BlockStmt, BranchStmt, CallExpr,
+        CastExpr, CastKind, CmpOpExpr, CmpOperator, DeclFuncStmt, ExitStmt, ExtractExpr, FieldExpr,
+        FuncStmt, IRExpr, IRStmt, IRTypedIdent, IndexExpr, InsertExpr, JumpStmt, LabelStmt, Literal, LoadExpr, NotExpr,
+        Operator, ReturnStmt, ShuffleExpr, StoreStmt, StructInitExpr, StructStmt, Type, UnionStmt, VarStmt,
+        FLOAT32_T, FLOAT64_T, INT128_T, INT16_T, INT32_T, INT64_T, INT8_T, UINT128_T, UINT16_T,
+        UINT32_T, UINT64_T, UINT8_T,
+    };
+    use proptest::{prelude::*, sample::select};
+
+    use crate::{IRLexer, IRParser};
+
+    /// Names of constants. They are kept apart from [`VARS`] since
+    /// the parser rejects assignments to constants
+    const CONSTS: &[&str] = &["c", "max", "LIMIT"];
+    const VARS: &[&str] = &["x", "y1", "tmp", "_value"];
+    const LABELS: &[&str] = &["a", "loop_start", "end"];
+    const FUNCS: &[&str] = &["main", "f", "print_int"];
+    const STRUCTS: &[&str] = &["Point", "Node"];
+
+    const TYPES: &[Type] = &[
+        Type::Bool,
+        INT8_T,
+        INT32_T,
+        FLOAT32_T,
+        Type::Void,
+        Type::Ident("Point"),
+        Type::Pointer(&INT8_T),
+        Type::Pointer(&Type::Pointer(&INT32_T)),
+        Type::Array(&INT32_T, 4),
+        Type::Array(&Type::Array(&INT8_T, 2), 3),
+        Type::Vector(&FLOAT32_T, 4),
+    ];
+
+    struct StripLocs;
+
+    impl<'ir> Fold<'ir> for StripLocs {
+        fn fold_stmt(&mut self, stmt: IRStmt<'ir>) -> IRStmt<'ir> {
+            fold::walk_stmt(self, stmt.with_loc(None))
+        }
+
+        fn fold_expr(&mut self, expr: IRExpr<'ir>) -> IRExpr<'ir> {
+            fold::walk_expr(self, expr.with_loc(None))
+        }
+    }
+
+    fn parse<'a>(source: &'a str, arena: &'a Bump) -> HIRStream<'a> {
+        let lexer = arena.alloc(IRLexer::new(source));
+        IRParser::new(lexer, arena).parse_program()
+    }
+
+    fn stream(stmts: Vec<IRStmt<'static>>) -> HIRStream<'static> {
+        let mut ir_gen = IRGenerator::default();
+        for stmt in stmts {
+            ir_gen.gen_ir(stmt);
+        }
+        ir_gen.stream()
+    }
+
+    fn typed_ident() -> impl Strategy<Value = IRTypedIdent<'static>> {
+        (select(VARS), select(TYPES)).prop_map(|(ident, _type)| IRTypedIdent { ident, _type })
+    }
+
+    fn literal() -> impl Strategy<Value = IRExpr<'static>> {
+        let leaf = prop_oneof![
+            any::<bool>().prop_map(|val| IRExpr::Literal(Literal::Bool(val), Type::Bool)),
+            any::<i8>().prop_map(|val| IRExpr::Literal(Literal::Int8(val), INT8_T)),
+            any::<i16>().prop_map(|val| IRExpr::Literal(Literal::Int16(val), INT16_T)),
+            any::<i32>().prop_map(|val| IRExpr::Literal(Literal::Int32(val), INT32_T)),
+            any::<i64>().prop_map(|val| IRExpr::Literal(Literal::Int64(val), INT64_T)),
+            any::<i128>().prop_map(|val| IRExpr::Literal(Literal::Int128(val), INT128_T)),
+            any::<u8>().prop_map(|val| IRExpr::Literal(Literal::UInt8(val), UINT8_T)),
+            any::<u16>().prop_map(|val| IRExpr::Literal(Literal::UInt16(val), UINT16_T)),
+            any::<u32>().prop_map(|val| IRExpr::Literal(Literal::UInt32(val), UINT32_T)),
+            any::<u64>().prop_map(|val| IRExpr::Literal(Literal::UInt64(val), UINT64_T)),
+            any::<u128>().prop_map(|val| IRExpr::Literal(Literal::UInt128(val), UINT128_T)),
+            any::<f32>()
+                .prop_filter("finite", |val| val.is_finite())
+                .prop_map(|val| IRExpr::Literal(Literal::Float32(val), FLOAT32_T)),
+            any::<f64>()
+                .prop_filter("finite", |val| val.is_finite())
+                .prop_map(|val| IRExpr::Literal(Literal::Float64(val), FLOAT64_T)),
+            any::<u8>().prop_map(|char| IRExpr::Literal(Literal::Char(char), INT8_T)),
+            // Text covers multi-byte characters, raw bytes cover invalid UTF-8
+            prop_oneof![
+                "(?s).{0,12}".prop_map(String::into_bytes),
+                prop::collection::vec(any::<u8>(), 0..12),
+            ]
+            .prop_map(|string| {
+                let string: &'static [u8] = Box::leak(string.into_boxed_slice());
+                IRExpr::Literal(
+                    Literal::String(string),
+                    Type::Array(&INT8_T, string.len() as u32),
+                )
+            }),
+        ];
+        leaf.prop_recursive(2, 12, 4, |inner| {
+            prop_oneof![
+                // Arrays of the same literal repeated, so all elements have the same type
+                (inner.clone(), 0..4usize).prop_map(|(elem, len)| {
+                    let IRExpr::Literal(_, elem_type) = elem else {
+                        unreachable!()
+                    };
+                    let elem_type: &'static Type = Box::leak(Box::new(elem_type));
+                    IRExpr::Literal(
+                        Literal::Array(len, vec![elem; len]),
+                        Type::Array(elem_type, len as u32),
+                    )
+                }),
+                (inner, 0..4usize).prop_map(|(elem, lanes)| {
+                    let IRExpr::Literal(_, elem_type) = elem else {
+                        unreachable!()
+                    };
+                    let elem_type: &'static Type = Box::leak(Box::new(elem_type));
+                    IRExpr::Literal(
+                        Literal::Vector(vec![elem; lanes]),
+                        Type::Vector(elem_type, lanes as u32),
+                    )
+                }),
+            ]
+        })
+    }
+
+    fn expr() -> impl Strategy<Value = IRExpr<'static>> {
+        let leaf = prop_oneof![
+            literal(),
+            select(VARS).prop_map(IRExpr::Ident),
+            select(CONSTS).prop_map(IRExpr::Ident),
+            select(TYPES).prop_map(|_type| IRExpr::Alloca(AllocaExpr { _type, loc: None })),
+        ];
+        leaf.prop_recursive(3, 24, 3, |inner| {
+            let boxed = || inner.clone().prop_map(Box::new);
+            prop_oneof![
+                (select(FUNCS), prop::collection::vec(inner.clone(), 0..3))
+                    .prop_map(|(name, args)| IRExpr::Call(CallExpr { name, args, loc: None })),
+                (select(STRUCTS), prop::collection::vec(inner.clone(), 0..3)).prop_map(
+                    |(name, values)| IRExpr::StructInit(StructInitExpr { name, values, loc: None })
+                ),
+                (select(OPERATORS), boxed(), boxed()).prop_map(|(op, left, right)| {
+                    IRExpr::ArithOp(ArithOpExpr { op, values: (left, right), loc: None })
+                }),
+                (select(CMP_OPERATORS), boxed(), boxed()).prop_map(|(op, left, right)| {
+                    IRExpr::CmpOp(CmpOpExpr { op, values: (left, right), loc: None })
+                }),
+                boxed().prop_map(|val| IRExpr::Not(NotExpr { val, loc: None })),
+                (select(CAST_KINDS), select(TYPES), boxed()).prop_map(|(kind, _type, val)| {
+                    IRExpr::Cast(CastExpr { kind, _type, val, loc: None })
+                }),
+                (select(TYPES), boxed())
+                    .prop_map(|(_type, ptr)| IRExpr::Load(LoadExpr { _type, ptr, loc: None })),
+                boxed().prop_map(|val| IRExpr::AddrOf(AddrOfExpr { val, loc: None })),
+                (boxed(), select(VARS))
+                    .prop_map(|(base, field)| IRExpr::Field(FieldExpr { base, field, loc: None })),
+                (boxed(), boxed())
+                    .prop_map(|(base, index)| IRExpr::Index(IndexExpr { base, index, loc: None })),
+                (boxed(), any::<u32>())
+                    .prop_map(|(vector, lane)| IRExpr::Extract(ExtractExpr { vector, lane, loc: None })),
+                (boxed(), boxed(), any::<u32>()).prop_map(|(vector, val, lane)| {
+                    IRExpr::Insert(InsertExpr { vector, val, lane, loc: None })
+                }),
+                (boxed(), boxed(), prop::collection::vec(0..8u32, 0..5)).prop_map(
+                    |(left, right, mask)| IRExpr::Shuffle(ShuffleExpr { values: (left, right), mask, loc: None })
+                ),
+            ]
+        })
+    }
+
+    const OPERATORS: &[Operator] = &[
+        Operator::Add,
+        Operator::Sub,
+        Operator::Mul,
+        Operator::SDiv,
+        Operator::UDiv,
+        Operator::SRem,
+        Operator::URem,
+        Operator::And,
+        Operator::Or,
+        Operator::XOr,
+        Operator::Shl,
+        Operator::LShr,
+        Operator::AShr,
+    ];
+
+    const CMP_OPERATORS: &[CmpOperator] = &[
+        CmpOperator::Eq,
+        CmpOperator::Ne,
+        CmpOperator::SLt,
+        CmpOperator::SLe,
+        CmpOperator::SGt,
+        CmpOperator::SGe,
+        CmpOperator::ULt,
+        CmpOperator::ULe,
+        CmpOperator::UGt,
+        CmpOperator::UGe,
+    ];
+
+    const CAST_KINDS: &[CastKind] = &[
+        CastKind::SExt,
+        CastKind::ZExt,
+        CastKind::Trunc,
+        CastKind::IntToFloat,
+        CastKind::FloatToInt,
+        CastKind::Bitcast,
+    ];
+
+    fn stmt() -> impl Strategy<Value = IRStmt<'static>> {
+        let leaf = prop_oneof![
+            (typed_ident(), expr()).prop_map(|(name, val)| IRStmt::Variable(VarStmt {
+                name,
+                val,
+                is_const: false,
+                loc: None,
+            })),
+            (select(CONSTS), select(TYPES), expr()).prop_map(|(ident, _type, val)| {
+                IRStmt::Variable(VarStmt {
+                    name: IRTypedIdent { ident, _type },
+                    val,
+                    is_const: true,
+                    loc: None,
+                })
+            }),
+            (select(VARS), expr())
+                .prop_map(|(name, val)| IRStmt::Assign(AssignStmt { name, val, loc: None })),
+            select(LABELS).prop_map(|name| IRStmt::Label(LabelStmt { name, loc: None })),
+            expr().prop_map(|ret_val| IRStmt::Return(ReturnStmt { ret_val, loc: None })),
+            expr().prop_map(|exit_code| IRStmt::Exit(ExitStmt { exit_code, loc: None })),
+            select(LABELS).prop_map(|label| IRStmt::Jump(JumpStmt { label, loc: None })),
+            (expr(), select(LABELS), select(LABELS)).prop_map(
+                |(condition, then_label, else_label)| IRStmt::Branch(BranchStmt {
+                    condition,
+                    then_label,
+                    else_label,
+                    loc: None,
+                })
+            ),
+            (expr(), expr())
+                .prop_map(|(val, ptr)| IRStmt::Store(StoreStmt { val, ptr, loc: None })),
+            (select(FUNCS), prop::collection::vec(expr(), 0..3))
+                .prop_map(|(name, args)| IRStmt::Call(CallExpr { name, args, loc: None })),
+            (select(STRUCTS), prop::collection::vec(typed_ident(), 0..3))
+                .prop_map(|(name, fields)| IRStmt::Struct(StructStmt { name, fields, loc: None })),
+            (select(STRUCTS), prop::collection::vec(typed_ident(), 0..3)).prop_map(
+                |(name, variants)| IRStmt::Union(UnionStmt { name, variants, loc: None })
+            ),
+            (select(FUNCS), select(TYPES), prop::collection::vec(typed_ident(), 0..3)).prop_map(
+                |(ident, _type, args)| IRStmt::DeclaredFunction(DeclFuncStmt {
+                    name: IRTypedIdent { ident, _type },
+                    args,
+                    loc: None,
+                })
+            ),
+        ];
+        leaf.prop_recursive(2, 16, 4, |inner| {
+            let block = || {
+                prop::collection::vec(inner.clone(), 0..4).prop_map(|stmts| BlockStmt { stmts })
+            };
+            prop_oneof![
+                block().prop_map(IRStmt::Entry),
+                (
+                    select(FUNCS),
+                    select(TYPES),
+                    prop::collection::vec(typed_ident(), 0..3),
+                    block()
+                )
+                    .prop_map(|(ident, _type, args, block)| IRStmt::Function(FuncStmt {
+                        name: IRTypedIdent { ident, _type },
+                        args,
+                        block,
+                        loc: None,
+                    })),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(stmts in prop::collection::vec(stmt(), 0..6)) {
+            let stream = stream(stmts);
+            let source = stream.to_string();
+
+            let arena = Bump::new();
+            let mut parsed = parse(&source, &arena);
+            parsed.stream = parsed
+                .stream
+                .into_iter()
+                .map(|stmt| StripLocs.fold_stmt(stmt))
+                .collect();
+
+            prop_assert_eq!(&parsed.stream, &stream.stream);
+            prop_assert_eq!(&parsed.types, &stream.types);
+            prop_assert_eq!(parsed.to_string(), source);
+        }
+    }
+
+    #[test]
+    fn test_print() {
+        let stream = stream(vec![
+            IRStmt::DeclaredFunction(DeclFuncStmt {
+                name: IRTypedIdent {
+                    ident: "puts",
+                    _type: INT32_T,
+                },
+                args: vec![IRTypedIdent {
+                    ident: "s",
+                    _type: Type::Pointer(&INT8_T),
+                }],
+                loc: None,
+            }),
+            IRStmt::Jump(JumpStmt {
+                label: "end",
+                loc: None,
+            }),
+            IRStmt::Return(ReturnStmt {
+                ret_val: IRExpr::Ident("x"),
+                loc: None,
+            }),
+            IRStmt::Exit(ExitStmt {
+                exit_code: IRExpr::Literal(Literal::Char(b'a'), INT8_T),
+                loc: None,
+            }),
+            IRStmt::Return(ReturnStmt {
+                ret_val: IRExpr::Literal(Literal::Float32(1.0), FLOAT32_T),
+                loc: None,
+            }),
+        ]);
+        assert_eq!(
+            stream.to_string(),
+            "decl func @puts($s *i8) i32\njmp 'end\nret %x\nexit l{'a':i8}\nret l{1.0:f32}"
+        );
+    }
+
+    #[test]
+    fn test_literals() {
+        let arena = Bump::new();
+        let stream = parse(
+            "ret l{[[1, -2], [l{3:i8}, 4]]:[[i8; 2]; 2]}
+            ret l{[1.5, 2; 2]:[f64; 2]}
+            ret l{<true, l{0:i1}>:<i1; 2>}
+            ret l{-170141183460469231731687303715884105728:i128}
+            ret l{255:u8}
+            ret l{340282366920938463463374607431768211455:u128}",
+            &arena,
+        );
+        let lits = stream
+            .stream
+            .into_iter()
+            .map(|stmt| match StripLocs.fold_stmt(stmt) {
+                IRStmt::Return(ReturnStmt { ret_val: IRExpr::Literal(lit, _), .. }) => lit,
+                stmt => panic!("Expected a literal, received {stmt}"),
+            })
+            .collect::<Vec<_>>();
+        let int8 = |val| IRExpr::Literal(Literal::Int8(val), INT8_T);
+        assert_eq!(
+            lits,
+            [
+                Literal::Array(
+                    2,
+                    vec![
+                        IRExpr::Literal(
+                            Literal::Array(2, vec![int8(1), int8(-2)]),
+                            Type::Array(&INT8_T, 2)
+                        ),
+                        IRExpr::Literal(
+                            Literal::Array(2, vec![int8(3), int8(4)]),
+                            Type::Array(&INT8_T, 2)
+                        ),
+                    ]
+                ),
+                Literal::Array(
+                    2,
+                    vec![
+                        IRExpr::Literal(Literal::Float64(1.5), FLOAT64_T),
+                        IRExpr::Literal(Literal::Float64(2.0), FLOAT64_T),
+                    ]
+                ),
+                Literal::Vector(vec![
+                    IRExpr::Literal(Literal::Bool(true), Type::Bool),
+                    IRExpr::Literal(Literal::Bool(false), Type::Bool),
+                ]),
+                Literal::Int128(i128::MIN),
+                Literal::UInt8(255),
+                Literal::UInt128(u128::MAX),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Integer literal 300 does not fit into i8")]
+    fn test_literal_out_of_range() {
+        parse("ret l{300:i8}", &Bump::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "Integer literal -1 does not fit into u8")]
+    fn test_unsigned_out_of_range() {
+        parse("ret l{-1:u8}", &Bump::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "Vector literal with 3 elements does not match the type <i32; 4>")]
+    fn test_vector_lanes() {
+        parse("ret l{<1, 2, 3>:<i32; 4>}", &Bump::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "Array literal with 3 elements and length 3 does not match the type [i32; 2]")]
+    fn test_array_literal_mismatch() {
+        parse("ret l{[1, 2, 3]:[i32; 2]}", &Bump::new());
+    }
+}
@@ -36,6 +36,9 @@ pub enum Token<'tok> {
     /// ; - semicolon is used in arrays to seperate type and size
     #[token(";")]
     Semicolon,
+    /// * - asterisk marks a pointer type
+    #[token("*")]
+    Asterisk,
 
     /// Brackets
     /// ( - left parenthesis
@@ -56,6 +59,12 @@ pub enum Token<'tok> {
     /// } - right curly brackets
     #[token("}")]
     RCurly,
+    /// < - left angle brackets
+    #[token("<")]
+    LAngle,
+    /// > - right angle brackets
+    #[token(">")]
+    RAngle,
 
     // --keywords--
     /// indicates the definition of a struct
@@ -85,9 +94,40 @@ pub enum Token<'tok> {
     /// Jump to a label
     #[token("jmp")]
     Jump,
+    /// Jump to one of two labels depending on a condition
+    #[token("br")]
+    Branch,
     /// Exit the program
     #[token("exit")]
     Exit,
+    // Memory Operations
+    /// Reserve stack memory
+    #[token("alloca")]
+    Alloca,
+    /// Read from a pointer
+    #[token("load")]
+    Load,
+    /// Write to a pointer
+    #[token("store")]
+    Store,
+    /// Take the address of a variable
+    #[token("addr-of")]
+    AddrOf,
+    /// Access a field of a struct or union
+    #[token("getfield")]
+    GetField,
+    /// Access an element of an array
+    #[token("getelem")]
+    GetElem,
+    /// Read a lane of a vector
+    #[token("extractelem")]
+    ExtractElem,
+    /// Replace a lane of a vector
+    #[token("insertelem")]
+    InsertElem,
+    /// Build a vector from the lanes of two vectors
+    #[token("shuffle")]
+    Shuffle,
     // Arithmetic Operations
     /// Addition
     #[token("add")]
@@ -98,12 +138,44 @@ pub enum Token<'tok> {
     /// Multiplication
     #[token("mul")]
     Mul,
-    /// Division
-    #[token("div")]
-    Div,
-    /// Modulo operator
-    #[token("mod")]
-    Mod,
+    /// Signed division
+    #[token("sdiv")]
+    SDiv,
+    /// Unsigned division
+    #[token("udiv")]
+    UDiv,
+    /// Signed remainder
+    #[token("srem")]
+    SRem,
+    /// Unsigned remainder
+    #[token("urem")]
+    URem,
+    // Bitwise Operations
+    /// Bitwise and
+    #[token("and")]
+    And,
+    /// Bitwise or
+    #[token("or")]
+    Or,
+    /// Bitwise exclusive or
+    #[token("xor")]
+    XOr,
+    /// Bitwise not
+    #[token("not")]
+    Not,
+    /// Shift left
+    #[token("shl")]
+    Shl,
+    /// Logical shift right
+    #[token("lshr")]
+    LShr,
+    /// Arithmetic shift right
+    #[token("ashr")]
+    AShr,
+    // Comparisons
+    /// Compare two values
+    #[token("cmp")]
+    Cmp,
 
     #[regex(r#""(?:\\.|[^\\"])*""#)]
     LitString(&'tok str),
@@ -111,10 +183,10 @@ pub enum Token<'tok> {
     #[regex(r"-?[0-9]+")]
     LitInt(&'tok str),
 
-    #[regex("-?[0-9]+\\.[0-9]+")]
+    #[regex(r"-?[0-9]+(?:\.[0-9]+(?:[eE][+-]?[0-9]+)?|[eE][+-]?[0-9]+)")]
     LitFloat(&'tok str),
 
-    #[regex(r#"'[^\\']'"#)]
+    #[regex(r#"'(?:[^\\']|\\[^x]|\\x[0-9a-fA-F]{2})'"#)]
     LitChar(&'tok str),
 
     #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*")]
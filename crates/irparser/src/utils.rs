@@ -10,9 +10,10 @@ macro_rules! expect_tok {
     }};
 }
 
+/// Panics with the message and the location of the current token of the parser
 #[macro_export]
 macro_rules! parser_error {
-    ($($arg:tt)+) => {{
-        panic!("Parser Error: {}", format_args!($($arg)+));
+    ($parser:expr, $($arg:tt)+) => {{
+        panic!("Parser Error at {}: {}", $parser.cur_loc(), format_args!($($arg)+));
     }};
 }
@@ -0,0 +1,217 @@
+//! Control-flow graphs for CHIR functions
+//!
+//! A [Cfg] splits the body of a function into basic blocks. A new block starts
+//! at every label and after every statement that transfers control (`ret`,
+//! `exit`, `jmp` and `br`). Blocks are connected by [edges](Edge) that describe
+//! which block can be executed after another one.
+
+mod tests;
+
+use std::collections::HashMap;
+
+use citadel_frontend::ir::{BlockStmt, FuncStmt, IRStmt, Ident};
+
+/// Index of a block in [Cfg::blocks]
+pub type BlockId = usize;
+
+#[derive(Debug)]
+pub struct Cfg<'c> {
+    /// The first block is the entry of the function
+    pub blocks: Vec<BasicBlock<'c>>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Debug)]
+pub struct BasicBlock<'c> {
+    pub id: BlockId,
+    /// The label the block starts with
+    pub label: Option<Ident<'c>>,
+    /// Statements of the block without the label
+    pub stmts: &'c [IRStmt<'c>],
+    pub preds: Vec<BlockId>,
+    pub succs: Vec<BlockId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub from: BlockId,
+    pub to: BlockId,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    Jump,
+    /// Taken if the condition of a branch is non-zero
+    Then,
+    /// Taken if the condition of a branch is zero
+    Else,
+    /// The block does not end with a jump, so execution
+    /// continues with the block that follows it
+    Fallthrough,
+}
+
+impl<'c> Cfg<'c> {
+    pub fn build(func: &'c FuncStmt<'c>) -> Self {
+        Self::from_block(&func.block)
+    }
+
+    /// Builds the graph of a block of statements, like the body of the entry
+    pub fn from_block(block: &'c BlockStmt<'c>) -> Self {
+        let mut cfg = Self {
+            blocks: Vec::new(),
+            edges: Vec::new(),
+        };
+        cfg.split_blocks(&block.stmts);
+        cfg.connect_blocks();
+        cfg
+    }
+
+    fn split_blocks(&mut self, stmts: &'c [IRStmt<'c>]) {
+        // Label and index of the first statement of the block that is currently open.
+        // No block is open after a statement that transfers control
+        let mut open = Some((None, 0));
+        for (i, stmt) in stmts.iter().enumerate() {
+            match stmt {
+                IRStmt::Label(node) => {
+                    if let Some((label, start)) = open {
+                        // An empty entry block is merged with the labeled one
+                        if label.is_some() || start < i {
+                            self.push_block(label, &stmts[start..i]);
+                        }
+                    }
+                    open = Some((Some(node.name), i + 1));
+                }
+                stmt => {
+                    let (label, start) = *open.get_or_insert((None, i));
+                    if is_terminator(stmt) {
+                        self.push_block(label, &stmts[start..=i]);
+                        open = None;
+                    }
+                }
+            }
+        }
+        if let Some((label, start)) = open {
+            if label.is_some() || start < stmts.len() || self.blocks.is_empty() {
+                self.push_block(label, &stmts[start..]);
+            }
+        }
+    }
+
+    fn push_block(&mut self, label: Option<Ident<'c>>, stmts: &'c [IRStmt<'c>]) {
+        self.blocks.push(BasicBlock {
+            id: self.blocks.len(),
+            label,
+            stmts,
+            preds: Vec::new(),
+            succs: Vec::new(),
+        });
+    }
+
+    fn connect_blocks(&mut self) {
+        let labels: HashMap<Ident, BlockId> = self
+            .blocks
+            .iter()
+            .filter_map(|block| Some((block.label?, block.id)))
+            .collect();
+        let block_of = |label| {
+            *labels
+                .get(label)
+                .unwrap_or_else(|| panic!("Could not find label with name {label:?}"))
+        };
+        for block in &self.blocks {
+            let mut edge = |to, kind| {
+                self.edges.push(Edge {
+                    from: block.id,
+                    to,
+                    kind,
+                })
+            };
+            match block.stmts.last() {
+                Some(IRStmt::Jump(jump)) => edge(block_of(jump.label), EdgeKind::Jump),
+                Some(IRStmt::Branch(branch)) => {
+                    edge(block_of(branch.then_label), EdgeKind::Then);
+                    edge(block_of(branch.else_label), EdgeKind::Else);
+                }
+                Some(IRStmt::Return(_) | IRStmt::Exit(_)) => (),
+                _ if block.id + 1 < self.blocks.len() => {
+                    edge(block.id + 1, EdgeKind::Fallthrough)
+                }
+                _ => (),
+            }
+        }
+        for edge in &self.edges {
+            self.blocks[edge.from].succs.push(edge.to);
+            self.blocks[edge.to].preds.push(edge.from);
+        }
+    }
+
+    /// Returns the blocks that cannot be reached from the entry block
+    pub fn unreachable_blocks(&self) -> Vec<BlockId> {
+        let mut reached = vec![false; self.blocks.len()];
+        let mut stack = vec![0];
+        while let Some(id) = stack.pop() {
+            if !reached[id] {
+                reached[id] = true;
+                stack.extend(&self.blocks[id].succs);
+            }
+        }
+        (0..self.blocks.len()).filter(|id| !reached[*id]).collect()
+    }
+
+    pub fn fallthrough_edges(&self) -> impl Iterator<Item = &Edge> {
+        self.edges
+            .iter()
+            .filter(|edge| edge.kind == EdgeKind::Fallthrough)
+    }
+
+    /// Renders the graph in the Graphviz DOT format. Unreachable blocks are
+    /// drawn grey and fallthrough edges dashed
+    pub fn to_dot(&self, name: &str) -> String {
+        let unreachable = self.unreachable_blocks();
+        let mut dot = format!("digraph \"{name}\" {{\n    node [shape=box];\n");
+        for block in &self.blocks {
+            let mut lines = vec![match block.label {
+                Some(label) => format!("bb{} '{label}:", block.id),
+                None => format!("bb{}:", block.id),
+            }];
+            lines.extend(block.stmts.iter().map(|stmt| stmt.to_string()));
+            let text = lines
+                .iter()
+                .map(|line| escape_dot(line))
+                .collect::<Vec<String>>()
+                .join("\\l");
+            let style = if unreachable.contains(&block.id) {
+                ", style=filled, fillcolor=lightgrey"
+            } else {
+                ""
+            };
+            dot.push_str(&format!(
+                "    bb{} [label=\"{text}\\l\"{style}];\n",
+                block.id
+            ));
+        }
+        for edge in &self.edges {
+            let attrs = match edge.kind {
+                EdgeKind::Jump => "",
+                EdgeKind::Then => " [label=\"then\"]",
+                EdgeKind::Else => " [label=\"else\"]",
+                EdgeKind::Fallthrough => " [style=dashed]",
+            };
+            dot.push_str(&format!("    bb{} -> bb{}{attrs};\n", edge.from, edge.to));
+        }
+        dot.push('}');
+        dot
+    }
+}
+
+fn is_terminator(stmt: &IRStmt) -> bool {
+    matches!(
+        stmt,
+        IRStmt::Return(_) | IRStmt::Exit(_) | IRStmt::Jump(_) | IRStmt::Branch(_)
+    )
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,69 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests {
+    use bumpalo::Bump;
+    use citadel_frontend::ir::IRStmt;
+    use citadel_irparser::{IRLexer, IRParser};
+
+    use crate::cfg::{Cfg, Edge, EdgeKind};
+
+    #[test]
+    fn test_cfg() {
+        let lexer = IRLexer::new(
+            "func @main() i32 {
+                ?i i32 = l{0:i32}
+                'loop:
+                br cmp slt %i, l{10:i32}, 'body, 'end
+                'body:
+                %i = add %i, l{1:i32}
+                jmp 'loop
+                ret l{1:i32}
+                'end:
+                ret %i
+            }",
+        );
+        let arena = Bump::new();
+        let mut parser = IRParser::new(&lexer, &arena);
+        let ir_stream = parser.parse_program();
+        let IRStmt::Function(func) = &ir_stream.stream[0] else {
+            panic!("Expected a function");
+        };
+        let cfg = Cfg::build(func);
+
+        assert_eq!(cfg.blocks.len(), 5);
+        assert_eq!(cfg.blocks[1].label, Some("loop"));
+        assert_eq!(cfg.blocks[1].preds, vec![0, 2]);
+        assert_eq!(cfg.blocks[1].succs, vec![2, 4]);
+        assert_eq!(cfg.blocks[2].stmts.len(), 2);
+        assert_eq!(cfg.unreachable_blocks(), vec![3]);
+        assert_eq!(
+            cfg.fallthrough_edges().collect::<Vec<&Edge>>(),
+            vec![&Edge {
+                from: 0,
+                to: 1,
+                kind: EdgeKind::Fallthrough
+            }]
+        );
+
+        let dot = cfg.to_dot("main");
+        assert!(dot.starts_with("digraph \"main\" {\n"));
+        assert!(dot.contains("    bb1 [label=\"bb1 'loop:\\lbr cmp slt %i, l{10:i32}, 'body, 'end\\l\"];\n"));
+        assert!(dot.contains("    bb1 -> bb2 [label=\"then\"];\n"));
+        assert!(dot.contains("    bb0 -> bb1 [style=dashed];\n"));
+        assert!(dot.contains("bb3 [label=\"bb3:\\lret l{1:i32}\\l\", style=filled, fillcolor=lightgrey];"));
+    }
+
+    #[test]
+    fn test_cfg_empty() {
+        let lexer = IRLexer::new("func @main() i32 {}");
+        let arena = Bump::new();
+        let mut parser = IRParser::new(&lexer, &arena);
+        let ir_stream = parser.parse_program();
+        let IRStmt::Function(func) = &ir_stream.stream[0] else {
+            panic!("Expected a function");
+        };
+        let cfg = Cfg::build(func);
+        assert_eq!(cfg.blocks.len(), 1);
+        assert!(cfg.edges.is_empty());
+    }
+}
@@ -0,0 +1,620 @@
+//! Lowering of an [HIRStream] to a CLIR [Module]
+//!
+//! Nested expressions are flattened into instructions that store their result
+//! in a new virtual register. Variables that are structs, unions or arrays, or
+//! whose address is taken, live in stack memory created by an `alloca` and are
+//! accessed through loads and stores. All other variables are kept in a register.
+//!
+//! The input is expected to be valid, which can be ensured by running the
+//! [verifier](citadel_frontend::ir::verifier) first. Invalid input causes a panic.
+
+use std::collections::{HashMap, HashSet};
+
+use bumpalo::Bump;
+use citadel_frontend::ir::{
+    irgen::{HIRStream, TypeTable},
+    visit::{walk_addr_of, Visitor},
+    AddrOfExpr, BlockStmt, CallExpr, IRExpr, IRStmt, IRTypedIdent, Ident, Literal, Type, INT1_T, INT64_T,
+};
+
+use super::*;
+
+/// Lowers the stream to CLIR. Types that have to be created during
+/// lowering, like pointers to temporary structs, are allocated in the arena
+pub fn lower<'l>(stream: &'l HIRStream<'l>, arena: &'l Bump) -> Module<'l> {
+    let mut functions = HashMap::new();
+    let mut globals = HashMap::new();
+    for stmt in &stream.stream {
+        match stmt {
+            IRStmt::Function(func) => {
+                functions.insert(func.name.ident, func.name._type);
+            }
+            IRStmt::DeclaredFunction(func) => {
+                functions.insert(func.name.ident, func.name._type);
+            }
+            IRStmt::Variable(var) => {
+                globals.insert(var.name.ident, &var.name._type);
+            }
+            _ => (),
+        }
+    }
+
+    let mut module = Module {
+        globals: Vec::new(),
+        declarations: Vec::new(),
+        entry: None,
+        functions: Vec::new(),
+        types: &stream.types,
+    };
+    let lower_function = |name, args, ret_type, block| {
+        FunctionLowering {
+            arena,
+            types: &stream.types,
+            functions: &functions,
+            globals: &globals,
+            variables: HashMap::new(),
+            labels: HashMap::new(),
+            vregs: Vec::new(),
+            blocks: Vec::new(),
+            current: BlockId(0),
+        }
+        .lower(name, args, ret_type, block)
+    };
+    for stmt in &stream.stream {
+        match stmt {
+            IRStmt::Entry(block) => {
+                module.entry = Some(lower_function(ENTRY_NAME, &[], Type::Void, block));
+            }
+            IRStmt::Function(func) => module.functions.push(lower_function(
+                func.name.ident,
+                &func.args,
+                func.name._type,
+                &func.block,
+            )),
+            IRStmt::DeclaredFunction(func) => module.declarations.push(Declaration {
+                name: func.name.ident,
+                args: func.args.iter().map(|arg| arg._type).collect(),
+                ret_type: func.name._type,
+            }),
+            IRStmt::Variable(var) => module.globals.push(Global {
+                name: var.name.ident,
+                _type: var.name._type,
+                val: match &var.val {
+                    IRExpr::Literal(lit, _) => lit.clone(),
+                    val => panic!("Global variables need to be initialized with a literal, received {val} instead"),
+                },
+            }),
+            IRStmt::Struct(_) | IRStmt::Union(_) => (),
+            stmt => panic!("{stmt} cannot be used outside of a function"),
+        }
+    }
+    module
+}
+
+/// Where the value of a variable is stored
+#[derive(Clone, Copy)]
+enum Var<'l> {
+    Reg(VReg),
+    /// The register holds a pointer to the stack memory of the variable
+    Slot(VReg, &'l Type<'l>),
+}
+
+/// A block whose terminator may not have been generated yet
+struct PartialBlock<'l> {
+    label: Option<Ident<'l>>,
+    insts: Vec<Inst<'l>>,
+    terminator: Option<Terminator<'l>>,
+}
+
+struct FunctionLowering<'a, 'l> {
+    arena: &'l Bump,
+    types: &'l TypeTable<'l>,
+    /// Return types of all functions
+    functions: &'a HashMap<Ident<'l>, Type<'l>>,
+    globals: &'a HashMap<Ident<'l>, &'l Type<'l>>,
+
+    variables: HashMap<Ident<'l>, Var<'l>>,
+    labels: HashMap<Ident<'l>, BlockId>,
+    vregs: Vec<Type<'l>>,
+    blocks: Vec<PartialBlock<'l>>,
+    current: BlockId,
+}
+
+impl<'a, 'l> FunctionLowering<'a, 'l> {
+    fn lower(
+        mut self,
+        name: Ident<'l>,
+        args: &'l [IRTypedIdent<'l>],
+        ret_type: Type<'l>,
+        block: &'l BlockStmt<'l>,
+    ) -> Function<'l> {
+        let addr_taken = addr_taken(&block.stmts);
+        let arg_regs: Vec<VReg> = args.iter().map(|arg| self.new_vreg(arg._type)).collect();
+        self.new_block(None);
+        let starts = self.create_blocks(&block.stmts);
+
+        for (arg, reg) in args.iter().zip(&arg_regs) {
+            let var = if is_memory(&arg._type) || addr_taken.contains(arg.ident) {
+                let ptr = self.alloca(&arg._type);
+                self.emit(Inst::Store {
+                    val: Value::Reg(*reg),
+                    ptr: Value::Reg(ptr),
+                });
+                Var::Slot(ptr, &arg._type)
+            } else {
+                Var::Reg(*reg)
+            };
+            self.variables.insert(arg.ident, var);
+        }
+
+        for (stmt, start) in block.stmts.iter().zip(starts) {
+            if let Some(start) = start {
+                if self.cur_block().terminator.is_none() {
+                    self.terminate(Terminator::Jump(start));
+                }
+                self.current = start;
+            }
+            self.lower_stmt(stmt, &addr_taken);
+        }
+        if self.cur_block().terminator.is_none() {
+            self.terminate(match ret_type {
+                Type::Void => Terminator::Return(None),
+                _ => Terminator::Unreachable,
+            });
+        }
+
+        Function {
+            name,
+            args: arg_regs,
+            ret_type,
+            vregs: self.vregs,
+            blocks: self
+                .blocks
+                .into_iter()
+                .enumerate()
+                .map(|(i, block)| Block {
+                    id: BlockId(i as u32),
+                    label: block.label,
+                    insts: block.insts,
+                    terminator: block.terminator.expect("Every block should be terminated"),
+                })
+                .collect(),
+        }
+    }
+
+    /// Creates a block for every label and for statements following
+    /// a terminator. Returns the block that starts at each statement
+    fn create_blocks(&mut self, stmts: &'l [IRStmt<'l>]) -> Vec<Option<BlockId>> {
+        let mut starts = Vec::with_capacity(stmts.len());
+        let mut terminated = false;
+        for stmt in stmts {
+            let start = match stmt {
+                IRStmt::Label(label) => {
+                    let block = self.new_block(Some(label.name));
+                    self.labels.insert(label.name, block);
+                    Some(block)
+                }
+                _ if terminated => Some(self.new_block(None)),
+                _ => None,
+            };
+            terminated = matches!(
+                stmt,
+                IRStmt::Return(_) | IRStmt::Exit(_) | IRStmt::Jump(_) | IRStmt::Branch(_)
+            );
+            starts.push(start);
+        }
+        starts
+    }
+
+    fn lower_stmt(&mut self, stmt: &'l IRStmt<'l>, addr_taken: &HashSet<Ident<'l>>) {
+        match stmt {
+            IRStmt::Variable(node) => {
+                let _type = &node.name._type;
+                let var = if is_memory(_type) || addr_taken.contains(node.name.ident) {
+                    let ptr = self.alloca(_type);
+                    let val = self.lower_expr(&node.val);
+                    self.emit(Inst::Store {
+                        val,
+                        ptr: Value::Reg(ptr),
+                    });
+                    Var::Slot(ptr, _type)
+                } else {
+                    let val = self.lower_expr(&node.val);
+                    let dst = self.new_vreg(*_type);
+                    self.emit(Inst::Copy { dst, val });
+                    Var::Reg(dst)
+                };
+                self.variables.insert(node.name.ident, var);
+            }
+            IRStmt::Assign(assign) => {
+                let val = self.lower_expr(&assign.val);
+                let inst = match self.variables.get(assign.name) {
+                    Some(Var::Reg(dst)) => Inst::Copy { dst: *dst, val },
+                    Some(Var::Slot(ptr, _)) => Inst::Store {
+                        val,
+                        ptr: Value::Reg(*ptr),
+                    },
+                    None => Inst::Store {
+                        val,
+                        ptr: Value::Global(assign.name),
+                    },
+                };
+                self.emit(inst);
+            }
+            IRStmt::Label(_) => (),
+            IRStmt::Return(ret) => {
+                let val = self.lower_expr(&ret.ret_val);
+                self.terminate(Terminator::Return(Some(val)));
+            }
+            IRStmt::Exit(exit) => {
+                let val = self.lower_expr(&exit.exit_code);
+                self.terminate(Terminator::Exit(val));
+            }
+            IRStmt::Jump(jump) => {
+                let block = self.label(jump.label);
+                self.terminate(Terminator::Jump(block));
+            }
+            IRStmt::Branch(branch) => {
+                let cond = self.lower_expr(&branch.condition);
+                let then_block = self.label(branch.then_label);
+                let else_block = self.label(branch.else_label);
+                self.terminate(Terminator::Branch {
+                    cond,
+                    then_block,
+                    else_block,
+                });
+            }
+            IRStmt::Call(call) => {
+                // Builtin functions like `print` are not declared and do not return a value
+                let ret_type = self.functions.get(call.name).copied().unwrap_or(Type::Void);
+                self.lower_call(call, ret_type);
+            }
+            IRStmt::Store(store) => {
+                let val = self.lower_expr(&store.val);
+                let ptr = self.lower_expr(&store.ptr);
+                self.emit(Inst::Store { val, ptr });
+            }
+            IRStmt::Entry(_)
+            | IRStmt::DeclaredFunction(_)
+            | IRStmt::Function(_)
+            | IRStmt::Struct(_)
+            | IRStmt::Union(_) => panic!("{stmt} cannot be used inside of a function"),
+        }
+    }
+
+    fn lower_expr(&mut self, expr: &'l IRExpr<'l>) -> Value<'l> {
+        match expr {
+            IRExpr::Literal(Literal::Array(_, elems) | Literal::Vector(elems), _type) => {
+                let elem_type = element_of(_type);
+                let ptr = Value::Reg(self.alloca(_type));
+                for (i, elem) in elems.iter().enumerate() {
+                    let val = self.lower_expr(elem);
+                    let index = Value::Const(Literal::Int64(i as i64), INT64_T);
+                    let elem_ptr = self.elem_ptr(ptr.clone(), index, elem_type);
+                    self.emit(Inst::Store { val, ptr: elem_ptr });
+                }
+                self.load(ptr, *_type)
+            }
+            IRExpr::Literal(lit, _type) => Value::Const(lit.clone(), *_type),
+            IRExpr::Ident(ident) => match self.variables.get(ident).copied() {
+                Some(Var::Reg(reg)) => Value::Reg(reg),
+                Some(Var::Slot(ptr, _type)) => self.load(Value::Reg(ptr), *_type),
+                None => {
+                    let _type = *self
+                        .globals
+                        .get(ident)
+                        .unwrap_or_else(|| panic!("Could not find ident with name {ident:?}"));
+                    self.load(Value::Global(ident), *_type)
+                }
+            },
+            IRExpr::Call(call) => {
+                let ret_type = *self
+                    .functions
+                    .get(call.name)
+                    .unwrap_or_else(|| panic!("Could not find function with name {:?}", call.name));
+                match self.lower_call(call, ret_type) {
+                    Some(dst) => Value::Reg(dst),
+                    None => panic!("The result of {call} cannot be used as a value since it returns void"),
+                }
+            }
+            IRExpr::ArithOp(op) => {
+                let lhs = self.lower_expr(&op.values.0);
+                let rhs = self.lower_expr(&op.values.1);
+                let dst = self.new_vreg(self.value_type(&lhs));
+                self.emit(Inst::Binary {
+                    dst,
+                    op: op.op,
+                    lhs,
+                    rhs,
+                });
+                Value::Reg(dst)
+            }
+            IRExpr::CmpOp(op) => {
+                let lhs = self.lower_expr(&op.values.0);
+                let rhs = self.lower_expr(&op.values.1);
+                let dst = self.new_vreg(INT1_T);
+                self.emit(Inst::Cmp {
+                    dst,
+                    op: op.op,
+                    lhs,
+                    rhs,
+                });
+                Value::Reg(dst)
+            }
+            IRExpr::Not(not) => {
+                let val = self.lower_expr(&not.val);
+                let dst = self.new_vreg(self.value_type(&val));
+                self.emit(Inst::Not { dst, val });
+                Value::Reg(dst)
+            }
+            IRExpr::Cast(cast) => {
+                let val = self.lower_expr(&cast.val);
+                let dst = self.new_vreg(cast._type);
+                self.emit(Inst::Cast {
+                    dst,
+                    kind: cast.kind,
+                    val,
+                });
+                Value::Reg(dst)
+            }
+            IRExpr::Alloca(alloca) => Value::Reg(self.alloca(&alloca._type)),
+            IRExpr::Load(load) => {
+                let ptr = self.lower_expr(&load.ptr);
+                self.load(ptr, load._type)
+            }
+            IRExpr::AddrOf(addr_of) => self.lower_place(&addr_of.val).0,
+            IRExpr::Field(_) | IRExpr::Index(_) => {
+                let (ptr, _type) = self.lower_place(expr);
+                self.load(ptr, *_type)
+            }
+            IRExpr::Extract(extract) => {
+                let (ptr, _type) = self.spill(&extract.vector);
+                let elem_type = element_of(_type);
+                let lane_ptr = self.lane_ptr(ptr, extract.lane, elem_type);
+                self.load(lane_ptr, *elem_type)
+            }
+            IRExpr::Insert(insert) => {
+                let (ptr, _type) = self.spill(&insert.vector);
+                let val = self.lower_expr(&insert.val);
+                let lane_ptr = self.lane_ptr(ptr.clone(), insert.lane, element_of(_type));
+                self.emit(Inst::Store { val, ptr: lane_ptr });
+                self.load(ptr, *_type)
+            }
+            IRExpr::Shuffle(shuffle) => {
+                let (lhs, _type) = self.spill(&shuffle.values.0);
+                let (rhs, _) = self.spill(&shuffle.values.1);
+                let (elem_type, lanes) = match _type {
+                    Type::Vector(elem_type, lanes) => (*elem_type, *lanes),
+                    _type => panic!("Cannot shuffle {_type}"),
+                };
+                let res_type = &*self.arena.alloc(Type::Vector(elem_type, shuffle.mask.len() as u32));
+                let res = Value::Reg(self.alloca(res_type));
+                for (i, &lane) in shuffle.mask.iter().enumerate() {
+                    let src_ptr = match lane < lanes {
+                        true => self.lane_ptr(lhs.clone(), lane, elem_type),
+                        false => self.lane_ptr(rhs.clone(), lane - lanes, elem_type),
+                    };
+                    let val = self.load(src_ptr, *elem_type);
+                    let dst_ptr = self.lane_ptr(res.clone(), i as u32, elem_type);
+                    self.emit(Inst::Store { val, ptr: dst_ptr });
+                }
+                self.load(res, *res_type)
+            }
+            IRExpr::StructInit(init) => {
+                let _type = &*self.arena.alloc(Type::Ident(init.name));
+                let ptr = Value::Reg(self.alloca(_type));
+                let fields = &self
+                    .types
+                    .get(init.name)
+                    .unwrap_or_else(|| panic!("Could not find type with the name {}", init.name))
+                    .1;
+                for (val, field) in init.values.iter().zip(fields) {
+                    let val = self.lower_expr(val);
+                    let field_ptr = self.field_ptr(ptr.clone(), init.name, field);
+                    self.emit(Inst::Store {
+                        val,
+                        ptr: field_ptr,
+                    });
+                }
+                self.load(ptr, *_type)
+            }
+        }
+    }
+
+    /// Returns a pointer to a variable, field or element and the type it points to
+    fn lower_place(&mut self, expr: &'l IRExpr<'l>) -> (Value<'l>, &'l Type<'l>) {
+        match expr {
+            IRExpr::Ident(ident) => match self.variables.get(ident).copied() {
+                Some(Var::Slot(ptr, _type)) => (Value::Reg(ptr), _type),
+                Some(Var::Reg(_)) => panic!("The variable {ident} is not stored in memory"),
+                None => {
+                    let _type = *self
+                        .globals
+                        .get(ident)
+                        .unwrap_or_else(|| panic!("Could not find ident with name {ident:?}"));
+                    (Value::Global(ident), _type)
+                }
+            },
+            IRExpr::Field(field) => {
+                let (base, _type) = self.lower_base(&field.base);
+                let name = match _type {
+                    Type::Ident(name) => name,
+                    _type => panic!("Cannot access field {} of {_type}", field.field),
+                };
+                let field = self
+                    .types
+                    .get(name)
+                    .and_then(|(_, fields)| fields.iter().find(|f| f.ident == field.field))
+                    .unwrap_or_else(|| panic!("Type {name} has no field with the name {}", field.field));
+                (self.field_ptr(base, name, field), &field._type)
+            }
+            IRExpr::Index(index) => {
+                let (base, _type) = self.lower_base(&index.base);
+                let elem_type = element_of(_type);
+                let index = self.lower_expr(&index.index);
+                (self.elem_ptr(base, index, elem_type), elem_type)
+            }
+            expr => panic!("Expected a variable, field or element, received {expr} instead"),
+        }
+    }
+
+    /// Returns a pointer to the struct or array that is accessed by a field or element
+    /// expression. The base is either the aggregate itself or a pointer to it
+    fn lower_base(&mut self, expr: &'l IRExpr<'l>) -> (Value<'l>, &'l Type<'l>) {
+        let is_place = match expr {
+            IRExpr::Ident(ident) => !matches!(self.variables.get(ident), Some(Var::Reg(_))),
+            IRExpr::Field(_) | IRExpr::Index(_) => true,
+            _ => false,
+        };
+        if is_place {
+            let (ptr, _type) = self.lower_place(expr);
+            return match _type {
+                Type::Pointer(inner) => (self.load(ptr, *_type), inner),
+                _ => (ptr, _type),
+            };
+        }
+        let val = self.lower_expr(expr);
+        match self.value_type(&val) {
+            Type::Pointer(inner) => (val, inner),
+            // Temporary aggregates have to be stored in memory before they can be accessed
+            _type => {
+                let _type = &*self.arena.alloc(_type);
+                let ptr = Value::Reg(self.alloca(_type));
+                self.emit(Inst::Store {
+                    val,
+                    ptr: ptr.clone(),
+                });
+                (ptr, _type)
+            }
+        }
+    }
+
+    fn lower_call(&mut self, call: &'l CallExpr<'l>, ret_type: Type<'l>) -> Option<VReg> {
+        let args = call.args.iter().map(|arg| self.lower_expr(arg)).collect();
+        let dst = match ret_type {
+            Type::Void => None,
+            _type => Some(self.new_vreg(_type)),
+        };
+        self.emit(Inst::Call {
+            dst,
+            func: call.name,
+            args,
+        });
+        dst
+    }
+
+    fn alloca(&mut self, _type: &'l Type<'l>) -> VReg {
+        let dst = self.new_vreg(Type::Pointer(_type));
+        self.emit(Inst::Alloca { dst, _type: *_type });
+        dst
+    }
+
+    fn load(&mut self, ptr: Value<'l>, _type: Type<'l>) -> Value<'l> {
+        let dst = self.new_vreg(_type);
+        self.emit(Inst::Load { dst, ptr });
+        Value::Reg(dst)
+    }
+
+    fn field_ptr(&mut self, ptr: Value<'l>, name: Ident<'l>, field: &'l IRTypedIdent<'l>) -> Value<'l> {
+        let dst = self.new_vreg(Type::Pointer(&field._type));
+        self.emit(Inst::FieldPtr {
+            dst,
+            ptr,
+            _type: name,
+            field: field.ident,
+        });
+        Value::Reg(dst)
+    }
+
+    fn elem_ptr(&mut self, ptr: Value<'l>, index: Value<'l>, elem_type: &'l Type<'l>) -> Value<'l> {
+        let dst = self.new_vreg(Type::Pointer(elem_type));
+        self.emit(Inst::ElemPtr { dst, ptr, index });
+        Value::Reg(dst)
+    }
+
+    fn lane_ptr(&mut self, ptr: Value<'l>, lane: u32, elem_type: &'l Type<'l>) -> Value<'l> {
+        let index = Value::Const(Literal::Int64(lane as i64), INT64_T);
+        self.elem_ptr(ptr, index, elem_type)
+    }
+
+    /// Stores a vector in a new stack slot so its lanes can be addressed
+    fn spill(&mut self, expr: &'l IRExpr<'l>) -> (Value<'l>, &'l Type<'l>) {
+        let val = self.lower_expr(expr);
+        let _type = &*self.arena.alloc(self.value_type(&val));
+        let ptr = Value::Reg(self.alloca(_type));
+        self.emit(Inst::Store { val, ptr: ptr.clone() });
+        (ptr, _type)
+    }
+
+    fn value_type(&self, val: &Value<'l>) -> Type<'l> {
+        match val {
+            Value::Reg(reg) => self.vregs[reg.0 as usize],
+            Value::Const(_, _type) => *_type,
+            Value::Global(name) => Type::Pointer(self.globals[name]),
+        }
+    }
+
+    fn label(&self, name: Ident<'l>) -> BlockId {
+        *self
+            .labels
+            .get(name)
+            .unwrap_or_else(|| panic!("Could not find label with name {name:?}"))
+    }
+
+    fn new_vreg(&mut self, _type: Type<'l>) -> VReg {
+        self.vregs.push(_type);
+        VReg(self.vregs.len() as u32 - 1)
+    }
+
+    fn new_block(&mut self, label: Option<Ident<'l>>) -> BlockId {
+        self.blocks.push(PartialBlock {
+            label,
+            insts: Vec::new(),
+            terminator: None,
+        });
+        BlockId(self.blocks.len() as u32 - 1)
+    }
+
+    fn cur_block(&mut self) -> &mut PartialBlock<'l> {
+        &mut self.blocks[self.current.0 as usize]
+    }
+
+    fn emit(&mut self, inst: Inst<'l>) {
+        self.cur_block().insts.push(inst);
+    }
+
+    fn terminate(&mut self, terminator: Terminator<'l>) {
+        self.cur_block().terminator = Some(terminator);
+    }
+}
+
+/// Whether variables of this type have to be stored in memory
+fn is_memory(_type: &Type) -> bool {
+    matches!(_type, Type::Ident(_) | Type::Array(..))
+}
+
+fn element_of<'l>(_type: &Type<'l>) -> &'l Type<'l> {
+    match _type {
+        Type::Array(_type, _) | Type::Vector(_type, _) => _type,
+        _type => panic!("Cannot index into {_type}"),
+    }
+}
+
+/// Collects the variables whose address is taken
+fn addr_taken<'l>(stmts: &'l [IRStmt<'l>]) -> HashSet<Ident<'l>> {
+    #[derive(Default)]
+    struct AddrTaken<'l>(HashSet<Ident<'l>>);
+
+    impl<'l> Visitor<'l> for AddrTaken<'l> {
+        fn visit_addr_of(&mut self, addr_of: &'l AddrOfExpr<'l>) {
+            if let IRExpr::Ident(ident) = *addr_of.val {
+                self.0.insert(ident);
+            }
+            walk_addr_of(self, addr_of);
+        }
+    }
+
+    let mut vars = AddrTaken::default();
+    stmts.iter().for_each(|stmt| vars.visit_stmt(stmt));
+    vars.0
+}
@@ -0,0 +1,208 @@
+//! The Citadel low-level representation (CLIR)
+//!
+//! CLIR is a three-address, assembly-like representation of a program.
+//! Every instruction performs a single operation on [values](Value) and writes
+//! its result into a virtual register. Functions consist of basic blocks, each
+//! of which ends with an explicit [Terminator], so control flow never falls through.
+//!
+//! Unlike registers in SSA form, virtual registers can be written more than once,
+//! which is how reassigned variables are represented.
+//!
+//! A CLIR [Module] is generated from an [HIRStream](citadel_frontend::ir::irgen::HIRStream)
+//! using [lower::lower]. The textual format is output-only: it is meant for
+//! debugging and tests, there is no parser that reads it back.
+
+pub mod lower;
+pub mod traits;
+mod tests;
+
+use citadel_frontend::ir::{irgen::TypeTable, CastKind, CmpOperator, Ident, Literal, Operator, Type};
+
+/// Name of the [Function] that holds the entry block. It is not a valid identifier,
+/// so it cannot clash with a function of the program
+pub const ENTRY_NAME: &str = "<entry>";
+
+#[derive(Debug)]
+pub struct Module<'l> {
+    pub globals: Vec<Global<'l>>,
+    pub declarations: Vec<Declaration<'l>>,
+    /// The entry point of the program
+    pub entry: Option<Function<'l>>,
+    pub functions: Vec<Function<'l>>,
+    /// Structs and unions of the program, taken from the high-level IR
+    pub types: &'l TypeTable<'l>,
+}
+
+/// A global variable with a constant initial value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Global<'l> {
+    pub name: Ident<'l>,
+    pub _type: Type<'l>,
+    pub val: Literal<'l>,
+}
+
+/// A function that is defined outside of the module
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration<'l> {
+    pub name: Ident<'l>,
+    pub args: Vec<Type<'l>>,
+    pub ret_type: Type<'l>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function<'l> {
+    pub name: Ident<'l>,
+    /// The arguments are passed in the first virtual registers
+    pub args: Vec<VReg>,
+    pub ret_type: Type<'l>,
+    /// Type of every virtual register, indexed by its number
+    pub vregs: Vec<Type<'l>>,
+    /// The first block is the one that is executed when calling the function
+    pub blocks: Vec<Block<'l>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VReg(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub u32);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block<'l> {
+    pub id: BlockId,
+    /// Name of the label in the high-level IR this block was created from
+    pub label: Option<Ident<'l>>,
+    pub insts: Vec<Inst<'l>>,
+    pub terminator: Terminator<'l>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<'l> {
+    Reg(VReg),
+    Const(Literal<'l>, Type<'l>),
+    /// Address of a global variable
+    Global(Ident<'l>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inst<'l> {
+    Copy {
+        dst: VReg,
+        val: Value<'l>,
+    },
+    Binary {
+        dst: VReg,
+        op: Operator,
+        lhs: Value<'l>,
+        rhs: Value<'l>,
+    },
+    Cmp {
+        dst: VReg,
+        op: CmpOperator,
+        lhs: Value<'l>,
+        rhs: Value<'l>,
+    },
+    Not {
+        dst: VReg,
+        val: Value<'l>,
+    },
+    /// Converts the value to the type of `dst`
+    Cast {
+        dst: VReg,
+        kind: CastKind,
+        val: Value<'l>,
+    },
+    /// Reserves stack memory for a value of `_type`
+    Alloca {
+        dst: VReg,
+        _type: Type<'l>,
+    },
+    /// Reads a value of the type of `dst` from memory
+    Load {
+        dst: VReg,
+        ptr: Value<'l>,
+    },
+    Store {
+        val: Value<'l>,
+        ptr: Value<'l>,
+    },
+    /// Computes a pointer to the field of the struct or union `_type` that `ptr` points to
+    FieldPtr {
+        dst: VReg,
+        ptr: Value<'l>,
+        _type: Ident<'l>,
+        field: Ident<'l>,
+    },
+    /// Computes a pointer to the element of the array that `ptr` points to
+    ElemPtr {
+        dst: VReg,
+        ptr: Value<'l>,
+        index: Value<'l>,
+    },
+    Call {
+        dst: Option<VReg>,
+        func: Ident<'l>,
+        args: Vec<Value<'l>>,
+    },
+}
+
+/// The last instruction of a block that decides which block is executed next
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator<'l> {
+    Jump(BlockId),
+    /// Jumps to `then_block` if the condition is non-zero
+    /// and to `else_block` otherwise
+    Branch {
+        cond: Value<'l>,
+        then_block: BlockId,
+        else_block: BlockId,
+    },
+    Return(Option<Value<'l>>),
+    Exit(Value<'l>),
+    /// The end of the block is never reached
+    Unreachable,
+}
+
+impl Inst<'_> {
+    /// Returns the register the instruction writes to
+    pub fn dst(&self) -> Option<VReg> {
+        match self {
+            Inst::Copy { dst, .. }
+            | Inst::Binary { dst, .. }
+            | Inst::Cmp { dst, .. }
+            | Inst::Not { dst, .. }
+            | Inst::Cast { dst, .. }
+            | Inst::Alloca { dst, .. }
+            | Inst::Load { dst, .. }
+            | Inst::FieldPtr { dst, .. }
+            | Inst::ElemPtr { dst, .. } => Some(*dst),
+            Inst::Call { dst, .. } => *dst,
+            Inst::Store { .. } => None,
+        }
+    }
+}
+
+impl Terminator<'_> {
+    /// Returns the blocks that can be executed after this terminator
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(block) => vec![*block],
+            Terminator::Branch {
+                then_block,
+                else_block,
+                ..
+            } => vec![*then_block, *else_block],
+            Terminator::Return(_) | Terminator::Exit(_) | Terminator::Unreachable => Vec::new(),
+        }
+    }
+}
+
+impl<'l> Function<'l> {
+    pub fn block(&self, id: BlockId) -> &Block<'l> {
+        &self.blocks[id.0 as usize]
+    }
+
+    pub fn type_of(&self, reg: VReg) -> &Type<'l> {
+        &self.vregs[reg.0 as usize]
+    }
+}
@@ -0,0 +1,192 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests {
+    use bumpalo::Bump;
+    use citadel_frontend::ir::{
+        irgen::IRGenerator, BlockStmt, CallExpr, ExitStmt, FuncStmt, IRExpr, IRStmt, IRTypedIdent,
+        Literal, ReturnStmt, INT32_T,
+    };
+    use citadel_irparser::{IRLexer, IRParser};
+
+    use crate::clir::{self, lower};
+
+    #[test]
+    fn test_lower() {
+        let lexer = IRLexer::new(
+            "entry {
+                exit call %sum(l{10:i32})
+            }
+            func @sum($n i32) i32 {
+                ?i i32 = l{0:i32}
+                ?acc i32 = l{0:i32}
+                'loop:
+                br cmp slt %i, %n, 'body, 'end
+                'body:
+                %acc = add %acc, mul %i, l{2:i32}
+                %i = add %i, l{1:i32}
+                jmp 'loop
+                'end:
+                ret %acc
+            }",
+        );
+        let arena = Bump::new();
+        let mut parser = IRParser::new(&lexer, &arena);
+        let ir_stream = parser.parse_program();
+        let module = lower::lower(&ir_stream, &arena);
+        assert_eq!(
+            module.to_string(),
+            "entry {
+bb0:
+    %0 i32 = call @sum(10:i32)
+    exit %0
+}
+
+func @sum(%0 i32) i32 {
+bb0:
+    %1 i32 = copy 0:i32
+    %2 i32 = copy 0:i32
+    jmp bb1
+bb1: # 'loop
+    %3 i1 = cmp slt %1, %0
+    br %3, bb2, bb3
+bb2: # 'body
+    %4 i32 = mul %1, 2:i32
+    %5 i32 = add %2, %4
+    %2 i32 = copy %5
+    %6 i32 = add %1, 1:i32
+    %1 i32 = copy %6
+    jmp bb1
+bb3: # 'end
+    ret %2
+}"
+        );
+    }
+
+    #[test]
+    fn test_lower_memory() {
+        let lexer = IRLexer::new(
+            "struct @Point {
+                $x i32,
+                $y i64,
+            }
+            func @main() i64 {
+                $p Point = struct %Point {l{1:i32}, l{2:i64}}
+                $x i32 = l{5:i32}
+                $ptr *i32 = addr-of %x
+                store getfield %p, x, %ptr
+                ret getfield %p, y
+            }",
+        );
+        let arena = Bump::new();
+        let mut parser = IRParser::new(&lexer, &arena);
+        let ir_stream = parser.parse_program();
+        let module = lower::lower(&ir_stream, &arena);
+        let main = &module.functions[0];
+        assert_eq!(
+            main.to_string(),
+            "func @main() i64 {
+bb0:
+    %0 *Point = alloca Point
+    %1 *Point = alloca Point
+    %2 *i32 = fieldptr %1, Point.x
+    store 1:i32, %2
+    %3 *i64 = fieldptr %1, Point.y
+    store 2:i64, %3
+    %4 Point = load %1
+    store %4, %0
+    %5 *i32 = alloca i32
+    store 5:i32, %5
+    %6 *i32 = copy %5
+    %7 *i32 = fieldptr %0, Point.x
+    %8 i32 = load %7
+    store %8, %6
+    %9 *i64 = fieldptr %0, Point.y
+    %10 i64 = load %9
+    ret %10
+}"
+        );
+    }
+
+    #[test]
+    fn test_lower_vectors() {
+        let lexer = IRLexer::new(
+            "func @main($v <i32; 2>) i32 {
+                ?w <i32; 2> = shuffle %v, %v, [1,2]
+                ret extractelem insertelem %w, l{3:i32}, 0, 1
+            }",
+        );
+        let arena = Bump::new();
+        let mut parser = IRParser::new(&lexer, &arena);
+        let ir_stream = parser.parse_program();
+        let module = lower::lower(&ir_stream, &arena);
+        assert_eq!(
+            module.functions[0].to_string(),
+            "func @main(%0 <i32; 2>) i32 {
+bb0:
+    %1 *<i32; 2> = alloca <i32; 2>
+    store %0, %1
+    %2 *<i32; 2> = alloca <i32; 2>
+    store %0, %2
+    %3 *<i32; 2> = alloca <i32; 2>
+    %4 *i32 = elemptr %1, 1:i64
+    %5 i32 = load %4
+    %6 *i32 = elemptr %3, 0:i64
+    store %5, %6
+    %7 *i32 = elemptr %2, 0:i64
+    %8 i32 = load %7
+    %9 *i32 = elemptr %3, 1:i64
+    store %8, %9
+    %10 <i32; 2> = load %3
+    %11 <i32; 2> = copy %10
+    %12 *<i32; 2> = alloca <i32; 2>
+    store %11, %12
+    %13 *i32 = elemptr %12, 0:i64
+    store 3:i32, %13
+    %14 <i32; 2> = load %12
+    %15 *<i32; 2> = alloca <i32; 2>
+    store %14, %15
+    %16 *i32 = elemptr %15, 1:i64
+    %17 i32 = load %16
+    ret %17
+}"
+        );
+    }
+
+    #[test]
+    fn test_lower_entry_name() {
+        // The irparser treats `entry` as a keyword, so the function is generated directly
+        let mut code_gen = IRGenerator::default();
+        code_gen.gen_ir(IRStmt::Entry(BlockStmt {
+            stmts: vec![IRStmt::Exit(ExitStmt {
+                exit_code: IRExpr::Call(CallExpr {
+                    name: "entry",
+                    args: Vec::new(),
+                    loc: None,
+                }),
+                loc: None,
+            })],
+        }));
+        code_gen.gen_ir(IRStmt::Function(FuncStmt {
+            name: IRTypedIdent {
+                ident: "entry",
+                _type: INT32_T,
+            },
+            args: Vec::new(),
+            block: BlockStmt {
+                stmts: vec![IRStmt::Return(ReturnStmt {
+                    ret_val: IRExpr::Literal(Literal::Int32(0), INT32_T),
+                    loc: None,
+                })],
+            },
+            loc: None,
+        }));
+        let ir_stream = code_gen.stream();
+        let arena = Bump::new();
+        let module = lower::lower(&ir_stream, &arena);
+        assert_eq!(
+            module.entry.as_ref().map(|entry| entry.name),
+            Some(clir::ENTRY_NAME)
+        );
+        assert_eq!(module.functions[0].name, "entry");
+    }
+}
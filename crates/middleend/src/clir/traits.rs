@@ -0,0 +1,163 @@
+//! This file contains the textual format of CLIR
+use std::fmt::Display;
+
+use super::*;
+
+impl Display for Module<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut sections = Vec::new();
+        if !self.globals.is_empty() {
+            sections.push(join(&self.globals, "\n"));
+        }
+        if !self.declarations.is_empty() {
+            sections.push(join(&self.declarations, "\n"));
+        }
+        if let Some(entry) = &self.entry {
+            sections.push(format!("entry {{\n{}}}", FunctionBody(entry)));
+        }
+        for func in &self.functions {
+            sections.push(func.to_string());
+        }
+        write!(f, "{}", sections.join("\n\n"))
+    }
+}
+
+impl Display for Global<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "global @{} {} = {}", self.name, self._type, self.val)
+    }
+}
+
+impl Display for Declaration<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "decl func @{}({}) {}",
+            self.name,
+            join(&self.args, ", "),
+            self.ret_type
+        )
+    }
+}
+
+impl Display for Function<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| format!("{arg} {}", self.type_of(*arg)))
+            .collect::<Vec<String>>()
+            .join(", ");
+        write!(
+            f,
+            "func @{}({}) {} {{\n{}}}",
+            self.name,
+            args,
+            self.ret_type,
+            FunctionBody(self)
+        )
+    }
+}
+
+/// Prints the blocks of a function. The destination registers
+/// of instructions are printed together with their type
+struct FunctionBody<'f, 'l>(&'f Function<'l>);
+
+impl Display for FunctionBody<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for block in &self.0.blocks {
+            write!(f, "{}:", block.id)?;
+            if let Some(label) = block.label {
+                write!(f, " # '{label}")?;
+            }
+            writeln!(f)?;
+            for inst in &block.insts {
+                match inst.dst() {
+                    Some(dst) => writeln!(f, "    {dst} {} = {}", self.0.type_of(dst), Op(inst))?,
+                    None => writeln!(f, "    {}", Op(inst))?,
+                }
+            }
+            writeln!(f, "    {}", block.terminator)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for VReg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+impl Display for BlockId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+impl Display for Value<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Reg(reg) => write!(f, "{reg}"),
+            Value::Const(lit, _type) => write!(f, "{lit}:{_type}"),
+            Value::Global(name) => write!(f, "@{name}"),
+        }
+    }
+}
+
+impl Display for Inst<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.dst() {
+            Some(dst) => write!(f, "{dst} = {}", Op(self)),
+            None => write!(f, "{}", Op(self)),
+        }
+    }
+}
+
+/// The operation of an instruction without its destination
+struct Op<'i, 'l>(&'i Inst<'l>);
+
+impl Display for Op<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Inst::Copy { val, .. } => write!(f, "copy {val}"),
+            Inst::Binary { op, lhs, rhs, .. } => write!(f, "{op} {lhs}, {rhs}"),
+            Inst::Cmp { op, lhs, rhs, .. } => write!(f, "cmp {op} {lhs}, {rhs}"),
+            Inst::Not { val, .. } => write!(f, "not {val}"),
+            Inst::Cast { kind, val, .. } => write!(f, "{kind} {val}"),
+            Inst::Alloca { _type, .. } => write!(f, "alloca {_type}"),
+            Inst::Load { ptr, .. } => write!(f, "load {ptr}"),
+            Inst::Store { val, ptr } => write!(f, "store {val}, {ptr}"),
+            Inst::FieldPtr {
+                ptr, _type, field, ..
+            } => write!(f, "fieldptr {ptr}, {_type}.{field}"),
+            Inst::ElemPtr { ptr, index, .. } => write!(f, "elemptr {ptr}, {index}"),
+            Inst::Call { func, args, .. } => write!(f, "call @{func}({})", join(args, ", ")),
+        }
+    }
+}
+
+impl Display for Terminator<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Terminator::Jump(block) => write!(f, "jmp {block}"),
+            Terminator::Branch {
+                cond,
+                then_block,
+                else_block,
+            } => write!(f, "br {cond}, {then_block}, {else_block}"),
+            Terminator::Return(Some(val)) => write!(f, "ret {val}"),
+            Terminator::Return(None) => write!(f, "ret"),
+            Terminator::Exit(val) => write!(f, "exit {val}"),
+            Terminator::Unreachable => write!(f, "unreachable"),
+        }
+    }
+}
+
+fn join<T: Display>(items: &[T], sep: &str) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<String>>()
+        .join(sep)
+}
@@ -1,4 +1,5 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
     macro_rules! test_optimization {
         ($name:ident,$in:ty,$out:ty) => {
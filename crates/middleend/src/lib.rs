@@ -6,4 +6,6 @@
 //! 
 //! This crate provides an api for writing new optimizers and IR layers
 
-pub mod experimental;
\ No newline at end of file
+pub mod experimental;
+pub mod clir;
+pub mod cfg;
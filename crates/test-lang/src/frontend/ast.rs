@@ -44,13 +44,20 @@ pub enum Operator {
     Sub,
     Div,
     Mul,
-    Equals
+    Rem,
+    Assign,
+    Equals,
+    NotEquals,
+    LessThan,
+    GreaterThan,
+    LessThanEquals,
+    GreaterThanEquals,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Literal<'ast> {
     Ident(Ident<'ast>),
-    String(&'ast str),
+    String(&'ast [u8]),
     Integer(i32),
     Float(f64),
     Boolean(bool),
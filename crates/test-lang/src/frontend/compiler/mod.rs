@@ -1,9 +1,9 @@
 //! The compiler module is responsible for taking the AST and converting it into IR code.
-use std::mem;
+use std::{collections::HashMap, mem};
 
 use bumpalo::Bump;
 use citadel_api::frontend::ir::{
-    self, irgen::{HIRStream, IRGenerator}, ArithOpExpr, CallExpr, ExitStmt, IRExpr, IRStmt, IRTypedIdent, ReturnStmt, VarStmt, FLOAT64_T, INT32_T, INT8_T
+    self, irgen::{HIRStream, IRGenerator}, ArithOpExpr, AssignStmt, BranchStmt, CallExpr, CmpOpExpr, CmpOperator, ExitStmt, IRExpr, IRStmt, IRTypedIdent, JumpStmt, LabelStmt, ReturnStmt, VarStmt, FLOAT64_T, INT1_T, INT32_T, INT8_T
 };
 
 use super::ast::{self, *};
@@ -14,8 +14,11 @@ pub struct Compiler<'c> {
     functions: ast::FunctionTable<'c>,
     label_index: usize,
     global_ctx: Option<CompileCtx<'c>>,
+    /// Types of the variables that are in scope
+    variables: HashMap<&'c str, ir::Type<'c>>,
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CompileCtx<'ctx> {
     FuncRetType(ir::Type<'ctx>),
@@ -55,6 +58,7 @@ impl<'c> Compiler<'c> {
             label_index: Default::default(),
             out: Default::default(),
             global_ctx: Default::default(),
+            variables: Default::default(),
         };
 
         compiler.init_program();
@@ -70,7 +74,9 @@ impl<'c> Compiler<'c> {
                 exit_code: IRExpr::Call(CallExpr {
                     name: "main",
                     args: vec![],
+                    loc: None,
                 }),
+                loc: None,
             })],
         }))
     }
@@ -80,8 +86,8 @@ impl<'c> Compiler<'c> {
             Statement::Let(node) => self.compile_let_stmt(node),
             Statement::Fn(node) => self.compile_fn_stmt(node),
             Statement::Return(node) => self.compile_return_stmt(node),
-            Statement::If(_) => todo!(),
-            Statement::Loop(_) => todo!(),
+            Statement::If(node) => self.compile_if_stmt(node),
+            Statement::Loop(node) => self.compile_loop_stmt(node),
             Statement::Block(_) => todo!(),
             Statement::Expression(node) => {
                 if let Some(call) = self.compile_expr_stmt(node) {
@@ -94,24 +100,32 @@ impl<'c> Compiler<'c> {
     fn compile_let_stmt(&mut self, node: LetStatement<'c>) {
         let name = self.compile_typed_ident(node.name);
         let (val, _) = self.compile_expr(node.val, Some(CompileCtx::VarType(name._type)));
+        self.variables.insert(name.ident, name._type);
         let stmt = IRStmt::Variable(VarStmt {
             val,
-            is_const: true,
+            is_const: false,
             name,
+            loc: None,
         });
         self.out.gen_ir(stmt);
     }
 
     fn compile_fn_stmt(&mut self, node: FnStatement<'c>) {
         self.global_ctx = Some(CompileCtx::FuncRetType(self.compile_type(node.ret_type)));
+        let args = self.compile_typed_idents(node.args);
+        for arg in &args {
+            self.variables.insert(arg.ident, arg._type);
+        }
         let stmt = IRStmt::Function(ir::FuncStmt {
             name: self.compile_typed_ident(TypedIdent {
                 _type: node.ret_type,
-                ident: &node.name,
+                ident: node.name,
             }),
-            args: self.compile_typed_idents(node.args),
+            args,
             block: self.compile_block_stmt(node.block),
+            loc: None,
         });
+        self.variables.clear();
         self.out.gen_ir(stmt);
     }
 
@@ -125,9 +139,61 @@ impl<'c> Compiler<'c> {
         ir::BlockStmt { stmts: block }
     }
 
+    fn compile_if_stmt(&mut self, node: IfStatement<'c>) {
+        let index = self.next_label_index();
+        let then_label = self.compile_label_name("if", index);
+        let end_label = self.compile_label_name("endif", index);
+        let (condition, _) = self.compile_expr(node.condition, None);
+        self.out.gen_ir(IRStmt::Branch(BranchStmt {
+            condition,
+            then_label,
+            else_label: end_label,
+            loc: None,
+        }));
+        self.out.gen_ir(IRStmt::Label(LabelStmt { name: then_label, loc: None }));
+        for stmt in node.block.stmts {
+            self.compile_stmt(stmt);
+        }
+        self.out.gen_ir(IRStmt::Label(LabelStmt { name: end_label, loc: None }));
+    }
+
+    fn compile_loop_stmt(&mut self, node: LoopStatement<'c>) {
+        let index = self.next_label_index();
+        let loop_label = self.compile_label_name("loop", index);
+        let end_label = self.compile_label_name("endloop", index);
+        self.out.gen_ir(IRStmt::Label(LabelStmt { name: loop_label, loc: None }));
+        if let Some(condition) = node.condition {
+            let body_label = self.compile_label_name("loopbody", index);
+            let (condition, _) = self.compile_expr(condition, None);
+            self.out.gen_ir(IRStmt::Branch(BranchStmt {
+                condition,
+                then_label: body_label,
+                else_label: end_label,
+                loc: None,
+            }));
+            self.out.gen_ir(IRStmt::Label(LabelStmt { name: body_label, loc: None }));
+        }
+        for stmt in node.block.stmts {
+            self.compile_stmt(stmt);
+        }
+        self.out.gen_ir(IRStmt::Jump(JumpStmt { label: loop_label, loc: None }));
+        self.out.gen_ir(IRStmt::Label(LabelStmt { name: end_label, loc: None }));
+    }
+
+    fn next_label_index(&mut self) -> usize {
+        let index = self.label_index;
+        self.label_index += 1;
+        index
+    }
+
+    fn compile_label_name(&self, name: &str, index: usize) -> ir::Ident<'c> {
+        self.arena.alloc_str(&format!("{name}{index}"))
+    }
+
     fn compile_return_stmt(&mut self, node: ReturnStatement<'c>) {
         let stmt = IRStmt::Return(ReturnStmt {
             ret_val: self.compile_expr(node.val, self.global_ctx).0,
+            loc: None,
         });
         self.out.gen_ir(stmt);
     }
@@ -135,14 +201,30 @@ impl<'c> Compiler<'c> {
     fn compile_expr_stmt(&mut self, node: Expression<'c>) -> Option<IRStmt<'c>> {
         match node {
             Expression::Call(mut node) => Some(match node.name {
-                "exit" => ir::IRStmt::Exit(ExitStmt { exit_code: self.compile_expr(node.args.remove(0), None).0 }),
+                "exit" => ir::IRStmt::Exit(ExitStmt { exit_code: self.compile_expr(node.args.remove(0), None).0, loc: None }),
                 _ => ir::IRStmt::Call(self.compile_call_expr(node).0),
             }),
+            Expression::Infix(node) if node.operator == Operator::Assign => {
+                Some(self.compile_assign(node))
+            }
             Expression::Infix(_) => None,
             Expression::Literal(_) => None,
         }
     }
 
+    fn compile_assign(&mut self, node: InfixOpExpr<'c>) -> IRStmt<'c> {
+        let name = match node.sides.0 {
+            Expression::Literal(Literal::Ident(ident)) => *ident,
+            expr => panic!("Cannot assign a value to {expr:?}"),
+        };
+        let _type = *self
+            .variables
+            .get(name)
+            .unwrap_or_else(|| panic!("No variable with name: {name}"));
+        let (val, _) = self.compile_expr(node.sides.1.clone(), Some(CompileCtx::VarType(_type)));
+        IRStmt::Assign(AssignStmt { name, val, loc: None })
+    }
+
     fn compile_expr(
         &mut self,
         node: Expression<'c>,
@@ -162,51 +244,84 @@ impl<'c> Compiler<'c> {
     }
 
     fn compile_infix_expr(&mut self, node: InfixOpExpr<'c>) -> IRExpr<'c> {
-        IRExpr::ArithOp(ArithOpExpr {
-            values: (Box::from(self.compile_expr(node.sides.0.clone(), None).0), Box::from(self.compile_expr(node.sides.1.clone(), None).0)),
-            op: Self::compile_op(node.operator),
-        })
+        let values = (
+            Box::from(self.compile_expr(node.sides.0.clone(), None).0),
+            Box::from(self.compile_expr(node.sides.1.clone(), None).0),
+        );
+        match node.operator {
+            Operator::Add | Operator::Sub | Operator::Div | Operator::Mul | Operator::Rem => {
+                IRExpr::ArithOp(ArithOpExpr {
+                    values,
+                    op: Self::compile_op(node.operator),
+                    loc: None,
+                })
+            }
+            Operator::Assign => panic!("An assignment cannot be used as a value"),
+            _ => IRExpr::CmpOp(CmpOpExpr {
+                values,
+                op: Self::compile_cmp_op(node.operator),
+                loc: None,
+            }),
+        }
     }
 
     fn compile_lit_expr(&self, node: Literal<'c>, ctx: Option<CompileCtx<'c>>) -> IRExpr<'c> {
         match node {
-            Literal::Integer(int) => IRExpr::Literal(
-                ir::Literal::Int32(int),
-                ctx.map(|c| c.as_type()).unwrap_or(ir::Type::Ident(INT32_T)),
-            ),
-            Literal::Float(float) => IRExpr::Literal(
-                ir::Literal::Float64(float),
-                ctx.map(|c| c.as_type())
-                    .unwrap_or(ir::Type::Ident(FLOAT64_T)),
-            ),
+            Literal::Integer(int) => {
+                let _type = ctx.map(|c| c.as_type()).unwrap_or(INT32_T);
+                IRExpr::Literal(Self::compile_int_lit(int, _type), _type)
+            }
+            Literal::Float(float) => {
+                let _type = ctx.map(|c| c.as_type()).unwrap_or(FLOAT64_T);
+                let lit = match _type {
+                    ir::Type::Float(ir::FloatType::F32) => ir::Literal::Float32(float as f32),
+                    _ => ir::Literal::Float64(float),
+                };
+                IRExpr::Literal(lit, _type)
+            }
             Literal::String(str) => IRExpr::Literal(
                 ir::Literal::String(str),
                 ctx.map(|c| c.as_type())
-                    .unwrap_or(ir::Type::Array(&ir::Type::Ident(INT8_T), str.len() as u32)),
+                    .unwrap_or(ir::Type::Array(&INT8_T, str.len() as u32)),
             ),
             Literal::Boolean(bool) => {
-                IRExpr::Literal(ir::Literal::Bool(bool), ir::Type::Ident(INT8_T))
+                IRExpr::Literal(ir::Literal::Bool(bool), INT1_T)
             }
             Literal::Char(ch) => {
-                IRExpr::Literal(ir::Literal::Char(ch as u8), ir::Type::Ident(INT8_T))
+                IRExpr::Literal(ir::Literal::Char(ch as u8), INT8_T)
             }
             Literal::Ident(ident) => IRExpr::Ident(ident),
         }
     }
 
+    /// Picks the literal variant that matches the integer type the value is used as
+    fn compile_int_lit(int: i32, _type: ir::Type<'c>) -> ir::Literal<'c> {
+        match _type {
+            ir::Type::Int(ir::IntType::I8) => ir::Literal::Int8(int as i8),
+            ir::Type::Int(ir::IntType::I16) => ir::Literal::Int16(int as i16),
+            ir::Type::Int(ir::IntType::I64) => ir::Literal::Int64(int as i64),
+            ir::Type::Int(ir::IntType::I128) => ir::Literal::Int128(int as i128),
+            ir::Type::Int(ir::IntType::U8) => ir::Literal::UInt8(int as u8),
+            ir::Type::Int(ir::IntType::U16) => ir::Literal::UInt16(int as u16),
+            ir::Type::Int(ir::IntType::U32) => ir::Literal::UInt32(int as u32),
+            ir::Type::Int(ir::IntType::U64) => ir::Literal::UInt64(int as u64),
+            ir::Type::Int(ir::IntType::U128) => ir::Literal::UInt128(int as u128),
+            _ => ir::Literal::Int32(int),
+        }
+    }
+
     fn compile_call_expr(
         &mut self,
         node: CallExpression<'c>,
     ) -> (CallExpr<'c>, Option<CompileCtx<'c>>) {
-        match node.name {
-            "puts" => return self.compile_print_call(node),
-            _ => (),
+        if node.name == "puts" {
+            return self.compile_print_call(node);
         }
 
         let func_info = self
             .functions
             .get(node.name)
-            .expect(format!("No method with name: {}", node.name).as_str())
+            .unwrap_or_else(|| panic!("No method with name: {}", node.name))
             .clone();
         let ctx = Some(CompileCtx::CallRetType(
             self.compile_type(func_info.ret_type),
@@ -214,6 +329,7 @@ impl<'c> Compiler<'c> {
         let expr = ir::CallExpr {
             name: func_info.ir_name,
             args: self.compile_call_args(node.args, func_info.args.as_slice()),
+            loc: None,
         };
         (expr, ctx)
     }
@@ -233,7 +349,7 @@ impl<'c> Compiler<'c> {
 
     fn compile_type(&self, _type: Type<'c>) -> ir::Type<'c> {
         match _type {
-            Type::Ident(id) => ir::Type::Ident(id),
+            Type::Ident(id) => ir::Type::from_ident(id),
             Type::Array(_type, len) => {
                 let _type = self.arena.alloc(self.compile_type(*_type));
                 ir::Type::Array(_type, len as u32)
@@ -273,12 +389,13 @@ impl<'c> Compiler<'c> {
             args: self.compile_call_args(
                 node.args,
                 &[TypedIdent {
-                    _type: Type::Array(&Type::Ident(INT8_T), msg_len),
+                    _type: Type::Array(&Type::Ident("i8"), msg_len),
                     ident: "msg",
                 }],
             ),
+            loc: None,
         };
-        (expr, Some(CompileCtx::CallRetType(ir::Type::Ident("void"))))
+        (expr, Some(CompileCtx::CallRetType(ir::Type::Void)))
     }
 
     fn compile_exit_expr(
@@ -288,13 +405,13 @@ impl<'c> Compiler<'c> {
         let exit_code = self
             .compile_expr(
                 node.args.remove(0),
-                Some(CompileCtx::VarType(ir::Type::Ident(INT32_T))),
+                Some(CompileCtx::VarType(INT32_T)),
             )
             .0;
-        self.out.gen_ir(IRStmt::Exit(ExitStmt { exit_code }));
+        self.out.gen_ir(IRStmt::Exit(ExitStmt { exit_code, loc: None }));
         (
-            IRExpr::Literal(ir::Literal::Int32(-1), ir::Type::Ident(INT32_T)),
-            Some(CompileCtx::CallRetType(ir::Type::Ident(INT32_T))),
+            IRExpr::Literal(ir::Literal::Int32(-1), INT32_T),
+            Some(CompileCtx::CallRetType(INT32_T)),
         )
     }
 
@@ -302,9 +419,22 @@ impl<'c> Compiler<'c> {
         match op {
             Operator::Add => ir::Operator::Add,
             Operator::Sub => ir::Operator::Sub,
-            Operator::Div => ir::Operator::Div,
+            Operator::Div => ir::Operator::SDiv,
             Operator::Mul => ir::Operator::Mul,
-            Operator::Equals => todo!(),
+            Operator::Rem => ir::Operator::SRem,
+            op => unreachable!("{op:?} is not an arithmetic operator"),
+        }
+    }
+
+    fn compile_cmp_op(op: Operator) -> CmpOperator {
+        match op {
+            Operator::Equals => CmpOperator::Eq,
+            Operator::NotEquals => CmpOperator::Ne,
+            Operator::LessThan => CmpOperator::SLt,
+            Operator::GreaterThan => CmpOperator::SGt,
+            Operator::LessThanEquals => CmpOperator::SLe,
+            Operator::GreaterThanEquals => CmpOperator::SGe,
+            op => unreachable!("{op:?} is not a comparison operator"),
         }
     }
 }
@@ -3,9 +3,10 @@
 use std::collections::HashMap;
 
 use bumpalo::Bump;
+use citadel_api::frontend::util::escape;
 
 use crate::{
-    expect_tok,
+    expect_tok, parser_error,
     frontend::ast::{self, FunctionInfo, LoopStatement, Type},
 };
 
@@ -27,6 +28,7 @@ pub struct Parser<'p> {
 }
 
 #[repr(u8)]
+#[allow(dead_code)]
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub enum Precedence {
     Lowest,
@@ -74,10 +76,18 @@ impl<'p> Parser<'p> {
             Token::Loop => self.parse_loop_stmt(),
             Token::Return => self.parse_return_stmt(),
             Token::LCurly => Some(Statement::Block(self.parse_block_stmt(Token::RCurly))),
-            _ => Some(Statement::Expression(self.parse_expr(Precedence::Lowest)?)),
+            _ => self.parse_expr_stmt(),
         }
     }
 
+    fn parse_expr_stmt(&mut self) -> Option<Statement<'p>> {
+        let expr = self.parse_expr(Precedence::Lowest)?;
+        if let Some(Token::Semicolon) = self.peek_tok() {
+            self.next_tok();
+        }
+        Some(Statement::Expression(expr))
+    }
+
     fn parse_expr(&mut self, prec: Precedence) -> Option<Expression<'p>> {
         let prefix = self.parse_prefix();
 
@@ -93,9 +103,18 @@ impl<'p> Parser<'p> {
 
     fn parse_infix(&mut self, left: Expression<'p>) -> Option<Expression<'p>> {
         match self.cur_tok()? {
-            Token::Equals | Token::Plus | Token::Minus | Token::Multiply | Token::Divide => {
-                self.parse_infix_expr(left)
-            }
+            Token::Equals
+            | Token::NotEquals
+            | Token::LessThan
+            | Token::GreaterThan
+            | Token::LessThanEquals
+            | Token::GreaterThanEquals
+            | Token::Plus
+            | Token::Minus
+            | Token::Multiply
+            | Token::Divide
+            | Token::Modulo
+            | Token::Assign => self.parse_infix_expr(left),
             Token::LParent => Some(Expression::Call(self.parse_call_expr(left)?)),
             // Token::LSquare => self.parse_index_expr(left),
             _ => panic!("Invalid for parsing an infix expr: {:#?}", left),
@@ -108,11 +127,25 @@ impl<'p> Parser<'p> {
             Token::LitFloat(float) => {
                 Some(Expression::Literal(Literal::Float(float.parse().unwrap())))
             }
-            Token::LitString(string) => Some(Expression::Literal(Literal::String(string))),
-            Token::LitChar(ch) => Some(Expression::Literal(Literal::Char(ch.parse().unwrap()))),
-            //Token::LitBool(boolean) => Some(Expression::Literal(Literal::Boolean(
-            //    boolean.parse().unwrap(),
-            //))),
+            Token::LitString(string) => match escape::unescape_str(string) {
+                Ok(string) => Some(Expression::Literal(Literal::String(
+                    self.arena.alloc_slice_copy(&string),
+                ))),
+                Err(err) => {
+                    parser_error!("{err}");
+                    None
+                }
+            },
+            Token::LitChar(ch) => match escape::unescape_char(ch) {
+                Ok(ch) => Some(Expression::Literal(Literal::Char(ch as char))),
+                Err(err) => {
+                    parser_error!("{err}");
+                    None
+                }
+            },
+            Token::LitBool(boolean) => Some(Expression::Literal(Literal::Boolean(
+                boolean.parse().unwrap(),
+            ))),
             Token::Ident(ident) => Some(Expression::Literal(Literal::Ident(ident))),
             _ => panic!("No prefix parse found for: {:?}", self.cur_tok()),
         }
@@ -225,8 +258,6 @@ impl<'p> Parser<'p> {
         self.next_tok();
         let block = self.parse_block_stmt(Token::RCurly);
 
-        self.next_tok();
-
         Some(Statement::If(IfStatement { condition, block }))
     }
 
@@ -237,7 +268,6 @@ impl<'p> Parser<'p> {
         self.next_tok();
         let block = self.parse_block_stmt(Token::RCurly);
 
-        self.next_tok();
         Some(Statement::Loop(LoopStatement {
             condition: None,
             block,
@@ -375,8 +405,14 @@ impl<'p> Parser<'p> {
             Token::Minus => Some(Operator::Sub),
             Token::Divide => Some(Operator::Div),
             Token::Multiply => Some(Operator::Mul),
-            Token::Assign => todo!(),
+            Token::Modulo => Some(Operator::Rem),
+            Token::Assign => Some(Operator::Assign),
             Token::Equals => Some(Operator::Equals),
+            Token::NotEquals => Some(Operator::NotEquals),
+            Token::LessThan => Some(Operator::LessThan),
+            Token::GreaterThan => Some(Operator::GreaterThan),
+            Token::LessThanEquals => Some(Operator::LessThanEquals),
+            Token::GreaterThanEquals => Some(Operator::GreaterThanEquals),
             tok => panic!("Cannot convert {tok:?} to operator"),
         }
     }
@@ -389,8 +425,10 @@ impl<'p> Parser<'p> {
         match token {
             Token::Assign => Precedence::Assign,
             Token::Plus | Token::Minus => Precedence::Sum,
-            Token::Multiply | Token::Divide => Precedence::Prod,
-            Token::Equals => Precedence::Equals,
+            Token::Multiply | Token::Divide | Token::Modulo => Precedence::Prod,
+            Token::Equals | Token::NotEquals => Precedence::Equals,
+            Token::LessThan | Token::GreaterThan => Precedence::LG,
+            Token::LessThanEquals | Token::GreaterThanEquals => Precedence::LGEq,
             Token::LParent => Precedence::Call,
             _ => Precedence::Lowest,
         }
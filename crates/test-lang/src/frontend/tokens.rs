@@ -26,8 +26,10 @@ pub enum Token<'tok> {
     LitInt(&'tok str),
     #[regex("-?[0-9]+\\.[0-9]+")]
     LitFloat(&'tok str),
-    #[regex(r#"'[^\\']'"#)]
+    #[regex(r#"'(?:[^\\']|\\[^x]|\\x[0-9a-fA-F]{2})'"#)]
     LitChar(&'tok str),
+    #[regex("true|false")]
+    LitBool(&'tok str),
     #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*")]
     Ident(&'tok str),
 
@@ -39,12 +41,24 @@ pub enum Token<'tok> {
     Divide,
     #[token("*")]
     Multiply,
+    #[token("%")]
+    Modulo,
     #[token("=")]
     Assign,
     #[token(";")]
     Semicolon,
     #[token("==")]
     Equals,
+    #[token("!=")]
+    NotEquals,
+    #[token("<")]
+    LessThan,
+    #[token(">")]
+    GreaterThan,
+    #[token("<=")]
+    LessThanEquals,
+    #[token(">=")]
+    GreaterThanEquals,
     #[token(":")]
     Colon,
     #[token(",")]
@@ -1,6 +1,12 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
-    use crate::{compile_asm, compile_chir};
+    use bumpalo::Bump;
+
+    use crate::{
+        compile_asm, compile_chir,
+        frontend::{lexer::Lexer, parser::Parser},
+    };
 
     #[test]
     fn test_compiler() {
@@ -19,4 +25,12 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn test_invalid_escape() {
+        // The invalid escape is reported as a parser error instead of panicking
+        let lexer = Lexer::new(r#"puts("\q")"#);
+        let arena = Bump::new();
+        assert!(Parser::new(&lexer, &arena).parse_program().is_empty());
+    }
 }